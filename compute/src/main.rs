@@ -16,9 +16,14 @@ use std::io::Write;
 use std::time::Instant;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use hmac_sha256::HMAC;
+use cc_core::{FailureMode, MAX_TARPIT_DELAY_MS};
 
 mod rules;
-use rules::{GraphInterpreter, GraphResult, HeaderMod, WafLog, load_graph_from_store, send_to_backend, BackendNodeData};
+use rules::{
+    GraphInterpreter, GraphResult, HeaderMod, WafLog, DefaultBackend, load_payload_from_store,
+    resolve_response_bodies, send_to_backend, BackendAttempt, BackendNodeData, TarpitThen, challenge_response,
+    has_clearance, header_pair, plan_attempts, verify_response, CHALLENGE_PATH,
+};
 
 /// Engine version - update this on each release
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -33,7 +38,7 @@ const ENGINE_NAME: &str = "Configure Compute";
 /// 4. Apply rule actions (block/route/allow)
 /// 5. Log security events
 #[fastly::main]
-fn main(req: Request) -> Result<Response, Error> {
+fn main(mut req: Request) -> Result<Response, Error> {
     // Handle CORS preflight for system endpoints
     if req.get_method() == "OPTIONS" && (req.get_path() == "/_version" || req.get_path() == "/_health") {
         return Ok(Response::from_status(StatusCode::NO_CONTENT)
@@ -99,31 +104,65 @@ fn main(req: Request) -> Result<Response, Error> {
     // Initialize log entry
     let mut log_entry = WafLog::new(&req, start_time);
 
-    // Load graph from config store - fail open if loading fails
+    // Load graph from config store. The default backend and failure policy
+    // come from the payload, so they are known even if the graph isn't; if
+    // the payload itself can't be read, fail open.
     let store = ConfigStore::open("security_rules");
-    let graph = match load_graph_from_store(&store, &service_id) {
+    let payload = load_payload_from_store(&store, &service_id);
+    let (default_backend, failure_policy, challenge, body_inspection) = payload
+        .as_ref()
+        .map(|p| (p.default_backend.clone(), p.failure_policy, p.challenge.clone(), p.body_inspection))
+        .unwrap_or_default();
+
+    // Challenge solutions are checked whatever the graph says: the graph
+    // only decides who gets challenged. Without a secret no challenge can
+    // have been issued, so the path belongs to the origin.
+    if req.get_path() == CHALLENGE_PATH && challenge.is_enabled() {
+        return match verify_response(&req, &challenge) {
+            Ok(response) => respond_with_reason(response, &mut logger, log_entry, "challenge:passed"),
+            Err(e) => {
+                println!("Challenge failed: {}", e);
+                log_entry.blocked = true;
+                let response = Response::from_status(StatusCode::FORBIDDEN).with_body_text_plain(&e.to_string());
+                respond_with_reason(response, &mut logger, log_entry, "challenge:failed")
+            }
+        };
+    }
+
+    let graph = payload.and_then(|p| p.graph()).and_then(|mut g| {
+        resolve_response_bodies(&mut g, |key| store.get(key))?;
+        Ok(g)
+    });
+    let graph = match graph {
         Ok(g) => {
             println!("Loaded graph with {} nodes, {} edges", g.nodes.len(), g.edges.len());
-            Some(g)
+            g
         }
         Err(e) => {
-            println!("Failed to load graph (fail-open): {}", e);
-            log_entry.set_final_action("failopen:graph_load_error");
-            None
+            println!("Failed to load graph: {}", e);
+            return handle_failure(req, &mut logger, log_entry, "graph_load_error", failure_policy.load, &default_backend);
         }
     };
 
-    // If graph loading failed, fail open to default backend
-    let graph = match graph {
-        Some(g) => g,
-        None => {
-            return forward_to_default_backend_with_reason(req, &mut logger, log_entry, "failopen:graph_load_error");
+    // Compile and evaluate - a graph that doesn't compile is a load error
+    let interpreter = match GraphInterpreter::new(&graph, failure_policy) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("Failed to compile graph: {}", e);
+            return handle_failure(req, &mut logger, log_entry, "graph_load_error", failure_policy.load, &default_backend);
         }
     };
 
-    // Create interpreter and evaluate
-    let interpreter = GraphInterpreter::new(&graph);
-    let result = interpreter.evaluate(&req);
+    // The body is buffered only for graphs that read body fields
+    let body = interpreter.reads_body().then(|| body_inspection.read(&mut req));
+    if body.as_ref().is_some_and(|b| b.is_truncated()) && failure_policy.body_limit.is_closed() {
+        return fail_closed(&mut logger, log_entry, "body_too_large");
+    }
+    let result = interpreter.evaluate(&req, &log_entry.request_id, has_clearance(&req, &challenge), body);
+    log_entry.events = interpreter.get_events();
+    log_entry.matched_rules = interpreter.get_matched_rules();
+    log_entry.buckets = interpreter.get_buckets();
+    log_entry.inactive_nodes = interpreter.get_inactive_nodes();
 
     // Handle result
     match result {
@@ -148,69 +187,44 @@ fn main(req: Request) -> Result<Response, Error> {
                 backend_data.name, backend_data.host, backend_data.port.unwrap_or(443));
             let action = format!("routed:{}", backend_data.name);
             log_entry.set_final_action(&action);
+            let sticky_cookie = interpreter.get_sticky_cookie();
 
-            // Add edge auth (if configured for this backend) and send to backend
+            // Add edge auth (if configured for this backend) and send to backend.
+            // The client body is streamed through as-is, never read or buffered
+            // here, so uploads reach the origin byte for byte.
             let mut backend_req = req.clone_without_body();
-            if let Err(e) = add_edge_auth(&mut backend_req, &backend_data) {
-                println!("Auth header error: {}", e);
+            let body_sent = req.has_body();
+            if body_sent {
+                backend_req.set_body(req.take_body());
             }
 
-            // Apply header modifications from graph traversal
-            let header_mods = interpreter.get_header_mods();
-            for header_mod in &header_mods {
-                match header_mod {
-                    HeaderMod::Set { name, value } => {
-                        println!("Header mod: set {}={}", name, value);
-                        backend_req.set_header(name, value);
-                    }
-                    HeaderMod::Append { name, value } => {
-                        println!("Header mod: append {}={}", name, value);
-                        backend_req.append_header(name, value);
-                    }
-                    HeaderMod::Remove { name } => {
-                        println!("Header mod: remove {}", name);
-                        backend_req.remove_header(name);
-                    }
-                }
-            }
-            if !header_mods.is_empty() {
-                println!("Applied {} header modification(s)", header_mods.len());
-            }
+            apply_request_mods(&mut backend_req, &interpreter);
 
-            // Apply cache settings from graph traversal
-            let cache_settings = interpreter.get_cache_settings();
-            if cache_settings.pass {
-                println!("Cache: bypass enabled (pass mode)");
-                backend_req.set_pass(true);
-            } else {
-                if let Some(ttl) = cache_settings.ttl {
-                    println!("Cache: TTL = {}s", ttl);
-                    backend_req.set_ttl(ttl as u32);
-                }
-                if let Some(swr) = cache_settings.stale_while_revalidate {
-                    println!("Cache: SWR = {}s", swr);
-                    backend_req.set_stale_while_revalidate(swr as u32);
-                }
-                for key in &cache_settings.surrogate_keys {
-                    println!("Cache: surrogate key = {}", key);
-                    if let Ok(header_val) = HeaderValue::from_str(key) {
-                        backend_req.set_surrogate_key(header_val);
-                    }
-                }
-            }
-
-            match send_to_backend(backend_req, &backend_data) {
-                Ok(mut response) => {
+            match send_with_failover(backend_req, &backend_data, &mut log_entry) {
+                Ok(response) => {
+                    let mut response = run_response_phase(&interpreter, &req, response, &mut log_entry);
                     response.set_header("X-CC-Action", &action);
+                    if let Some((name, member)) = sticky_cookie {
+                        response.append_header(
+                            "Set-Cookie",
+                            format!("{}={}; Path=/; HttpOnly; Secure; SameSite=Lax", name, member),
+                        );
+                    }
                     log_entry.add_response(&response);
                     log_entry.finalize();
                     writeln!(logger, "{}", serde_json::to_string(&log_entry)?)?;
                     Ok(response)
                 }
                 Err(e) => {
-                    // Fail open on backend errors
-                    println!("Backend error (fail-open): {}", e);
-                    forward_to_default_backend_with_reason(req, &mut logger, log_entry, &format!("failopen:backend_error:{}", backend_data.name))
+                    // Fail open to the default backend - unless the body already
+                    // went to the failed backend, as it can't be sent again
+                    println!("Backend error: {}", e);
+                    let reason = format!("backend_error:{}", backend_data.name);
+                    if body_sent && !failure_policy.backend.is_closed() {
+                        let reason = format!("failopen:{}", reason);
+                        return respond_with_reason(default_backend_unavailable(&reason), &mut logger, log_entry, &reason);
+                    }
+                    handle_failure(req, &mut logger, log_entry, &reason, failure_policy.backend, &default_backend)
                 }
             }
         }
@@ -237,8 +251,10 @@ fn main(req: Request) -> Result<Response, Error> {
             let mut response = Response::from_status(
                 StatusCode::from_u16(status_code).unwrap_or(StatusCode::FOUND)
             );
-            response.set_header("Location", &redirect_url);
-            response.set_header("X-CC-Action", &format!("redirect:{}", status_code));
+            if let Some((name, value)) = header_pair("Location", &redirect_url) {
+                response.set_header(name, value);
+            }
+            response.set_header("X-CC-Action", format!("redirect:{}", status_code));
 
             log_entry.add_response(&response);
             log_entry.finalize();
@@ -247,37 +263,345 @@ fn main(req: Request) -> Result<Response, Error> {
             Ok(response)
         }
 
+        GraphResult::Respond { status_code, content_type, headers, body } => {
+            println!("Responding: {} ({})", status_code, content_type);
+            log_entry.blocked = status_code >= 400;
+            let response = synthetic_response(status_code, &content_type, &headers, body);
+            respond_with_reason(response, &mut logger, log_entry, &format!("respond:{}", status_code))
+        }
+
+        GraphResult::Challenge { challenge_type } => {
+            println!("Challenging client ({:?})", challenge_type);
+            log_entry.blocked = true;
+            match challenge_response(&req, &challenge, challenge_type, &log_entry.request_id) {
+                Some(response) => respond_with_reason(response, &mut logger, log_entry, "challenge"),
+                None => {
+                    // Unsigned clearances could be forged; refuse instead
+                    println!("No challenge secret configured - blocking");
+                    let response = Response::from_status(StatusCode::FORBIDDEN).with_body_text_plain("Blocked");
+                    respond_with_reason(response, &mut logger, log_entry, "blocked")
+                }
+            }
+        }
+
+        GraphResult::Tarpit { delay_ms, then } => {
+            // Held and then answered like any other request: the response
+            // doesn't say the client was tarpitted (only the log does)
+            let delay_ms = delay_ms.min(MAX_TARPIT_DELAY_MS);
+            println!("Tarpit: holding request for {}ms", delay_ms);
+            std::thread::sleep(Duration::from_millis(delay_ms as u64));
+
+            match then {
+                TarpitThen::Block { status_code, message } => {
+                    log_entry.blocked = true;
+                    let status = StatusCode::from_u16(status_code).unwrap_or(StatusCode::FORBIDDEN);
+                    let response = Response::from_status(status).with_body_text_plain(&message);
+                    write_log(response, &mut logger, log_entry, "tarpit:blocked")
+                }
+                TarpitThen::Forward => {
+                    let client_req = req.clone_without_body();
+                    apply_request_mods(&mut req, &interpreter);
+                    let response = send_to_default_backend(req, "tarpit:forwarded", &default_backend);
+                    let response = run_response_phase(&interpreter, &client_req, response, &mut log_entry);
+                    write_log(response, &mut logger, log_entry, "tarpit:forwarded")
+                }
+            }
+        }
+
         GraphResult::Allow => {
             println!("Allowed - using default backend");
-            forward_to_default_backend_with_reason(req, &mut logger, log_entry, "allowed")
+            forward_through_response_phase(req, &interpreter, &mut logger, log_entry, "allowed", &default_backend)
         }
 
         GraphResult::NoMatch => {
             println!("No matching path - using default backend");
-            forward_to_default_backend_with_reason(req, &mut logger, log_entry, "nomatch")
+            forward_through_response_phase(req, &interpreter, &mut logger, log_entry, "nomatch", &default_backend)
+        }
+
+        GraphResult::StepLimitExceeded => {
+            println!("Graph evaluation exceeded the step limit (fail-open)");
+            forward_to_default_backend_with_reason(req, &mut logger, log_entry, "failopen:step_limit", &default_backend)
+        }
+
+        GraphResult::FailClosed { reason } => {
+            println!("Graph evaluation failed: {}", reason);
+            fail_closed(&mut logger, log_entry, &reason)
         }
     }
 }
 
-/// Return an error response when no backend is configured.
+/// Handle a failure according to the graph's failure policy: fail open to
+/// the default backend (`failopen:{reason}`), or refuse the request.
+fn handle_failure(
+    req: Request,
+    logger: &mut Endpoint,
+    log_entry: WafLog,
+    reason: &str,
+    mode: FailureMode,
+    default_backend: &DefaultBackend,
+) -> Result<Response, Error> {
+    if mode.is_closed() {
+        return fail_closed(logger, log_entry, reason);
+    }
+    forward_to_default_backend_with_reason(req, logger, log_entry, &format!("failopen:{}", reason), default_backend)
+}
+
+/// Refuse a request after a failure, tagged `failclosed:{reason}`.
+fn fail_closed(logger: &mut Endpoint, mut log_entry: WafLog, reason: &str) -> Result<Response, Error> {
+    println!("Failing closed (reason: {})", reason);
+    log_entry.blocked = true;
+
+    let body = serde_json::json!({
+        "error": "fail_closed",
+        "message": "The request could not be checked safely and was refused.",
+        "reason": reason,
+        "engine": "Configure Compute",
+    });
+    let response = Response::from_status(StatusCode::SERVICE_UNAVAILABLE)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_body(body.to_string());
+
+    respond_with_reason(response, logger, log_entry, &format!("failclosed:{}", reason))
+}
+
+/// Forward a request the graph didn't route to the default backend.
 ///
 /// This is called when:
-/// - `allowed` - Graph action node set to allow but no backend configured
-/// - `nomatch` - No matching path in graph and no default backend
-/// - `failopen:*` - Error occurred during graph evaluation
+/// - `allowed` - Graph action node set to allow
+/// - `nomatch` - No matching path in graph
+/// - `failopen:*` - Error occurred loading or evaluating the graph
 ///
-/// Returns a 503 Service Unavailable with a clear error message.
+/// The reason is kept in `X-CC-Action` and the log. If the default backend
+/// can't be reached, returns a 503 Service Unavailable instead.
 fn forward_to_default_backend_with_reason(
-    _req: Request,
+    req: Request,
+    logger: &mut Endpoint,
+    log_entry: WafLog,
+    reason: &str,
+    default_backend: &DefaultBackend,
+) -> Result<Response, Error> {
+    let response = send_to_default_backend(req, reason, default_backend);
+    respond_with_reason(response, logger, log_entry, reason)
+}
+
+/// Send a request to a backend node's origin, retrying and failing over
+/// as [`plan_attempts`] plans from its retry policy and fallback. Every
+/// attempt is logged when either is configured.
+///
+/// A request with a body goes out once: its body is streamed to the first
+/// attempt. The result is the last attempt's: a failure status is returned
+/// as the response, a connection error as `Err`.
+fn send_with_failover(
+    req: Request,
+    backend: &BackendNodeData,
+    log_entry: &mut WafLog,
+) -> Result<Response, String> {
+    let policy = backend.retry.clone().unwrap_or_default();
+    let log_attempts = backend.retry.is_some() || backend.fallback.is_some();
+    let backends = plan_attempts(backend, req.get_method_str(), req.has_body());
+
+    let mut req = Some(req);
+    let mut result = Err("No backend attempted".to_string());
+    for (i, target) in backends.iter().enumerate() {
+        let last = i + 1 == backends.len();
+        // Keep a copy for the next attempt; only bodyless requests get one
+        let mut attempt_req = match req.take() {
+            Some(r) if last => r,
+            Some(r) => {
+                let attempt = r.clone_without_body();
+                req = Some(r);
+                attempt
+            }
+            None => break,
+        };
+        if let Err(e) = add_edge_auth(&mut attempt_req, target) {
+            println!("Auth header error: {}", e);
+        }
+
+        result = send_to_backend(attempt_req, target);
+        let status = result.as_ref().ok().map(|r| r.get_status().as_u16());
+        if log_attempts {
+            log_entry.attempts.push(BackendAttempt {
+                backend: target.name.clone(),
+                status,
+                error: result.as_ref().err().cloned(),
+            });
+        }
+        if !policy.is_failure(status) {
+            break;
+        }
+        if !last {
+            println!("Backend {} failed (attempt {}), trying {}", target.name, i + 1, backends[i + 1].name);
+        }
+    }
+    result
+}
+
+/// Apply the header modifications and cache settings collected during graph
+/// traversal to a request about to be forwarded, whichever backend it goes to.
+fn apply_request_mods(req: &mut Request, interpreter: &GraphInterpreter) {
+    // Apply header modifications from graph traversal
+    let header_mods = interpreter.get_header_mods();
+    for header_mod in &header_mods {
+        match header_mod {
+            HeaderMod::Set { name, value } => {
+                println!("Header mod: set {}={}", name, value);
+                if let Some((name, value)) = header_pair(name, value) {
+                    req.set_header(name, value);
+                }
+            }
+            HeaderMod::Append { name, value } => {
+                println!("Header mod: append {}={}", name, value);
+                if let Some((name, value)) = header_pair(name, value) {
+                    req.append_header(name, value);
+                }
+            }
+            HeaderMod::Remove { name } => {
+                println!("Header mod: remove {}", name);
+                req.remove_header(name);
+            }
+        }
+    }
+    if !header_mods.is_empty() {
+        println!("Applied {} header modification(s)", header_mods.len());
+    }
+
+    // Apply cache settings from graph traversal
+    let cache_settings = interpreter.get_cache_settings();
+    if cache_settings.pass {
+        println!("Cache: bypass enabled (pass mode)");
+        req.set_pass(true);
+    } else {
+        if let Some(ttl) = cache_settings.ttl {
+            println!("Cache: TTL = {}s", ttl);
+            req.set_ttl(ttl as u32);
+        }
+        if let Some(swr) = cache_settings.stale_while_revalidate {
+            println!("Cache: SWR = {}s", swr);
+            req.set_stale_while_revalidate(swr as u32);
+        }
+        for key in &cache_settings.surrogate_keys {
+            println!("Cache: surrogate key = {}", key);
+            if let Ok(header_val) = HeaderValue::from_str(key) {
+                req.set_surrogate_key(header_val);
+            }
+        }
+    }
+}
+
+/// Forward a request the graph allowed or didn't match to the default
+/// backend, running the graph's response phase on the response.
+fn forward_through_response_phase(
+    mut req: Request,
+    interpreter: &GraphInterpreter,
     logger: &mut Endpoint,
     mut log_entry: WafLog,
     reason: &str,
+    default_backend: &DefaultBackend,
 ) -> Result<Response, Error> {
-    println!("No backend configured (reason: {})", reason);
+    let client_req = req.clone_without_body();
+    apply_request_mods(&mut req, interpreter);
+    let response = send_to_default_backend(req, reason, default_backend);
+    let response = run_response_phase(interpreter, &client_req, response, &mut log_entry);
+    respond_with_reason(response, logger, log_entry, reason)
+}
+
+/// Run the graph's response phase, if it has one, on a backend response.
+///
+/// The response phase's header mods are applied to the response, which a
+/// respond or block action replaces first. Its events are added to the log.
+fn run_response_phase(
+    interpreter: &GraphInterpreter,
+    req: &Request,
+    response: Response,
+    log_entry: &mut WafLog,
+) -> Response {
+    if !interpreter.has_response_phase() {
+        return response;
+    }
+
+    let result = interpreter.evaluate_response(req, &response, &log_entry.request_id);
+    log_entry.events.extend(interpreter.get_events());
+
+    let mut response = match result {
+        GraphResult::Respond { status_code, content_type, headers, body } => {
+            println!("Response phase: replacing response with {} ({})", status_code, content_type);
+            synthetic_response(status_code, &content_type, &headers, body)
+        }
+        GraphResult::Block { status_code, message } => {
+            println!("Response phase: blocking response with {}", status_code);
+            log_entry.blocked = true;
+            Response::from_status(StatusCode::from_u16(status_code).unwrap_or(StatusCode::FORBIDDEN))
+                .with_body_text_plain(&message)
+        }
+        // Anything else keeps the origin's response
+        _ => response,
+    };
 
+    for header_mod in &interpreter.get_header_mods() {
+        match header_mod {
+            HeaderMod::Set { name, value } => {
+                println!("Response header mod: set {}={}", name, value);
+                if let Some((name, value)) = header_pair(name, value) {
+                    response.set_header(name, value);
+                }
+            }
+            HeaderMod::Append { name, value } => {
+                println!("Response header mod: append {}={}", name, value);
+                if let Some((name, value)) = header_pair(name, value) {
+                    response.append_header(name, value);
+                }
+            }
+            HeaderMod::Remove { name } => {
+                println!("Response header mod: remove {}", name);
+                response.remove_header(name);
+            }
+        }
+    }
+    response
+}
+
+/// Response of a respond node.
+fn synthetic_response(status_code: u16, content_type: &str, headers: &[(String, String)], body: String) -> Response {
+    let mut response = Response::from_status(StatusCode::from_u16(status_code).unwrap_or(StatusCode::OK))
+        .with_body(body);
+    let headers = headers.iter().map(|(name, value)| (name.as_str(), value.as_str()));
+    for (name, value) in std::iter::once(("Content-Type", content_type)).chain(headers) {
+        if let Some((name, value)) = header_pair(name, value) {
+            response.set_header(name, value);
+        }
+    }
+    response
+}
+
+/// Send a request to the default backend, or a 503 if it can't be reached.
+fn send_to_default_backend(mut req: Request, reason: &str, default_backend: &DefaultBackend) -> Response {
+    let result = match default_backend {
+        DefaultBackend::Named(name) => {
+            println!("Forwarding to default backend {} (reason: {})", name, reason);
+            req.send(name.as_str())
+                .map_err(|e| format!("Backend request failed: {}", e))
+        }
+        DefaultBackend::Dynamic(backend_data) => {
+            println!("Forwarding to default backend {} (reason: {})", backend_data.name, reason);
+            if let Err(e) = add_edge_auth(&mut req, backend_data) {
+                println!("Auth header error: {}", e);
+            }
+            send_to_backend(req, backend_data)
+        }
+    };
+
+    result.unwrap_or_else(|e| {
+        println!("Default backend error: {}", e);
+        default_backend_unavailable(reason)
+    })
+}
+
+/// 503 response for when there is no backend to forward to.
+fn default_backend_unavailable(reason: &str) -> Response {
     let error_message = match reason {
-        "allowed" => "No backend configured. Add a Backend node to your graph to route traffic.",
-        "nomatch" => "No matching rule in graph. Ensure your graph has a complete path from Request to Backend.",
+        "allowed" => "Default backend unavailable. Add a Backend node to your graph to route traffic.",
+        "nomatch" => "No matching rule in graph and the default backend is unavailable.",
         r if r.starts_with("failopen:") => "Graph evaluation error. Check your graph configuration.",
         _ => "Backend not configured.",
     };
@@ -289,12 +613,29 @@ fn forward_to_default_backend_with_reason(
         "engine": "Configure Compute",
     });
 
-    let mut response = Response::from_status(StatusCode::SERVICE_UNAVAILABLE)
+    Response::from_status(StatusCode::SERVICE_UNAVAILABLE)
         .with_content_type(fastly::mime::APPLICATION_JSON)
-        .with_body(body.to_string());
+        .with_body(body.to_string())
+}
 
+/// Tag a response with the action taken and write the log entry.
+fn respond_with_reason(
+    mut response: Response,
+    logger: &mut Endpoint,
+    log_entry: WafLog,
+    reason: &str,
+) -> Result<Response, Error> {
     response.set_header("X-CC-Action", reason);
+    write_log(response, logger, log_entry, reason)
+}
 
+/// Write the log entry for a response, without tagging the response.
+fn write_log(
+    response: Response,
+    logger: &mut Endpoint,
+    mut log_entry: WafLog,
+    reason: &str,
+) -> Result<Response, Error> {
     log_entry.add_response(&response);
     log_entry.set_final_action(reason);
     log_entry.finalize();
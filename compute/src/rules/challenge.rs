@@ -0,0 +1,385 @@
+//! Browser challenges for challenge action nodes.
+//!
+//! A challenged request gets an interstitial page whose script solves a
+//! small proof-of-work over a signed token and sends the answer to
+//! [`CHALLENGE_PATH`]. A correct answer earns an HMAC-signed clearance
+//! cookie, bound to the client IP; requests carrying a valid clearance
+//! continue past challenge nodes instead of being challenged again.
+//!
+//! The non-interactive JS challenge is the same page at the lowest
+//! difficulty: running the script is the test. Interactive and captcha
+//! challenges aren't implemented and get the JS challenge.
+
+use cc_core::{cookie_values, query_pairs, ChallengeType};
+use fastly::http::StatusCode;
+use fastly::{Request, Response};
+use hmac_sha256::{Hash, HMAC};
+use serde::Deserialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Path the challenge page submits its solution to.
+pub const CHALLENGE_PATH: &str = "/_cc/challenge";
+
+/// Cookie holding the clearance.
+pub const CLEARANCE_COOKIE: &str = "cc_clearance";
+
+/// How long a challenge token can be solved for.
+const TOKEN_TTL_SECONDS: u64 = 300;
+
+/// Difficulty of the non-interactive JS challenge.
+const JS_DIFFICULTY: u8 = 1;
+
+/// Upper bound on the configured difficulty; every extra digit is 16x the work.
+const MAX_DIFFICULTY: u8 = 6;
+
+/// Challenge settings deployed with the graph.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChallengeConfig {
+    /// Key for signing tokens and clearances. Without one, challenge
+    /// nodes block the request instead.
+    pub secret: Option<String>,
+    /// How long a clearance is valid
+    pub clearance_ttl_seconds: u64,
+    /// Leading zero hex digits a proof-of-work solution needs
+    pub difficulty: u8,
+}
+
+impl Default for ChallengeConfig {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            clearance_ttl_seconds: 3600,
+            difficulty: 4,
+        }
+    }
+}
+
+impl ChallengeConfig {
+    /// Whether challenges can be issued: a secret is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.secret().is_some()
+    }
+
+    fn secret(&self) -> Option<&str> {
+        self.secret.as_deref().filter(|s| !s.is_empty())
+    }
+
+    fn difficulty_for(&self, challenge_type: ChallengeType) -> u8 {
+        match challenge_type {
+            ChallengeType::ProofOfWork => self.difficulty.clamp(JS_DIFFICULTY, MAX_DIFFICULTY),
+            _ => JS_DIFFICULTY,
+        }
+    }
+}
+
+/// Why a challenge solution was refused.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ChallengeError {
+    #[error("Challenge secret not configured")]
+    NotConfigured,
+
+    #[error("Malformed challenge token")]
+    Malformed,
+
+    #[error("Challenge token signature mismatch")]
+    BadSignature,
+
+    #[error("Challenge token expired")]
+    Expired,
+
+    #[error("Challenge solution incorrect")]
+    Unsolved,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Request handling
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Whether the request carries a valid clearance cookie.
+pub fn has_clearance(req: &Request, config: &ChallengeConfig) -> bool {
+    let Some(secret) = config.secret() else {
+        return false;
+    };
+    // HTTP/2 clients may split cookies over several headers
+    let cookies = req.get_header_all_str_lossy("cookie").join("; ");
+    has_valid_clearance(&cookies, &client_ip(req), secret, now())
+}
+
+/// Whether any clearance cookie in a `Cookie` header is valid for `ip`.
+fn has_valid_clearance(cookies: &str, ip: &str, secret: &str, now: u64) -> bool {
+    cookie_values(cookies, CLEARANCE_COOKIE)
+        .iter()
+        .any(|clearance| verify_clearance(clearance, ip, secret, now))
+}
+
+/// The interstitial page for a challenged request, or `None` when no
+/// secret is configured.
+pub fn challenge_response(
+    req: &Request,
+    config: &ChallengeConfig,
+    challenge_type: ChallengeType,
+    request_id: &str,
+) -> Option<Response> {
+    let secret = config.secret()?;
+    let difficulty = config.difficulty_for(challenge_type);
+    let token = issue_token(&client_ip(req), secret, now(), difficulty, request_id);
+
+    Some(
+        Response::from_status(StatusCode::FORBIDDEN)
+            .with_content_type(fastly::mime::TEXT_HTML_UTF_8)
+            .with_header("Cache-Control", "no-store")
+            .with_body(interstitial(&token, difficulty)),
+    )
+}
+
+/// Check a solution sent to [`CHALLENGE_PATH`]. On success, redirects back
+/// to the challenged page with a clearance cookie.
+pub fn verify_response(req: &Request, config: &ChallengeConfig) -> Result<Response, ChallengeError> {
+    let secret = config.secret().ok_or(ChallengeError::NotConfigured)?;
+    let token = req.get_query_parameter("token").ok_or(ChallengeError::Malformed)?;
+    let solution = req.get_query_parameter("solution").ok_or(ChallengeError::Malformed)?;
+    let now = now();
+    let ip = client_ip(req);
+
+    verify_solution(token, solution, &ip, secret, now)?;
+
+    let return_path = return_path(req.get_query_str().unwrap_or_default());
+    let cookie = format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; Secure; SameSite=Lax",
+        CLEARANCE_COOKIE,
+        issue_clearance(&ip, secret, now, config.clearance_ttl_seconds),
+        config.clearance_ttl_seconds
+    );
+    Ok(Response::from_status(StatusCode::FOUND)
+        .with_header("Location", return_path)
+        .with_header("Set-Cookie", cookie)
+        .with_header("Cache-Control", "no-store"))
+}
+
+fn client_ip(req: &Request) -> String {
+    req.get_client_ip_addr().map(|ip| ip.to_string()).unwrap_or_default()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Tokens and clearances
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Token to solve: `issued.difficulty.nonce.signature`
+fn issue_token(ip: &str, secret: &str, now: u64, difficulty: u8, nonce: &str) -> String {
+    let signature = sign(secret, &format!("challenge:{}:{}:{}:{}", now, difficulty, nonce, ip));
+    format!("{}.{}.{}.{}", now, difficulty, nonce, signature)
+}
+
+fn verify_solution(token: &str, solution: &str, ip: &str, secret: &str, now: u64) -> Result<(), ChallengeError> {
+    let parts: Vec<&str> = token.split('.').collect();
+    let [issued, difficulty, nonce, signature] = parts[..] else {
+        return Err(ChallengeError::Malformed);
+    };
+    let issued: u64 = issued.parse().map_err(|_| ChallengeError::Malformed)?;
+    let difficulty: u8 = difficulty.parse().map_err(|_| ChallengeError::Malformed)?;
+
+    let expected = sign(secret, &format!("challenge:{}:{}:{}:{}", issued, difficulty, nonce, ip));
+    if !constant_time_eq(signature, &expected) {
+        return Err(ChallengeError::BadSignature);
+    }
+    if now > issued + TOKEN_TTL_SECONDS {
+        return Err(ChallengeError::Expired);
+    }
+    if solution.is_empty() || solution.len() > 20 || !solution.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ChallengeError::Malformed);
+    }
+
+    let hash = Hash::hash(format!("{}:{}", token, solution).as_bytes());
+    if leading_zero_digits(&hash) < difficulty as usize {
+        return Err(ChallengeError::Unsolved);
+    }
+    Ok(())
+}
+
+/// Clearance cookie value: `expires.signature`
+fn issue_clearance(ip: &str, secret: &str, now: u64, ttl_seconds: u64) -> String {
+    let expires = now + ttl_seconds;
+    format!("{}.{}", expires, sign(secret, &format!("clearance:{}:{}", expires, ip)))
+}
+
+fn verify_clearance(clearance: &str, ip: &str, secret: &str, now: u64) -> bool {
+    let Some((expires, signature)) = clearance.split_once('.') else {
+        return false;
+    };
+    let Ok(expires) = expires.parse::<u64>() else {
+        return false;
+    };
+    constant_time_eq(signature, &sign(secret, &format!("clearance:{}:{}", expires, ip))) && now < expires
+}
+
+fn sign(secret: &str, data: &str) -> String {
+    hex::encode(HMAC::mac(data.as_bytes(), secret.as_bytes()))
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Leading zero hex digits of a hash.
+fn leading_zero_digits(hash: &[u8]) -> usize {
+    let mut zeros = 0;
+    for byte in hash {
+        if *byte == 0 {
+            zeros += 2;
+            continue;
+        }
+        if byte >> 4 == 0 {
+            zeros += 1;
+        }
+        break;
+    }
+    zeros
+}
+
+/// Where to send the client after a solved challenge: the decoded `return`
+/// parameter of the solution's query string, if it is a path on this site.
+fn return_path(query: &str) -> String {
+    let requested = query_pairs(query).into_iter().find(|(name, _)| name == "return").map(|(_, p)| p);
+    match requested {
+        // "//host" and "/\host" would leave the site, and browsers drop tabs
+        // and line breaks, so "/\t/host" would too
+        Some(p) if p.starts_with('/') && !p.starts_with("//") && !p.starts_with("/\\")
+            && !p.chars().any(char::is_control) => p,
+        _ => "/".to_string(),
+    }
+}
+
+fn interstitial(token: &str, difficulty: u8) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="robots" content="noindex">
+<title>Checking your browser</title>
+</head>
+<body>
+<p>Checking your browser&hellip;</p>
+<noscript><p>Please enable JavaScript to continue.</p></noscript>
+<script>
+(async () => {{
+  const token = "{token}";
+  const prefix = "0".repeat({difficulty});
+  const encoder = new TextEncoder();
+  for (let n = 0; ; n++) {{
+    const digest = await crypto.subtle.digest("SHA-256", encoder.encode(token + ":" + n));
+    const hex = Array.from(new Uint8Array(digest), b => b.toString(16).padStart(2, "0")).join("");
+    if (hex.startsWith(prefix)) {{
+      const query = new URLSearchParams({{ token, solution: String(n), return: location.pathname + location.search }});
+      location.replace("{path}?" + query);
+      return;
+    }}
+  }}
+}})();
+</script>
+</body>
+</html>
+"#,
+        token = token,
+        difficulty = difficulty,
+        path = CHALLENGE_PATH,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-secret";
+    const IP: &str = "203.0.113.7";
+    const NOW: u64 = 1_700_000_000;
+
+    fn solve(token: &str, difficulty: u8) -> String {
+        (0u64..)
+            .map(|n| n.to_string())
+            .find(|n| leading_zero_digits(&Hash::hash(format!("{}:{}", token, n).as_bytes())) >= difficulty as usize)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_solved_token_verifies() {
+        let token = issue_token(IP, SECRET, NOW, 2, "req-1");
+        let solution = solve(&token, 2);
+
+        assert_eq!(verify_solution(&token, &solution, IP, SECRET, NOW + 10), Ok(()));
+    }
+
+    #[test]
+    fn test_bad_solutions_refused() {
+        let token = issue_token(IP, SECRET, NOW, 2, "req-1");
+        let solution = solve(&token, 2);
+
+        // Another client, a stale token, a forged difficulty, a wrong answer
+        assert_eq!(verify_solution(&token, &solution, "198.51.100.1", SECRET, NOW), Err(ChallengeError::BadSignature));
+        assert_eq!(verify_solution(&token, &solution, IP, SECRET, NOW + 301), Err(ChallengeError::Expired));
+        let forged = token.replacen(".2.", ".0.", 1);
+        assert_eq!(verify_solution(&forged, "0", IP, SECRET, NOW), Err(ChallengeError::BadSignature));
+        let wrong = (0u64..)
+            .map(|n| n.to_string())
+            .find(|n| leading_zero_digits(&Hash::hash(format!("{}:{}", token, n).as_bytes())) < 2)
+            .unwrap();
+        assert_eq!(verify_solution(&token, &wrong, IP, SECRET, NOW), Err(ChallengeError::Unsolved));
+        assert_eq!(verify_solution("garbage", "1", IP, SECRET, NOW), Err(ChallengeError::Malformed));
+    }
+
+    #[test]
+    fn test_clearance_bound_to_client_and_expiry() {
+        let clearance = issue_clearance(IP, SECRET, NOW, 3600);
+
+        assert!(verify_clearance(&clearance, IP, SECRET, NOW + 10));
+        assert!(!verify_clearance(&clearance, IP, SECRET, NOW + 3600));
+        assert!(!verify_clearance(&clearance, "198.51.100.1", SECRET, NOW));
+        assert!(!verify_clearance(&clearance, IP, "other-secret", NOW));
+
+        // A stale copy of the cookie doesn't hide a valid one
+        let cookies = format!("cc_clearance=1.stale; theme=dark; cc_clearance={}", clearance);
+        assert!(has_valid_clearance(&cookies, IP, SECRET, NOW + 10));
+        assert!(!has_valid_clearance("cc_clearance=1.stale", IP, SECRET, NOW + 10));
+    }
+
+    #[test]
+    fn test_cookie_and_return_path_parsing() {
+        assert_eq!(cookie_values("a=1; cc_clearance=123.abc; b=2", CLEARANCE_COOKIE), vec!["123.abc"]);
+        assert!(cookie_values("a=1", CLEARANCE_COOKIE).is_empty());
+
+        // Encoded the way the interstitial's URLSearchParams encodes it
+        assert_eq!(return_path("token=t&solution=1&return=%2Fshop%3Fpage%3D2"), "/shop?page=2");
+        assert_eq!(return_path("return=%2Fsearch%3Fq%3Dred%2520shoes"), "/search?q=red%20shoes");
+        assert_eq!(return_path("return=%2F%2Fevil.example"), "/");
+        assert_eq!(return_path("return=%2F%5Cevil.example"), "/");
+        assert_eq!(return_path("return=%2F%09%2Fevil.example"), "/");
+        assert_eq!(return_path("return=%2Faccount%0D%0ASet-Cookie%3A+x%3D1"), "/");
+        assert_eq!(return_path("return=https%3A%2F%2Fevil.example"), "/");
+        assert_eq!(return_path("token=t&solution=1"), "/");
+    }
+
+    #[test]
+    fn test_difficulty_by_challenge_type() {
+        let config = ChallengeConfig { difficulty: 12, ..ChallengeConfig::default() };
+
+        assert_eq!(config.difficulty_for(ChallengeType::NonInteractive), JS_DIFFICULTY);
+        assert_eq!(config.difficulty_for(ChallengeType::ProofOfWork), MAX_DIFFICULTY);
+    }
+
+    #[test]
+    fn test_enabled_only_with_secret() {
+        let config = |secret: Option<&str>| ChallengeConfig { secret: secret.map(String::from), ..ChallengeConfig::default() };
+
+        assert!(config(Some(SECRET)).is_enabled());
+        assert!(!config(Some("")).is_enabled());
+        assert!(!config(None).is_enabled());
+    }
+}
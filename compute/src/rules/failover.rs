@@ -0,0 +1,88 @@
+//! Retries and failover for backend nodes.
+//!
+//! [`plan_attempts`] decides which backends a request goes to, in order;
+//! the service sends the attempts until one doesn't count as a failure
+//! under the backend's retry policy.
+
+use super::types::BackendNodeData;
+
+/// Backends to send a request to, in order: the backend itself as many
+/// times as its retry policy allows (at most
+/// [`MAX_BACKEND_ATTEMPTS`](super::types::MAX_BACKEND_ATTEMPTS)), then its
+/// fallback once.
+///
+/// A request with a body goes out once, as its body is streamed to the
+/// first attempt and can't be sent again. So does a non-idempotent method,
+/// unless the policy allows retrying those.
+pub fn plan_attempts<'a>(backend: &'a BackendNodeData, method: &str, has_body: bool) -> Vec<&'a BackendNodeData> {
+    let policy = backend.retry.clone().unwrap_or_default();
+    let idempotent = matches!(method, "GET" | "HEAD" | "OPTIONS" | "PUT" | "DELETE");
+
+    let mut attempts = vec![backend];
+    if !has_body && (idempotent || !policy.idempotent_only) {
+        attempts.resize(policy.attempts() as usize, backend);
+        attempts.extend(backend.fallback.as_deref());
+    }
+    attempts
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::types::RetryPolicy;
+
+    fn backend(retry: serde_json::Value) -> BackendNodeData {
+        serde_json::from_value(serde_json::json!({
+            "name": "primary",
+            "host": "a.example.com",
+            "retry": retry,
+            "fallback": {"name": "standby", "host": "b.example.com"}
+        }))
+        .unwrap()
+    }
+
+    fn names(attempts: &[&BackendNodeData]) -> Vec<String> {
+        attempts.iter().map(|b| b.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_attempts_retry_then_fall_back() {
+        let primary = backend(serde_json::json!({"maxAttempts": 3}));
+        assert_eq!(names(&plan_attempts(&primary, "GET", false)), ["primary", "primary", "primary", "standby"]);
+
+        // A typo can't multiply the traffic sent to a failing origin
+        let primary = backend(serde_json::json!({"maxAttempts": 4_000_000_000u32}));
+        assert_eq!(plan_attempts(&primary, "GET", false).len(), 6);
+        let primary = backend(serde_json::json!({"maxAttempts": 0}));
+        assert_eq!(names(&plan_attempts(&primary, "GET", false)), ["primary", "standby"]);
+    }
+
+    #[test]
+    fn test_requests_with_body_or_side_effects_sent_once() {
+        let primary = backend(serde_json::json!({"maxAttempts": 3}));
+        assert_eq!(names(&plan_attempts(&primary, "PUT", true)), ["primary"]);
+        assert_eq!(names(&plan_attempts(&primary, "POST", false)), ["primary"]);
+
+        // Retrying non-idempotent methods is opt-in, and still never resends a body
+        let primary = backend(serde_json::json!({"maxAttempts": 2, "idempotentOnly": false}));
+        assert_eq!(names(&plan_attempts(&primary, "POST", false)), ["primary", "primary", "standby"]);
+        assert_eq!(names(&plan_attempts(&primary, "POST", true)), ["primary"]);
+    }
+
+    #[test]
+    fn test_failures_per_retry_policy() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_failure(None));
+        assert!(policy.is_failure(Some(503)));
+        assert!(!policy.is_failure(Some(500)));
+        assert!(!policy.is_failure(Some(200)));
+
+        let policy = RetryPolicy { retry_on_error: false, retry_on_status: vec![500], ..RetryPolicy::default() };
+        assert!(!policy.is_failure(None));
+        assert!(policy.is_failure(Some(500)));
+    }
+}
@@ -1,712 +1,340 @@
 //! Graph-based rule interpreter.
 //!
 //! Evaluates security rules stored as a visual graph (nodes + edges).
-//! This allows the editor to deploy rules directly without conversion.
-
-use std::collections::HashMap;
-use std::net::IpAddr;
+//! The editor graph is converted into a typed cc-core graph and compiled
+//! once into an execution plan, which cc-core's interpreter runs - the same
+//! engine the editor previews with. This module supplies the Fastly request
+//! (and, for the response phase, backend response) data, the buffered
+//! request body, and edge rate limiting.
+
+use chrono::Utc;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use fastly::backend::BackendBuilder;
+use fastly::http::{HeaderName, HeaderValue};
 use fastly::{Request, Response};
 use fastly::geo::geo_lookup;
 use fastly::device_detection::lookup as device_lookup;
 
 use std::time::Duration;
-use fastly::erl::{ERL, RateCounter, Penaltybox, RateWindow, CounterDuration};
-use ipnet::IpNet;
-
-use super::types::{
-    GraphPayload, GraphNode, GraphEdge,
-    BackendNodeData, ActionNodeData, RuleGroupNodeData, ConditionNodeData, RateLimitNodeData,
-    HeaderNodeData, RedirectNodeData, CacheNodeData, TransformNodeData,
+use fastly::erl::{ERL, RateCounter, Penaltybox, CounterDuration};
+use cc_core::{
+    cookie, query_param, query_param_count, query_param_names, time_field, ChallengeType, ExecutionResult,
+    ExecutionState, FailurePolicy, NodeKind, Plan, RateLimiter, RateWindow, RequestBody, RequestField,
+    RequestSource, Value,
 };
 
+use super::loader::LoadError;
+use super::types::{GraphPayload, BackendNodeData};
+
+pub use cc_core::{CacheSettings, HeaderMod, SecurityEvent, TarpitThen};
+
 /// Result of evaluating the graph for a request.
 pub enum GraphResult {
     /// Route the request to a backend with full configuration
-    Route(BackendNodeData),
+    Route(Box<BackendNodeData>),
     /// Block the request with a response
     Block { status_code: u16, message: String },
     /// Redirect the request
     Redirect { url: String, status_code: u16, preserve_query: bool },
+    /// Answer with a synthetic response
+    Respond {
+        status_code: u16,
+        content_type: String,
+        headers: Vec<(String, String)>,
+        body: String,
+    },
+    /// Challenge the client (it hasn't passed a challenge yet)
+    Challenge { challenge_type: ChallengeType },
+    /// Hold the request, then block or forward it
+    Tarpit { delay_ms: u32, then: TarpitThen },
     /// Allow the request (pass through to default backend)
     Allow,
     /// No matching path found
     NoMatch,
-}
-
-/// Header modification to apply before forwarding
-#[derive(Debug, Clone)]
-pub enum HeaderMod {
-    Set { name: String, value: String },
-    Append { name: String, value: String },
-    Remove { name: String },
-}
-
-/// Cache settings collected during graph traversal
-#[derive(Debug, Clone, Default)]
-pub struct CacheSettings {
-    /// Bypass cache entirely
-    pub pass: bool,
-    /// Time-to-live in seconds
-    pub ttl: Option<u64>,
-    /// Stale-while-revalidate duration in seconds
-    pub stale_while_revalidate: Option<u64>,
-    /// Surrogate keys for cache purging
-    pub surrogate_keys: Vec<String>,
+    /// Evaluation was cut off by the step limit (the graph loops)
+    StepLimitExceeded,
+    /// A failure the graph's failure policy says to fail closed on
+    FailClosed { reason: String },
 }
 
 /// Evaluates a graph against an incoming request.
-pub struct GraphInterpreter<'a> {
-    nodes: HashMap<String, &'a GraphNode>,
-    edges_from: HashMap<String, Vec<&'a GraphEdge>>,
-    rate_limiter: Option<ERL>,
-    /// Separate rate counter for debug lookups (ERL takes ownership of the main one)
-    rate_counter_debug: Option<RateCounter>,
-    /// Cached geo lookup result
-    geo_cache: std::cell::RefCell<Option<Option<fastly::geo::Geo>>>,
-    /// Header modifications collected during traversal
-    header_mods: std::cell::RefCell<Vec<HeaderMod>>,
-    /// Cache settings collected during traversal
-    cache_settings: std::cell::RefCell<CacheSettings>,
-    /// Transform results stored by variable name
-    transform_results: std::cell::RefCell<HashMap<String, String>>,
+pub struct GraphInterpreter {
+    plan: Plan,
+    /// Execution state; holds the header mods and cache settings after evaluate()
+    state: RefCell<ExecutionState>,
+    /// Request body buffered for evaluate(), read by both phases
+    body: RefCell<Option<RequestBody>>,
 }
 
-impl<'a> GraphInterpreter<'a> {
-    /// Create a new interpreter for the given graph.
-    pub fn new(graph: &'a GraphPayload) -> Self {
-        let mut nodes = HashMap::new();
-        let mut edges_from: HashMap<String, Vec<&GraphEdge>> = HashMap::new();
-
-        for node in &graph.nodes {
-            nodes.insert(node.id.clone(), node);
-        }
-
-        for edge in &graph.edges {
-            edges_from.entry(edge.source.clone()).or_default().push(edge);
-        }
+impl GraphInterpreter {
+    /// Create a new interpreter for the given graph and failure policy.
+    /// Fails if the graph can't be converted or compiled (e.g. an invalid regex).
+    pub fn new(payload: &GraphPayload, failure_policy: FailurePolicy) -> Result<Self, LoadError> {
+        let graph = cc_core::from_payload(payload)?;
+        let plan = Plan::compile(&graph).map_err(LoadError::CompileError)?;
 
         // Initialize rate limiter if graph contains rateLimit nodes
-        let has_rate_limit = graph.nodes.iter().any(|n| n.node_type == "rateLimit");
-        let (rate_limiter, rate_counter_debug) = if has_rate_limit {
-            // Try to open the rate counter and penalty box
-            // These must be configured in fastly.toml and linked to the service
-            let counter = RateCounter::open("cc_rate_counter");
-            let penaltybox = Penaltybox::open("cc_penalty_box");
-            // Open a second counter handle for debug lookups
-            let counter_debug = RateCounter::open("cc_rate_counter");
-            (Some(ERL::open(counter, penaltybox)), Some(counter_debug))
+        let has_rate_limit = graph.nodes.iter().any(|n| matches!(n.kind, NodeKind::RateLimit { .. }));
+        let mut state = if has_rate_limit {
+            ExecutionState::with_rate_limiter(Box::new(EdgeRateLimiter::open()))
         } else {
-            (None, None)
+            ExecutionState::new()
         };
+        state.failure_policy = failure_policy;
 
-        Self {
-            nodes,
-            edges_from,
-            rate_limiter,
-            rate_counter_debug,
-            geo_cache: std::cell::RefCell::new(None),
-            header_mods: std::cell::RefCell::new(Vec::new()),
-            cache_settings: std::cell::RefCell::new(CacheSettings::default()),
-            transform_results: std::cell::RefCell::new(HashMap::new()),
-        }
+        Ok(Self {
+            plan,
+            state: RefCell::new(state),
+            body: RefCell::new(None),
+        })
+    }
+
+    /// Whether the graph reads body fields, so the body has to be buffered
+    /// before evaluate().
+    pub fn reads_body(&self) -> bool {
+        self.plan.reads_body()
     }
 
     /// Get the header modifications collected during evaluation.
     /// Call this after evaluate() to get the mods to apply.
     pub fn get_header_mods(&self) -> Vec<HeaderMod> {
-        self.header_mods.borrow().clone()
+        self.state.borrow().header_mods.clone()
     }
 
-    /// Get the cache settings collected during evaluation.
-    /// Call this after evaluate() to get the settings to apply.
-    pub fn get_cache_settings(&self) -> CacheSettings {
-        self.cache_settings.borrow().clone()
+    /// Get the security events log nodes recorded during evaluation.
+    /// Call this after evaluate() to add them to the request's log entry.
+    pub fn get_events(&self) -> Vec<SecurityEvent> {
+        self.state.borrow().events.clone()
     }
 
-    /// Get geo data for client IP, caching the result
-    fn get_geo(&self, req: &Request) -> Option<fastly::geo::Geo> {
-        let mut cache = self.geo_cache.borrow_mut();
-        if cache.is_none() {
-            let geo_result = match req.get_client_ip_addr() {
-                Some(ip) => geo_lookup(ip),
-                None => None,
-            };
-            *cache = Some(geo_result);
-        }
-        // Return a clone of the cached geo result
-        match cache.as_ref() {
-            Some(Some(geo)) => Some(geo.clone()),
-            _ => None,
-        }
+    /// Get the id of the rule that matched in each rule list, by list name.
+    /// Call this after evaluate() to add them to the request's log entry.
+    pub fn get_matched_rules(&self) -> BTreeMap<String, String> {
+        self.state.borrow().matched_rules.clone()
     }
 
-    /// Get device data for user agent (not cached since Device doesn't implement Clone)
-    fn get_device(&self, req: &Request) -> Option<fastly::device_detection::Device> {
-        let ua = req.get_header_str("user-agent").unwrap_or("");
-        device_lookup(ua)
+    /// Get the bucket each split node put the request in, by split name.
+    /// Call this after evaluate() to add them to the request's log entry.
+    pub fn get_buckets(&self) -> BTreeMap<String, String> {
+        self.state.borrow().buckets.clone()
     }
 
-    /// Evaluate the graph for an incoming request.
-    pub fn evaluate(&self, req: &Request) -> GraphResult {
-        // Find the request node (entry point)
-        let request_node = self.nodes.values()
-            .find(|n| n.node_type == "request");
-
-        let request_node = match request_node {
-            Some(n) => n,
-            None => {
-                println!("[Graph] No request node found");
-                return GraphResult::NoMatch;
-            }
-        };
-
-        println!("[Graph] Starting evaluation from request node {}", request_node.id);
-
-        // Follow edges from the request node
-        self.follow_path(&request_node.id, req)
+    /// Get the nodes that were passed through because they were outside
+    /// their schedule (activeFrom/activeUntil).
+    /// Call this after evaluate() to add them to the request's log entry.
+    pub fn get_inactive_nodes(&self) -> Vec<String> {
+        self.state.borrow().inactive_nodes.clone()
     }
 
-    /// Follow edges from a node, evaluating conditions along the way.
-    fn follow_path(&self, node_id: &str, req: &Request) -> GraphResult {
-        let node = match self.nodes.get(node_id) {
-            Some(n) => n,
-            None => return GraphResult::NoMatch,
-        };
-
-        println!("[Graph] Evaluating node {} (type: {})", node_id, node.node_type);
-
-        match node.node_type.as_str() {
-            "request" => {
-                // Entry point - follow outgoing edges
-                self.follow_outgoing(node_id, None, req)
-            }
-
-            "ruleGroup" => {
-                // Evaluate inline conditions
-                let data: RuleGroupNodeData = match serde_json::from_value(node.data.clone()) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("[Graph] Failed to parse ruleGroup data: {}", e);
-                        return GraphResult::NoMatch;
-                    }
-                };
-
-                let matches = self.evaluate_rule_group(&data, req);
-                println!("[Graph] RuleGroup '{}' matches: {}", data.name.as_deref().unwrap_or("unnamed"), matches);
-
-                // Follow the appropriate output handle
-                let handle = if matches { "match" } else { "noMatch" };
-                self.follow_outgoing(node_id, Some(handle), req)
-            }
-
-            "condition" => {
-                // Evaluate single condition
-                let data: ConditionNodeData = match serde_json::from_value(node.data.clone()) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("[Graph] Failed to parse condition data: {}", e);
-                        return GraphResult::NoMatch;
-                    }
-                };
-
-                let matches = self.evaluate_condition(&data, req);
-                println!("[Graph] Condition {}={} matches: {}", data.field, data.value, matches);
-
-                // UI uses "true"/"false" handles for condition nodes
-                if matches {
-                    self.follow_outgoing(node_id, Some("true"), req)
-                } else {
-                    self.follow_outgoing(node_id, Some("false"), req)
-                }
-            }
-
-            "action" => {
-                // Terminal node - return action result
-                let data: ActionNodeData = match serde_json::from_value(node.data.clone()) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("[Graph] Failed to parse action data: {}", e);
-                        return GraphResult::Block { status_code: 500, message: "Invalid action config".to_string() };
-                    }
-                };
-
-                println!("[Graph] Action: {} (status: {:?})", data.action, data.status_code);
-
-                match data.action.as_str() {
-                    "block" => GraphResult::Block {
-                        status_code: data.status_code.unwrap_or(403),
-                        message: data.message.unwrap_or_else(|| "Blocked".to_string()),
-                    },
-                    "allow" => GraphResult::Allow,
-                    "redirect" => {
-                        let url = data.url.unwrap_or_else(|| "/".to_string());
-                        println!("[Graph] Action Redirect: {} -> {} (preserve_query: {})",
-                            data.status_code.unwrap_or(302), url, data.preserve_query.unwrap_or(true));
-                        GraphResult::Redirect {
-                            url,
-                            status_code: data.status_code.unwrap_or(302),
-                            preserve_query: data.preserve_query.unwrap_or(true),
-                        }
-                    }
-                    _ => GraphResult::Block {
-                        status_code: data.status_code.unwrap_or(403),
-                        message: data.message.unwrap_or_else(|| "Blocked".to_string()),
-                    },
-                }
-            }
-
-            "backend" => {
-                // Terminal node - route to backend
-                let data: BackendNodeData = match serde_json::from_value(node.data.clone()) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("[Graph] Failed to parse backend data: {}", e);
-                        return GraphResult::NoMatch;
-                    }
-                };
-
-                println!("[Graph] Routing to backend: {} ({}:{})",
-                    data.name, data.host, data.port.unwrap_or(443));
-
-                GraphResult::Route(data)
-            }
-
-            "rateLimit" => {
-                // Rate limiting node - check if client has exceeded rate limit
-                let data: RateLimitNodeData = match serde_json::from_value(node.data.clone()) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("[Graph] Failed to parse rateLimit data: {}", e);
-                        // Fail open - continue to "ok" path
-                        return self.follow_outgoing(node_id, Some("ok"), req);
-                    }
-                };
-
-                // Warning for local development
-                println!("[Graph] ⚠️  Rate limiting in local dev (Viceroy) may not persist counters between requests.");
-                println!("[Graph]    Deploy to Fastly to test rate limiting behavior accurately.");
-
-                // Get the client identifier based on keyBy
-                let entry = self.get_rate_limit_key(&data, req);
-
-                // Map window unit to RateWindow enum (for check_rate)
-                let window = match data.window_unit.as_str() {
-                    "second" => RateWindow::OneSec,
-                    "minute" => RateWindow::SixtySecs,
-                    "hour" => RateWindow::SixtySecs, // Use 60s window for hourly, limit is adjusted
-                    _ => RateWindow::SixtySecs,
-                };
-
-                // Helper to get CounterDuration for lookup_count debug
-                let get_counter_duration = || match data.window_unit.as_str() {
-                    "second" => CounterDuration::TenSec, // Closest to 1 second
-                    "minute" => CounterDuration::SixtySecs,
-                    "hour" => CounterDuration::SixtySecs,
-                    _ => CounterDuration::SixtySecs,
-                };
-
-                // Debug: lookup current count before check
-                let count_before = self.rate_counter_debug.as_ref()
-                    .and_then(|c| c.lookup_count(&entry, get_counter_duration()).ok())
-                    .unwrap_or(0);
-
-                println!("[Graph] Rate limit check for entry: {} (count: {}, limit: {}/{})",
-                    entry, count_before, data.limit, data.window_unit);
-
-                // Check if we have a rate limiter
-                let exceeded = match &self.rate_limiter {
-                    Some(erl) => {
-                        // Calculate the rate limit based on window
-                        // ERL checks requests per second, so we need to convert
-                        let rps_limit = match data.window_unit.as_str() {
-                            "second" => data.limit,
-                            "minute" => data.limit, // limit per 60s window
-                            "hour" => data.limit / 60, // approximate hourly to per-minute
-                            _ => data.limit,
-                        };
-
-                        // Penalty box TTL: 2 minutes for second/minute, 10 minutes for hour
-                        let ttl = match data.window_unit.as_str() {
-                            "hour" => Duration::from_secs(600),
-                            _ => Duration::from_secs(120),
-                        };
-
-                        match erl.check_rate(&entry, 1, window, rps_limit, ttl) {
-                            Ok(is_blocked) => {
-                                // Debug: lookup count after increment
-                                let count_after = self.rate_counter_debug.as_ref()
-                                    .and_then(|c| c.lookup_count(&entry, get_counter_duration()).ok())
-                                    .unwrap_or(0);
-                                println!("[Graph] Rate limit result: blocked={}, count: {} -> {}",
-                                    is_blocked, count_before, count_after);
-                                is_blocked
-                            }
-                            Err(e) => {
-                                println!("[Graph] Rate limit error: {:?}, failing open", e);
-                                false // Fail open on error
-                            }
-                        }
-                    }
-                    None => {
-                        println!("[Graph] No rate limiter configured, failing open");
-                        false // Fail open if no rate limiter
-                    }
-                };
-
-                // Follow the appropriate output handle
-                let handle = if exceeded { "exceeded" } else { "ok" };
-                self.follow_outgoing(node_id, Some(handle), req)
-            }
+    /// Get the cookie (name, value) a cookie-strategy backend pool wants set
+    /// on the response to keep the client on its member.
+    /// Call this after evaluate(), before evaluate_response() clears it.
+    pub fn get_sticky_cookie(&self) -> Option<(String, String)> {
+        self.state.borrow().sticky_cookie.clone()
+    }
 
-            "header" => {
-                // Header modification node - collect the mod and continue
-                let data: HeaderNodeData = match serde_json::from_value(node.data.clone()) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("[Graph] Failed to parse header data: {}", e);
-                        return self.follow_outgoing(node_id, None, req);
-                    }
-                };
-
-                // Collect the header modification
-                let header_mod = match data.operation.as_str() {
-                    "set" => {
-                        let value = data.value.unwrap_or_default();
-                        println!("[Graph] Header SET: {} = {}", data.name, value);
-                        Some(HeaderMod::Set { name: data.name, value })
-                    }
-                    "append" => {
-                        let value = data.value.unwrap_or_default();
-                        println!("[Graph] Header APPEND: {} += {}", data.name, value);
-                        Some(HeaderMod::Append { name: data.name, value })
-                    }
-                    "remove" => {
-                        println!("[Graph] Header REMOVE: {}", data.name);
-                        Some(HeaderMod::Remove { name: data.name })
-                    }
-                    _ => {
-                        println!("[Graph] Header unknown op: {}", data.operation);
-                        None
-                    }
-                };
-
-                if let Some(hm) = header_mod {
-                    self.header_mods.borrow_mut().push(hm);
-                }
-
-                // Continue to next node
-                self.follow_outgoing(node_id, Some("next"), req)
-            }
+    /// Get the cache settings collected during evaluation.
+    /// Call this after evaluate() to get the settings to apply.
+    pub fn get_cache_settings(&self) -> CacheSettings {
+        self.state.borrow().cache_settings.clone()
+    }
 
-            "cache" => {
-                // Cache control node - collect settings and continue
-                let data: CacheNodeData = match serde_json::from_value(node.data.clone()) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("[Graph] Failed to parse cache data: {}", e);
-                        return self.follow_outgoing(node_id, Some("next"), req);
-                    }
-                };
-
-                let mut settings = self.cache_settings.borrow_mut();
-
-                match data.mode.as_str() {
-                    "pass" => {
-                        println!("[Graph] Cache: BYPASS (pass mode)");
-                        settings.pass = true;
-                    }
-                    "configure" => {
-                        // Calculate TTL in seconds based on unit
-                        if let Some(ttl_value) = data.ttl {
-                            let multiplier = match data.ttl_unit.as_deref().unwrap_or("seconds") {
-                                "minutes" => 60,
-                                "hours" => 3600,
-                                "days" => 86400,
-                                _ => 1, // seconds
-                            };
-                            let ttl_secs = ttl_value * multiplier;
-                            println!("[Graph] Cache: TTL = {}s", ttl_secs);
-                            settings.ttl = Some(ttl_secs);
-                        }
+    /// Evaluate the graph for an incoming request. `request_id` is the id
+    /// the request is logged under (the `requestId` field in templates);
+    /// `challenge_passed` whether it carries a valid challenge clearance;
+    /// `body` the buffered body, if the graph reads it.
+    pub fn evaluate(
+        &self,
+        req: &Request,
+        request_id: &str,
+        challenge_passed: bool,
+        body: Option<RequestBody>,
+    ) -> GraphResult {
+        *self.body.borrow_mut() = body;
+        let body = self.body.borrow();
+        let mut source = FastlyRequest::new(req, request_id, challenge_passed);
+        source.body = body.as_ref();
+        let mut state = self.state.borrow_mut();
+        let result = cc_core::execute(&self.plan, &source, &mut state);
+
+        for line in &state.trace {
+            println!("[Graph] {}", line);
+        }
+        result.into()
+    }
 
-                        // Calculate SWR in seconds based on unit
-                        if let Some(swr_value) = data.stale_while_revalidate {
-                            let multiplier = match data.swr_unit.as_deref().unwrap_or("seconds") {
-                                "minutes" => 60,
-                                "hours" => 3600,
-                                _ => 1, // seconds
-                            };
-                            let swr_secs = swr_value * multiplier;
-                            println!("[Graph] Cache: SWR = {}s", swr_secs);
-                            settings.stale_while_revalidate = Some(swr_secs);
-                        }
+    /// Whether the graph has a response node, i.e. a response phase to run
+    /// on backend responses.
+    pub fn has_response_phase(&self) -> bool {
+        self.plan.response_entry().is_some()
+    }
 
-                        // Parse surrogate keys (space-separated)
-                        if let Some(ref keys) = data.surrogate_keys {
-                            if !keys.is_empty() {
-                                let parsed_keys: Vec<String> = keys.split_whitespace()
-                                    .map(|s| s.to_string())
-                                    .collect();
-                                println!("[Graph] Cache: Surrogate keys = {:?}", parsed_keys);
-                                settings.surrogate_keys = parsed_keys;
-                            }
-                        }
-                    }
-                    _ => {
-                        println!("[Graph] Cache: unknown mode '{}'", data.mode);
-                    }
-                }
-
-                // Continue to next node
-                self.follow_outgoing(node_id, Some("next"), req)
-            }
+    /// Evaluate the response phase for the backend's response to `req`.
+    /// Header mods and events are replaced by the response phase's; read
+    /// the request phase's first.
+    pub fn evaluate_response(&self, req: &Request, resp: &Response, request_id: &str) -> GraphResult {
+        let body = self.body.borrow();
+        let mut source = FastlyRequest::new(req, request_id, false);
+        source.response = Some(resp);
+        source.body = body.as_ref();
+        let mut state = self.state.borrow_mut();
+        let result = cc_core::execute_response(&self.plan, &source, &mut state);
+
+        for line in &state.trace {
+            println!("[Graph] {}", line);
+        }
+        result.into()
+    }
+}
 
-            "redirect" => {
-                // Terminal node - return redirect result
-                let data: RedirectNodeData = match serde_json::from_value(node.data.clone()) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("[Graph] Failed to parse redirect data: {}", e);
-                        return GraphResult::Block { status_code: 500, message: "Invalid redirect config".to_string() };
-                    }
-                };
-
-                println!("[Graph] Redirect: {} -> {} (preserve_query: {})",
-                    data.status_code.unwrap_or(302), data.url, data.preserve_query.unwrap_or(true));
-
-                GraphResult::Redirect {
-                    url: data.url,
-                    status_code: data.status_code.unwrap_or(302),
-                    preserve_query: data.preserve_query.unwrap_or(true),
-                }
-            }
+impl From<ExecutionResult> for GraphResult {
+    fn from(result: ExecutionResult) -> Self {
+        match result {
+            ExecutionResult::Allow => GraphResult::Allow,
+            ExecutionResult::Block { status_code, message } => GraphResult::Block { status_code, message },
+            ExecutionResult::Redirect { url, status_code, preserve_query } => {
+                GraphResult::Redirect { url, status_code, preserve_query }
+            }
+            ExecutionResult::Respond { status_code, content_type, headers, body } => {
+                GraphResult::Respond { status_code, content_type, headers, body }
+            }
+            ExecutionResult::Route { backend } => GraphResult::Route(backend),
+            ExecutionResult::NoMatch => GraphResult::NoMatch,
+            ExecutionResult::StepLimitExceeded => GraphResult::StepLimitExceeded,
+            ExecutionResult::FailClosed { reason } => GraphResult::FailClosed { reason },
+            ExecutionResult::Challenge { challenge_type } => GraphResult::Challenge { challenge_type },
+            ExecutionResult::Tarpit { delay_ms, then } => GraphResult::Tarpit { delay_ms, then },
+            // Only a Backend node says where a named backend is, so sending
+            // the request anywhere else would be a guess
+            ExecutionResult::Forward { backend } => GraphResult::FailClosed {
+                reason: format!("Forward to backend '{}' without a Backend node", backend),
+            },
+        }
+    }
+}
 
-            "transform" => {
-                // Transform node - apply transformation and continue
-                let data: TransformNodeData = match serde_json::from_value(node.data.clone()) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("[Graph] Failed to parse transform data: {}", e);
-                        return self.follow_outgoing(node_id, Some("value_out"), req);
-                    }
-                };
-
-                // Get the source field value
-                let source_value = self.get_field_value(&data.field, req).unwrap_or_default();
-                println!("[Graph] Transform: {} on '{}' (input: '{}')", data.operation, data.field, source_value);
-
-                // Apply the transformation
-                let result = self.apply_transform(&data.operation, &source_value, data.pattern.as_deref());
-
-                // Store the result if outputVar is specified
-                if let Some(ref var_name) = data.output_var {
-                    println!("[Graph] Transform result stored in '{}': '{}'", var_name, result);
-                    self.transform_results.borrow_mut().insert(var_name.clone(), result.clone());
-                } else {
-                    println!("[Graph] Transform result (no var): '{}'", result);
-                }
-
-                // Continue to next node
-                self.follow_outgoing(node_id, Some("value_out"), req)
-            }
+/// Request body inspection settings deployed with the graph.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BodyInspection {
+    /// Most bytes of a body buffered for body fields
+    pub max_bytes: usize,
+}
 
-            _ => {
-                println!("[Graph] Unknown node type: {}", node.node_type);
-                self.follow_outgoing(node_id, None, req)
-            }
-        }
+impl Default for BodyInspection {
+    fn default() -> Self {
+        Self { max_bytes: 16 * 1024 }
     }
+}
 
-    /// Get the rate limit key based on the keyBy field
-    fn get_rate_limit_key(&self, data: &RateLimitNodeData, req: &Request) -> String {
-        match data.key_by.as_str() {
-            "ip" => {
-                self.get_field_value("clientIp", req)
-                    .unwrap_or_else(|| "unknown".to_string())
-            }
-            "fingerprint" => {
-                // Try JA4 first, then JA3
-                self.get_field_value("ja4", req)
-                    .or_else(|| self.get_field_value("ja3", req))
-                    .unwrap_or_else(|| "unknown".to_string())
-            }
-            "header" => {
-                if let Some(header_name) = &data.header_name {
-                    req.get_header_str(header_name)
-                        .map(|s| s.to_string())
-                        .unwrap_or_else(|| "unknown".to_string())
-                } else {
-                    "unknown".to_string()
-                }
-            }
-            "path" => req.get_path().to_string(),
-            _ => "unknown".to_string(),
+impl BodyInspection {
+    /// Buffer the beginning of the request body, up to `max_bytes`. The
+    /// bytes are written back, so the backend still receives the whole body.
+    pub fn read(&self, req: &mut Request) -> RequestBody {
+        if !req.has_body() {
+            return RequestBody::default();
         }
+        let declared = req.get_header_str("content-length").and_then(|l| l.trim().parse::<u64>().ok());
+        // One byte past the cap tells whether the body is longer
+        let prefix = req.get_body_prefix_mut(self.max_bytes.saturating_add(1));
+        let truncated = prefix.len() > self.max_bytes;
+        let buffered = &prefix[..prefix.len().min(self.max_bytes)];
+        RequestBody::new(buffered, declared.unwrap_or(prefix.len() as u64), truncated)
     }
+}
 
-    /// Follow outgoing edges from a node, optionally filtering by source handle.
-    fn follow_outgoing(&self, node_id: &str, source_handle: Option<&str>, req: &Request) -> GraphResult {
-        let edges = match self.edges_from.get(node_id) {
-            Some(e) => e,
-            None => {
-                println!("[Graph] No outgoing edges from {}", node_id);
-                return GraphResult::NoMatch;
-            }
-        };
+/// Request fields read from the live Fastly request.
+///
+/// Metadata and headers are read from the request; the body only as the
+/// prefix buffered by [`BodyInspection::read`], so it can still be
+/// streamed to the backend unchanged.
+struct FastlyRequest<'a> {
+    req: &'a Request,
+    request_id: &'a str,
+    challenge_passed: bool,
+    /// Backend response, in the response phase
+    response: Option<&'a Response>,
+    /// Buffered body, when the graph reads body fields
+    body: Option<&'a RequestBody>,
+    /// Cached geo lookup result
+    geo_cache: RefCell<Option<Option<fastly::geo::Geo>>>,
+}
 
-        // Filter by source handle if specified
-        let matching_edges: Vec<_> = edges.iter()
-            .filter(|e| {
-                match source_handle {
-                    Some(h) => e.source_handle.as_deref() == Some(h),
-                    None => true,
-                }
-            })
-            .collect();
-
-        if matching_edges.is_empty() {
-            println!("[Graph] No matching edges from {} (handle: {:?})", node_id, source_handle);
-            return GraphResult::NoMatch;
-        }
+impl RequestSource for FastlyRequest<'_> {
+    fn get_field(&self, field: &RequestField) -> Value {
+        let query = || self.req.get_query_str().unwrap_or_default();
+        let value = match field {
+            RequestField::QueryParam { name } => return query_param(query(), name),
+            RequestField::QueryParamCount => return query_param_count(query()),
+            RequestField::QueryParamNames => return query_param_names(query()),
+            field if field.is_body() => return self.body.map_or(Value::None, |b| b.field(field)),
+            RequestField::Now | RequestField::Hour { .. } | RequestField::Weekday { .. } |
+            RequestField::Today { .. } => return time_field(field, Utc::now().timestamp()),
+            // HTTP/2 clients may split cookies over several headers
+            RequestField::Cookie { name } => {
+                return cookie(&self.req.get_header_all_str_lossy("cookie").join("; "), name)
+            }
+            RequestField::Header { name } => self.req.get_header_str(name).map(|s| s.to_string()),
+            RequestField::ResponseStatus => self.response.map(|r| r.get_status().as_u16().to_string()),
+            RequestField::ResponseHeader { name } => {
+                self.response.and_then(|r| r.get_header_str(name)).map(|s| s.to_string())
+            }
+            // Transform variables are resolved by the interpreter
+            RequestField::Var { .. } => None,
+            other => self.get_field_value(other.key()),
+        };
+        value.map(Value::String).unwrap_or(Value::None)
+    }
 
-        // Follow the first matching edge
-        let edge = matching_edges[0];
-        println!("[Graph] Following edge {} -> {}", edge.source, edge.target);
-        self.follow_path(&edge.target, req)
+    fn challenge_passed(&self) -> bool {
+        self.challenge_passed
     }
 
-    /// Evaluate a rule group's conditions.
-    fn evaluate_rule_group(&self, data: &RuleGroupNodeData, req: &Request) -> bool {
-        let logic = data.logic.to_uppercase();
+    fn now(&self) -> Option<i64> {
+        Some(Utc::now().timestamp())
+    }
+}
 
-        if data.conditions.is_empty() {
-            return true; // No conditions = always match
+impl<'a> FastlyRequest<'a> {
+    fn new(req: &'a Request, request_id: &'a str, challenge_passed: bool) -> Self {
+        Self {
+            req,
+            request_id,
+            challenge_passed,
+            response: None,
+            body: None,
+            geo_cache: RefCell::new(None),
         }
+    }
 
-        let results: Vec<bool> = data.conditions.iter()
-            .map(|c| {
-                let cond = ConditionNodeData {
-                    field: c.field.clone(),
-                    operator: c.operator.clone(),
-                    value: c.value.clone(),
-                    header_name: c.header_name.clone(),
-                };
-                self.evaluate_condition(&cond, req)
-            })
-            .collect();
-
-        match logic.as_str() {
-            "AND" => results.iter().all(|&r| r),
-            "OR" => results.iter().any(|&r| r),
-            "NOT" => !results.iter().any(|&r| r),
-            _ => results.iter().all(|&r| r), // Default to AND
+    /// Get geo data for client IP, caching the result
+    fn get_geo(&self) -> Option<fastly::geo::Geo> {
+        let mut cache = self.geo_cache.borrow_mut();
+        if cache.is_none() {
+            let geo_result = match self.req.get_client_ip_addr() {
+                Some(ip) => geo_lookup(ip),
+                None => None,
+            };
+            *cache = Some(geo_result);
+        }
+        // Return a clone of the cached geo result
+        match cache.as_ref() {
+            Some(Some(geo)) => Some(geo.clone()),
+            _ => None,
         }
     }
 
-    /// Evaluate a single condition against a request.
-    fn evaluate_condition(&self, data: &ConditionNodeData, req: &Request) -> bool {
-        // For custom header field, use the headerName
-        let effective_field = if data.field == "header" {
-            data.header_name.as_deref().unwrap_or("header")
-        } else {
-            &data.field
-        };
-        let field_value = self.get_field_value(effective_field, req);
-        let field_value_str = field_value.as_deref().unwrap_or("");
-
-        println!("[Graph] Checking {} {} {} (actual: {})", effective_field, data.operator, data.value, field_value_str);
-
-        match data.operator.as_str() {
-            "equals" => field_value_str == data.value,
-            "startsWith" | "starts" => field_value_str.starts_with(&data.value),
-            "endsWith" | "ends" => field_value_str.ends_with(&data.value),
-            "contains" => field_value_str.contains(&data.value),
-            "notContains" => !field_value_str.contains(&data.value),
-            "notEquals" | "!=" => field_value_str != data.value,
-            "in" => data.value.split(',').map(|s| s.trim()).any(|v| field_value_str == v),
-            "notIn" | "!in" => !data.value.split(',').map(|s| s.trim()).any(|v| field_value_str == v),
-            "matches" => {
-                // Regex matching
-                regex::Regex::new(&data.value)
-                    .map(|re| re.is_match(field_value_str))
-                    .unwrap_or(false)
-            }
-            "inCidr" => {
-                // CIDR range matching for IP addresses
-                if let Some(ip_str) = &field_value {
-                    if let Ok(ip) = ip_str.parse::<IpAddr>() {
-                        // Support comma-separated CIDR list
-                        data.value.split(',').map(|s| s.trim()).any(|cidr| {
-                            cidr.parse::<IpNet>()
-                                .map(|net| net.contains(&ip))
-                                .unwrap_or(false)
-                        })
-                    } else {
-                        println!("[Graph] Failed to parse IP: {}", ip_str);
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            "notInCidr" => {
-                // Negated CIDR range matching - true if IP is NOT in any CIDR
-                if let Some(ip_str) = &field_value {
-                    if let Ok(ip) = ip_str.parse::<IpAddr>() {
-                        let in_any_cidr = data.value.split(',').map(|s| s.trim()).any(|cidr| {
-                            cidr.parse::<IpNet>()
-                                .map(|net| net.contains(&ip))
-                                .unwrap_or(false)
-                        });
-                        let result = !in_any_cidr;
-                        println!("[Graph] notInCidr: IP {} notIn {} = {} (in_any={})",
-                            ip_str, data.value, result, in_any_cidr);
-                        result
-                    } else {
-                        println!("[Graph] Failed to parse IP: {}", ip_str);
-                        false
-                    }
-                } else {
-                    println!("[Graph] notInCidr: no IP value found");
-                    false
-                }
-            }
-            "greaterThan" | ">" => {
-                field_value_str.parse::<f64>().ok()
-                    .zip(data.value.parse::<f64>().ok())
-                    .map(|(a, b)| a > b)
-                    .unwrap_or(false)
-            }
-            "lessThan" | "<" => {
-                field_value_str.parse::<f64>().ok()
-                    .zip(data.value.parse::<f64>().ok())
-                    .map(|(a, b)| a < b)
-                    .unwrap_or(false)
-            }
-            "greaterOrEqual" | ">=" => {
-                field_value_str.parse::<f64>().ok()
-                    .zip(data.value.parse::<f64>().ok())
-                    .map(|(a, b)| a >= b)
-                    .unwrap_or(false)
-            }
-            "lessOrEqual" | "<=" => {
-                field_value_str.parse::<f64>().ok()
-                    .zip(data.value.parse::<f64>().ok())
-                    .map(|(a, b)| a <= b)
-                    .unwrap_or(false)
-            }
-            "exists" => field_value.is_some() && !field_value_str.is_empty(),
-            "notExists" => field_value.is_none() || field_value_str.is_empty(),
-            _ => {
-                println!("[Graph] Unknown operator: {}", data.operator);
-                false
-            }
-        }
+    /// Get device data for user agent (not cached since Device doesn't implement Clone)
+    fn get_device(&self) -> Option<fastly::device_detection::Device> {
+        let ua = self.req.get_header_str("user-agent").unwrap_or("");
+        device_lookup(ua)
     }
 
     /// Get the value of a field from the request.
-    fn get_field_value(&self, field: &str, req: &Request) -> Option<String> {
+    fn get_field_value(&self, field: &str) -> Option<String> {
+        let req = self.req;
         match field {
             // ═══════════════════════════════════════════════════════════════════
             // REQUEST BASICS
@@ -737,7 +365,7 @@ impl<'a> GraphInterpreter<'a> {
             }
             "asn" => {
                 // Try native geo lookup first
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| g.as_number().to_string())
                     .or_else(|| req.get_header_str("fastly-client-geo-asn").map(|s| s.to_string()))
             }
@@ -750,62 +378,63 @@ impl<'a> GraphInterpreter<'a> {
                 // Returns "true" if Fastly has detected this request is part of a DDoS attack
                 req.get_client_ddos_detected().map(|v| v.to_string())
             }
+            "requestId" => Some(self.request_id.to_string()),
 
             // ═══════════════════════════════════════════════════════════════════
             // GEOLOCATION (Native Fastly Geo API with header fallback)
             // ═══════════════════════════════════════════════════════════════════
             "country" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| g.country_code().to_string())
                     .or_else(|| req.get_header_str("fastly-client-geo-country").map(|s| s.to_string()))
             }
             "countryCode3" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| g.country_code3().to_string())
             }
             "continent" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| format!("{:?}", g.continent()))
                     .or_else(|| req.get_header_str("fastly-client-geo-continent").map(|s| s.to_string()))
             }
             "city" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| g.city().to_string())
                     .or_else(|| req.get_header_str("fastly-client-geo-city").map(|s| s.to_string()))
             }
             "region" => {
-                self.get_geo(req)
+                self.get_geo()
                     .and_then(|g| g.region().map(|s| s.to_string()))
             }
             "postalCode" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| g.postal_code().to_string())
             }
             "latitude" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| g.latitude().to_string())
             }
             "longitude" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| g.longitude().to_string())
             }
             "metroCode" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| g.metro_code().to_string())
             }
             "utcOffset" => {
-                self.get_geo(req)
+                self.get_geo()
                     .and_then(|g| g.utc_offset().map(|uo| {
                         let (h, m, _) = uo.as_hms();
                         if m == 0 { format!("{}", h) } else { format!("{}:{:02}", h, m.abs()) }
                     }))
             }
             "connSpeed" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| format!("{:?}", g.conn_speed()))
             }
             "connType" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| format!("{:?}", g.conn_type()))
             }
 
@@ -813,16 +442,16 @@ impl<'a> GraphInterpreter<'a> {
             // PROXY/VPN DETECTION (Native Fastly Geo API)
             // ═══════════════════════════════════════════════════════════════════
             "proxyType" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| format!("{:?}", g.proxy_type()))
             }
             "proxyDescription" => {
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| format!("{:?}", g.proxy_description()))
             }
             "isHostingProvider" => {
                 // Check if proxy_description indicates hosting
-                self.get_geo(req)
+                self.get_geo()
                     .map(|g| {
                         let desc = format!("{:?}", g.proxy_description()).to_lowercase();
                         desc.contains("hosting").to_string()
@@ -834,12 +463,12 @@ impl<'a> GraphInterpreter<'a> {
             // DEVICE DETECTION (Native Fastly Device Detection API)
             // ═══════════════════════════════════════════════════════════════════
             "isBot" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.is_bot().map(|b| b.to_string()))
                     .or(Some("false".to_string()))
             }
             "botName" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| {
                         if d.is_bot().unwrap_or(false) {
                             d.device_name().map(|s| s.to_string())
@@ -849,49 +478,49 @@ impl<'a> GraphInterpreter<'a> {
                     })
             }
             "isMobile" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.is_mobile().map(|b| b.to_string()))
                     .or(Some("false".to_string()))
             }
             "isTablet" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.is_tablet().map(|b| b.to_string()))
                     .or(Some("false".to_string()))
             }
             "isDesktop" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.is_desktop().map(|b| b.to_string()))
                     .or(Some("false".to_string()))
             }
             "isSmartTV" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.is_smarttv().map(|b| b.to_string()))
                     .or(Some("false".to_string()))
             }
             "isGameConsole" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.is_gameconsole().map(|b| b.to_string()))
                     .or(Some("false".to_string()))
             }
             "deviceName" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.device_name().map(|s| s.to_string()))
             }
             "deviceBrand" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.brand().map(|s| s.to_string()))
             }
             "deviceModel" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.model().map(|s| s.to_string()))
             }
             "browserName" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.user_agent_name().map(|s| s.to_string()))
             }
             "browserVersion" => {
                 // Combine major.minor.patch versions
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| {
                         d.user_agent_major_version().map(|major| {
                             let minor = d.user_agent_minor_version().unwrap_or("0");
@@ -900,12 +529,12 @@ impl<'a> GraphInterpreter<'a> {
                     })
             }
             "osName" => {
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| d.os_name().map(|s| s.to_string()))
             }
             "osVersion" => {
                 // Combine major.minor versions
-                self.get_device(req)
+                self.get_device()
                     .and_then(|d| {
                         d.os_major_version().map(|major| {
                             let minor = d.os_minor_version().unwrap_or("0");
@@ -956,158 +585,94 @@ impl<'a> GraphInterpreter<'a> {
             }
         }
     }
+}
 
-    /// Apply a transformation operation to a value.
-    fn apply_transform(&self, operation: &str, value: &str, pattern: Option<&str>) -> String {
-        match operation {
-            "lowercase" => value.to_lowercase(),
-            "uppercase" => value.to_uppercase(),
-            "urlDecode" => {
-                // URL decode the value
-                Self::url_decode(value)
-            }
-            "base64Decode" => {
-                // Base64 decode the value
-                use base64::{Engine as _, engine::general_purpose::STANDARD};
-                STANDARD.decode(value)
-                    .ok()
-                    .and_then(|bytes| String::from_utf8(bytes).ok())
-                    .unwrap_or_else(|| {
-                        println!("[Graph] Base64 decode failed for: {}", value);
-                        value.to_string()
-                    })
-            }
-            "htmlDecode" => {
-                // HTML entity decode
-                Self::html_decode(value)
-            }
-            "removeWhitespace" => {
-                // Remove all whitespace characters
-                value.chars().filter(|c| !c.is_whitespace()).collect()
-            }
-            "extract" => {
-                // Regex extract - use capture group 1 if available
-                if let Some(pat) = pattern {
-                    match regex::Regex::new(pat) {
-                        Ok(re) => {
-                            re.captures(value)
-                                .and_then(|caps| caps.get(1).map(|m| m.as_str().to_string()))
-                                .unwrap_or_else(|| {
-                                    println!("[Graph] No match for pattern '{}' in '{}'", pat, value);
-                                    String::new()
-                                })
-                        }
-                        Err(e) => {
-                            println!("[Graph] Invalid regex pattern '{}': {}", pat, e);
-                            String::new()
-                        }
-                    }
-                } else {
-                    println!("[Graph] Extract operation requires a pattern");
-                    String::new()
-                }
-            }
-            _ => {
-                println!("[Graph] Unknown transform operation: {}", operation);
-                value.to_string()
-            }
+/// Edge Rate Limiting backed by the service's rate counter and penalty box.
+///
+/// These must be configured in fastly.toml and linked to the service.
+struct EdgeRateLimiter {
+    erl: ERL,
+    /// Separate rate counter for debug lookups (ERL takes ownership of the main one)
+    rate_counter_debug: RateCounter,
+    /// Separate penalty box handle for direct checks
+    penaltybox: Penaltybox,
+}
+
+impl EdgeRateLimiter {
+    fn open() -> Self {
+        let counter = RateCounter::open("cc_rate_counter");
+        let penaltybox = Penaltybox::open("cc_penalty_box");
+        Self {
+            erl: ERL::open(counter, penaltybox),
+            rate_counter_debug: RateCounter::open("cc_rate_counter"),
+            penaltybox: Penaltybox::open("cc_penalty_box"),
         }
     }
 
-    /// URL decode a string (percent-encoding).
-    fn url_decode(input: &str) -> String {
-        let mut result = String::with_capacity(input.len());
-        let mut chars = input.chars().peekable();
-
-        while let Some(c) = chars.next() {
-            if c == '%' {
-                // Try to read two hex digits
-                let hex: String = chars.by_ref().take(2).collect();
-                if hex.len() == 2 {
-                    if let Ok(byte) = u8::from_str_radix(&hex, 16) {
-                        result.push(byte as char);
-                        continue;
-                    }
-                }
-                // Failed to decode, keep the original
-                result.push('%');
-                result.push_str(&hex);
-            } else if c == '+' {
-                // Plus sign is space in query strings
-                result.push(' ');
-            } else {
-                result.push(c);
-            }
-        }
-        result
+    fn lookup_count(&self, entry: &str, window: RateWindow) -> u32 {
+        // Closest counter duration to the rate window
+        let duration = match window {
+            RateWindow::OneSec | RateWindow::TenSecs => CounterDuration::TenSec,
+            RateWindow::SixtySecs => CounterDuration::SixtySecs,
+        };
+        self.rate_counter_debug.lookup_count(entry, duration).unwrap_or(0)
+    }
+}
+
+impl RateLimiter for EdgeRateLimiter {
+    fn check_rate(
+        &mut self,
+        _counter_name: &str,
+        entry: &str,
+        window: RateWindow,
+        limit: u32,
+        penalty_ttl_seconds: u32,
+    ) -> Result<bool, String> {
+        // Warning for local development
+        println!("[Graph] ⚠️  Rate limiting in local dev (Viceroy) may not persist counters between requests.");
+        println!("[Graph]    Deploy to Fastly to test rate limiting behavior accurately.");
+
+        let count_before = self.lookup_count(entry, window);
+        println!("[Graph] Rate limit check for entry: {} (count: {}, limit: {}/{})",
+            entry, count_before, limit, window.display_name());
+
+        let erl_window = match window {
+            RateWindow::OneSec => fastly::erl::RateWindow::OneSec,
+            RateWindow::TenSecs => fastly::erl::RateWindow::TenSecs,
+            RateWindow::SixtySecs => fastly::erl::RateWindow::SixtySecs,
+        };
+        let ttl = Duration::from_secs(penalty_ttl_seconds.into());
+        let is_blocked = self.erl.check_rate(entry, 1, erl_window, limit, ttl)
+            .map_err(|e| format!("{:?}", e))?;
+
+        // Debug: lookup count after increment
+        let count_after = self.lookup_count(entry, window);
+        println!("[Graph] Rate limit result: blocked={}, count: {} -> {}",
+            is_blocked, count_before, count_after);
+        Ok(is_blocked)
     }
 
-    /// HTML entity decode common entities.
-    fn html_decode(input: &str) -> String {
-        let mut result = input.to_string();
-
-        // Named entities
-        result = result.replace("&amp;", "&");
-        result = result.replace("&lt;", "<");
-        result = result.replace("&gt;", ">");
-        result = result.replace("&quot;", "\"");
-        result = result.replace("&apos;", "'");
-        result = result.replace("&nbsp;", " ");
-
-        // Numeric entities (basic support)
-        // Handle &#NNN; format
-        let mut output = String::with_capacity(result.len());
-        let mut chars = result.chars().peekable();
-
-        while let Some(c) = chars.next() {
-            if c == '&' && chars.peek() == Some(&'#') {
-                chars.next(); // consume '#'
-                let mut num_str = String::new();
-                let is_hex = chars.peek() == Some(&'x') || chars.peek() == Some(&'X');
-                if is_hex {
-                    chars.next(); // consume 'x'
-                }
-
-                // Collect digits
-                while let Some(&d) = chars.peek() {
-                    if d == ';' {
-                        chars.next(); // consume ';'
-                        break;
-                    }
-                    if (is_hex && d.is_ascii_hexdigit()) || (!is_hex && d.is_ascii_digit()) {
-                        num_str.push(chars.next().unwrap());
-                    } else {
-                        break;
-                    }
-                }
-
-                // Parse and convert
-                let code_point = if is_hex {
-                    u32::from_str_radix(&num_str, 16).ok()
-                } else {
-                    num_str.parse::<u32>().ok()
-                };
-
-                if let Some(cp) = code_point {
-                    if let Some(ch) = char::from_u32(cp) {
-                        output.push(ch);
-                        continue;
-                    }
-                }
-
-                // Failed to decode, keep original
-                output.push('&');
-                output.push('#');
-                if is_hex {
-                    output.push('x');
-                }
-                output.push_str(&num_str);
-            } else {
-                output.push(c);
-            }
-        }
+    fn in_penalty_box(&mut self, _counter_name: &str, entry: &str) -> Result<bool, String> {
+        self.penaltybox.has(entry).map_err(|e| format!("{:?}", e))
+    }
 
-        output
+    fn add_to_penalty_box(&mut self, _counter_name: &str, entry: &str, ttl_seconds: u32) -> Result<(), String> {
+        self.penaltybox
+            .add(entry, Duration::from_secs(ttl_seconds.into()))
+            .map_err(|e| format!("{:?}", e))
+    }
+}
+
+/// A header as the Fastly API takes it, or `None` (logged) when the name or
+/// value isn't valid: the `&str` conversions panic on those. Values are
+/// rendered from the request, so every one goes through here.
+pub fn header_pair(name: &str, value: &str) -> Option<(HeaderName, HeaderValue)> {
+    match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+        (Ok(name), Ok(value)) => Some((name, value)),
+        _ => {
+            println!("[Graph] Skipping invalid header {}: {:?}", name, value);
+            None
+        }
     }
 }
 
@@ -1236,3 +801,35 @@ pub fn send_to_backend(
     req.send(backend)
         .map_err(|e| format!("Backend request failed: {}", e))
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cc_core::{query_param, Template};
+
+    #[test]
+    fn test_forward_without_backend_fails_closed() {
+        let result = GraphResult::from(ExecutionResult::Forward { backend: "legacy".to_string() });
+        assert!(matches!(
+            result,
+            GraphResult::FailClosed { reason } if reason == "Forward to backend 'legacy' without a Backend node"
+        ));
+    }
+
+    #[test]
+    fn test_header_pair_rejects_line_breaks() {
+        assert!(header_pair("X-Next", "/account").is_some());
+        assert!(header_pair("X-Next", "/a\r\nSet-Cookie: admin=1").is_none());
+        assert!(header_pair("X Next", "/account").is_none());
+
+        // A decoded parameter rendered into a header is cleaned before it gets here
+        let next = query_param("?next=%0D%0ASet-Cookie:%20admin=1", "next");
+        let rendered = Template::parse("${queryParam:next}").unwrap().render(|_| next.clone());
+        assert!(header_pair("X-Next", &rendered).is_none());
+        assert!(header_pair("X-Next", &cc_core::header_safe(&rendered)).is_some());
+    }
+}
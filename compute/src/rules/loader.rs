@@ -6,10 +6,14 @@
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use flate2::read::GzDecoder;
+use cc_core::FailurePolicy;
 use serde::Deserialize;
 use std::io::Read;
 
-use super::types::GraphPayload;
+use super::challenge::ChallengeConfig;
+use super::graph::BodyInspection;
+use super::types::{BackendNodeData, GraphPayload};
+use super::validator::{check_graph, ValidationPolicy};
 
 /// Combined payload stored in config store.
 /// Key is just the service ID, value is this JSON structure.
@@ -19,6 +23,57 @@ pub struct CcPayload {
     #[serde(rename = "deployedAt")]
     pub deployed_at: String,
     pub rules_packed: String,
+    /// What to do when the graph has validation errors
+    #[serde(default)]
+    pub validation: ValidationPolicy,
+    /// Where allowed, unmatched and fail-open requests go
+    #[serde(rename = "defaultBackend", default)]
+    pub default_backend: DefaultBackend,
+    /// Fail-open / fail-closed behavior per failure class
+    #[serde(rename = "failurePolicy", default)]
+    pub failure_policy: FailurePolicy,
+    /// Settings for challenge action nodes
+    #[serde(default)]
+    pub challenge: ChallengeConfig,
+    /// Size cap for request bodies buffered for body fields
+    #[serde(rename = "bodyInspection", default)]
+    pub body_inspection: BodyInspection,
+}
+
+impl CcPayload {
+    /// Decompresses and validates the packed graph.
+    pub fn graph(&self) -> Result<GraphPayload, LoadError> {
+        if self.rules_packed.is_empty() {
+            return Err(LoadError::EmptyRules);
+        }
+
+        let graph = decompress_graph(&self.rules_packed)?;
+        check_graph(&graph, self.validation)?;
+        Ok(graph)
+    }
+}
+
+/// Static backend declared in fastly.toml, used when no default is deployed.
+pub const DEFAULT_BACKEND_NAME: &str = "protected_origin";
+
+/// Backend for requests the graph doesn't route: allowed, unmatched and
+/// fail-open requests.
+///
+/// Deployed as either the name of a static backend (`"protected_origin"`)
+/// or a backend configured like a backend node (`{"name": ..., "host": ...}`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum DefaultBackend {
+    /// A backend declared on the service
+    Named(String),
+    /// A dynamic backend
+    Dynamic(Box<BackendNodeData>),
+}
+
+impl Default for DefaultBackend {
+    fn default() -> Self {
+        DefaultBackend::Named(DEFAULT_BACKEND_NAME.to_string())
+    }
 }
 
 /// Errors that can occur during graph loading.
@@ -41,6 +96,19 @@ pub enum LoadError {
 
     #[error("Empty rules_packed in payload")]
     EmptyRules,
+
+    #[error("Graph conversion error: {0}")]
+    ConvertError(#[from] cc_core::ConvertError),
+
+    #[error("Graph compile error: {}", join_errors(.0))]
+    CompileError(Vec<cc_core::CompileError>),
+
+    #[error("Graph rejected by validation: {}", join_errors(.0))]
+    Rejected(Vec<cc_core::Diagnostic>),
+}
+
+fn join_errors<E: ToString>(errors: &[E]) -> String {
+    errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; ")
 }
 
 /// Decompresses and parses graph payload from Config Store.
@@ -49,9 +117,8 @@ pub enum LoadError {
 ///
 /// The JSON must be a graph format: { nodes: [...], edges: [...] }
 pub fn decompress_graph(packed: &str) -> Result<GraphPayload, LoadError> {
-    let json = if packed.starts_with("raw:") {
+    let json = if let Some(b64) = packed.strip_prefix("raw:") {
         // Uncompressed fallback format
-        let b64 = &packed[4..];
         let bytes = BASE64.decode(b64)?;
         String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?
     } else {
@@ -76,14 +143,43 @@ pub fn decompress_graph(packed: &str) -> Result<GraphPayload, LoadError> {
     Ok(graph)
 }
 
-/// Loads graph from Config Store.
+/// Fills in the body of respond nodes that keep it in the Config Store
+/// (`bodyKey`), so pages too large to inline aren't part of the graph.
+/// A missing key is a load error, like a missing graph.
+pub fn resolve_response_bodies(
+    graph: &mut GraphPayload,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(), LoadError> {
+    for node in graph.nodes.iter_mut().filter(|n| n.node_type == "respond") {
+        let Some(key) = node.data.get("bodyKey").and_then(|k| k.as_str()).filter(|k| !k.is_empty()) else {
+            continue;
+        };
+        let body = lookup(key).ok_or_else(|| LoadError::KeyNotFound(key.to_string()))?;
+        node.data["body"] = serde_json::Value::String(body);
+    }
+    Ok(())
+}
+
+/// Loads graph from Config Store and validates it.
 ///
-/// Key format: just the `service_id`
-/// Value format: JSON with { version, deployedAt, rules_packed }
+/// With `"validation": "reject"` a graph with validation errors is a load
+/// error; by default the diagnostics are only logged.
 pub fn load_graph_from_store(
     store: &fastly::ConfigStore,
     service_id: &str,
 ) -> Result<GraphPayload, LoadError> {
+    load_payload_from_store(store, service_id)?.graph()
+}
+
+/// Loads the service's payload from Config Store, leaving the graph packed.
+///
+/// Key format: just the `service_id`
+/// Value format: JSON with { version, deployedAt, rules_packed, validation?,
+/// defaultBackend?, failurePolicy?, challenge?, bodyInspection? }
+pub fn load_payload_from_store(
+    store: &fastly::ConfigStore,
+    service_id: &str,
+) -> Result<CcPayload, LoadError> {
     // Key is just the service ID
     let payload_json = store
         .get(service_id)
@@ -95,11 +191,7 @@ pub fn load_graph_from_store(
     let payload: CcPayload = serde_json::from_str(&payload_json)?;
     println!("Payload version: {}, deployed: {}", payload.version, payload.deployed_at);
 
-    if payload.rules_packed.is_empty() {
-        return Err(LoadError::EmptyRules);
-    }
-
-    decompress_graph(&payload.rules_packed)
+    Ok(payload)
 }
 
 #[cfg(test)]
@@ -164,6 +256,76 @@ mod tests {
         assert!(matches!(result, Err(LoadError::InvalidFormat)));
     }
 
+    #[test]
+    fn test_default_backend_forms() {
+        let payload: CcPayload = serde_json::from_str(
+            r#"{"version": "1", "deployedAt": "now", "rules_packed": ""}"#,
+        ).unwrap();
+        assert_eq!(payload.default_backend, DefaultBackend::Named("protected_origin".to_string()));
+
+        let payload: CcPayload = serde_json::from_str(
+            r#"{"version": "1", "deployedAt": "now", "rules_packed": "", "defaultBackend": "origin_b"}"#,
+        ).unwrap();
+        assert_eq!(payload.default_backend, DefaultBackend::Named("origin_b".to_string()));
+
+        let payload: CcPayload = serde_json::from_str(
+            r#"{"version": "1", "deployedAt": "now", "rules_packed": "",
+                "defaultBackend": {"name": "origin", "host": "origin.example.com", "port": 8443}}"#,
+        ).unwrap();
+        match payload.default_backend {
+            DefaultBackend::Dynamic(backend) => {
+                assert_eq!(backend.host, "origin.example.com");
+                assert_eq!(backend.port, Some(8443));
+            }
+            other => panic!("expected a dynamic backend, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_failure_policy_defaults_open() {
+        let payload: CcPayload = serde_json::from_str(
+            r#"{"version": "1", "deployedAt": "now", "rules_packed": "",
+                "failurePolicy": {"load": "closed", "rateLimit": "closed"}}"#,
+        ).unwrap();
+
+        let policy = payload.failure_policy;
+        assert!(policy.load.is_closed());
+        assert!(policy.rate_limit.is_closed());
+        assert!(!policy.backend.is_closed());
+        assert!(!policy.step_limit.is_closed());
+        assert!(!policy.body_limit.is_closed());
+        assert_eq!(policy.invalid_node, None);
+    }
+
+    #[test]
+    fn test_body_inspection_cap() {
+        let parse = |json: &str| serde_json::from_str::<CcPayload>(json).unwrap().body_inspection;
+        assert_eq!(parse(r#"{"version": "1", "deployedAt": "now", "rules_packed": ""}"#).max_bytes, 16 * 1024);
+        let payload = r#"{"version": "1", "deployedAt": "now", "rules_packed": "",
+            "bodyInspection": {"maxBytes": 1024}}"#;
+        assert_eq!(parse(payload).max_bytes, 1024);
+    }
+
+    #[test]
+    fn test_response_bodies_read_from_store() {
+        let json = r#"{"nodes":[
+            {"id":"1","type":"respond","position":{"x":0,"y":0},"data":{"bodyKey":"page_maintenance"}},
+            {"id":"2","type":"respond","position":{"x":0,"y":0},"data":{"body":"inline"}}
+        ],"edges":[]}"#;
+        let mut graph = decompress_graph(&format!("raw:{}", BASE64.encode(json))).unwrap();
+
+        let store = |key: &str| (key == "page_maintenance").then(|| "<h1>Back soon</h1>".to_string());
+        resolve_response_bodies(&mut graph, store).unwrap();
+        assert_eq!(graph.nodes[0].data["body"], "<h1>Back soon</h1>");
+        assert_eq!(graph.nodes[1].data["body"], "inline");
+
+        graph.nodes[0].data["bodyKey"] = "page_missing".into();
+        assert!(matches!(
+            resolve_response_bodies(&mut graph, store),
+            Err(LoadError::KeyNotFound(key)) if key == "page_missing"
+        ));
+    }
+
     #[test]
     fn test_decompress_gzip_format() {
         // Test gzip compressed graph format
@@ -6,9 +6,11 @@
 //! - Performance metrics
 //! - Security actions taken
 
+use cc_core::SecurityEvent;
 use chrono::Utc;
 use fastly::{Request, Response};
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::time::Instant;
 use uuid::{timestamp::Timestamp, NoContext, Uuid};
 
@@ -48,6 +50,19 @@ struct ResponseDetails {
     headers: Vec<(String, String)>,
 }
 
+/// One try at sending the request to a backend node's origin.
+///
+/// Logged when a backend has a retry policy or fallback, so failovers can be
+/// traced: either the response status or the error is set.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendAttempt {
+    pub backend: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 /// Complete log entry for a request processed by the WAF.
 ///
 /// This is the main logging structure that combines:
@@ -63,6 +78,21 @@ pub struct WafLog {
     response: Option<ResponseDetails>,
     pub final_action: String,
     pub blocked: bool,
+    /// Events recorded by log nodes on the way through the graph
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<SecurityEvent>,
+    /// Rule that matched in each rule list, by list name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub matched_rules: BTreeMap<String, String>,
+    /// Bucket each split node put the request in, by split name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub buckets: BTreeMap<String, String>,
+    /// Nodes passed through because they were outside their schedule
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inactive_nodes: Vec<String>,
+    /// Backend attempts, in order (only for backends with retries or a fallback)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<BackendAttempt>,
     #[serde(skip)]
     start_time: Instant,
 }
@@ -78,7 +108,7 @@ impl WafLog {
     pub fn new(req: &Request, start_time: Instant) -> Self {
         let now = Utc::now();
         let ts = Timestamp::from_unix(
-            &NoContext,
+            NoContext,
             now.timestamp() as u64,
             now.timestamp_subsec_nanos(),
         );
@@ -123,6 +153,11 @@ impl WafLog {
             response: None,
             final_action: "initializing".to_string(),
             blocked: false,
+            events: Vec::new(),
+            matched_rules: BTreeMap::new(),
+            buckets: BTreeMap::new(),
+            inactive_nodes: Vec::new(),
+            attempts: Vec::new(),
         }
     }
 
@@ -3,12 +3,17 @@
 //! This module provides graph-based rule evaluation for the Fastly Compute service.
 //! Rules are stored as visual graphs (nodes + edges) that match the editor format exactly.
 
+mod challenge;
+mod failover;
 mod graph;
 mod loader;
 mod logging;
 mod types;
+mod validator;
 
-pub use graph::{GraphInterpreter, GraphResult, HeaderMod, send_to_backend};
-pub use loader::load_graph_from_store;
-pub use logging::WafLog;
+pub use challenge::{challenge_response, has_clearance, verify_response, CHALLENGE_PATH};
+pub use graph::{GraphInterpreter, GraphResult, HeaderMod, TarpitThen, header_pair, send_to_backend};
+pub use failover::plan_attempts;
+pub use loader::{load_graph_from_store, load_payload_from_store, resolve_response_bodies, DefaultBackend};
+pub use logging::{BackendAttempt, WafLog};
 pub use types::BackendNodeData;
@@ -2,243 +2,9 @@
 //!
 //! These types match the editor's node/edge structure exactly,
 //! allowing rules to be stored and loaded without conversion.
+//! They live in cc-core so the editor and the edge share one definition.
 
-use serde::{Deserialize, Serialize};
-
-// ============================================================================
-// Graph Structure (matches editor's React Flow format)
-// ============================================================================
-
-/// The complete graph payload from the editor.
-/// This is the single format used for both storage and runtime evaluation.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct GraphPayload {
-    pub nodes: Vec<GraphNode>,
-    pub edges: Vec<GraphEdge>,
-}
-
-/// A single node in the visual graph.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct GraphNode {
-    pub id: String,
-    #[serde(rename = "type")]
-    pub node_type: String,
-    pub position: NodePosition,
-    pub data: serde_json::Value,
-}
-
-/// Node position for layout (preserved for editor reload).
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct NodePosition {
-    pub x: f64,
-    pub y: f64,
-}
-
-/// An edge connecting two nodes.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct GraphEdge {
-    pub id: String,
-    pub source: String,
-    pub target: String,
-    #[serde(rename = "sourceHandle")]
-    pub source_handle: Option<String>,
-    #[serde(rename = "targetHandle")]
-    pub target_handle: Option<String>,
-    #[serde(rename = "type")]
-    pub edge_type: Option<String>,
-}
-
-// ============================================================================
-// Node Data Types (parsed from GraphNode.data)
-// ============================================================================
-
-/// Node data for condition nodes.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ConditionNodeData {
-    pub field: String,
-    pub operator: String,
-    pub value: String,
-    /// Custom header name (when field is "header")
-    #[serde(rename = "headerName")]
-    pub header_name: Option<String>,
-}
-
-/// Node data for ruleGroup nodes (inline conditions with match/noMatch outputs).
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct RuleGroupNodeData {
-    pub name: Option<String>,
-    pub logic: String,
-    pub conditions: Vec<RuleGroupCondition>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct RuleGroupCondition {
-    pub id: String,
-    pub field: String,
-    pub operator: String,
-    pub value: String,
-    /// Custom header name (when field is "header")
-    #[serde(rename = "headerName")]
-    pub header_name: Option<String>,
-}
-
-/// Node data for action nodes.
-/// Supports: block, allow, challenge, log, redirect
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ActionNodeData {
-    pub action: String,
-    #[serde(rename = "statusCode")]
-    pub status_code: Option<u16>,
-    pub message: Option<String>,
-    /// Redirect URL (when action is "redirect")
-    pub url: Option<String>,
-    /// Preserve query string on redirect (when action is "redirect")
-    #[serde(rename = "preserveQuery")]
-    pub preserve_query: Option<bool>,
-}
-
-/// Node data for backend nodes - full parity with Fastly BackendBuilder.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct BackendNodeData {
-    // Basic
-    pub name: String,
-    pub host: String,
-    pub port: Option<u16>,
-
-    // Timeouts (ms)
-    #[serde(rename = "connectTimeout")]
-    pub connect_timeout: Option<u64>,
-    #[serde(rename = "firstByteTimeout")]
-    pub first_byte_timeout: Option<u64>,
-    #[serde(rename = "betweenBytesTimeout")]
-    pub between_bytes_timeout: Option<u64>,
-
-    // SSL/TLS
-    #[serde(rename = "useTLS")]
-    pub use_tls: Option<bool>,
-    #[serde(rename = "verifyCertificate")]
-    pub verify_certificate: Option<bool>,
-    #[serde(rename = "sniHostname")]
-    pub sni_hostname: Option<String>,
-    #[serde(rename = "caCertificate")]
-    pub ca_certificate: Option<String>,
-    #[serde(rename = "clientCertificate")]
-    pub client_certificate: Option<String>,
-    #[serde(rename = "clientKey")]
-    pub client_key: Option<String>,
-    #[serde(rename = "minTLSVersion")]
-    pub min_tls_version: Option<String>,
-    #[serde(rename = "maxTLSVersion")]
-    pub max_tls_version: Option<String>,
-
-    // Host
-    #[serde(rename = "overrideHost")]
-    pub override_host: Option<String>,
-    #[serde(rename = "preferIPv6")]
-    pub prefer_ipv6: Option<bool>,
-
-    // Connection Pooling
-    #[serde(rename = "enablePooling")]
-    pub enable_pooling: Option<bool>,
-    #[serde(rename = "keepaliveTime")]
-    pub keepalive_time: Option<u64>,
-    #[serde(rename = "maxConnections")]
-    pub max_connections: Option<u32>,
-    #[serde(rename = "maxConnectionUses")]
-    pub max_connection_uses: Option<u32>,
-    #[serde(rename = "maxConnectionLifetime")]
-    pub max_connection_lifetime: Option<u64>,
-
-    // TCP Keepalive
-    #[serde(rename = "tcpKeepalive")]
-    pub tcp_keepalive: Option<bool>,
-    #[serde(rename = "tcpKeepaliveTime")]
-    pub tcp_keepalive_time: Option<u64>,
-    #[serde(rename = "tcpKeepaliveInterval")]
-    pub tcp_keepalive_interval: Option<u64>,
-    #[serde(rename = "tcpKeepaliveProbes")]
-    pub tcp_keepalive_probes: Option<u32>,
-
-    // Edge Auth - HMAC signature for origin verification
-    #[serde(rename = "edgeAuthSecret")]
-    pub edge_auth_secret: Option<String>,
-}
-
-/// Node data for rateLimit nodes.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct RateLimitNodeData {
-    /// Maximum requests allowed in the window
-    pub limit: u32,
-    /// Time window: "second", "minute", or "hour"
-    #[serde(rename = "windowUnit")]
-    pub window_unit: String,
-    /// Key to identify clients: "ip", "fingerprint", "header", or "path"
-    #[serde(rename = "keyBy")]
-    pub key_by: String,
-    /// Header name when keyBy is "header"
-    #[serde(rename = "headerName")]
-    pub header_name: Option<String>,
-}
-
-/// Node data for header nodes.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct HeaderNodeData {
-    /// Operation: "set", "append", or "remove"
-    pub operation: String,
-    /// Header name
-    pub name: String,
-    /// Header value (for set/append operations)
-    pub value: Option<String>,
-}
-
-/// Node data for cache control nodes.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct CacheNodeData {
-    /// Mode: "configure" or "pass"
-    pub mode: String,
-    /// Time-to-live value
-    pub ttl: Option<u64>,
-    /// TTL unit: "seconds", "minutes", "hours", "days"
-    #[serde(rename = "ttlUnit")]
-    pub ttl_unit: Option<String>,
-    /// Stale-while-revalidate duration
-    #[serde(rename = "staleWhileRevalidate")]
-    pub stale_while_revalidate: Option<u64>,
-    /// SWR unit: "seconds", "minutes", "hours"
-    #[serde(rename = "swrUnit")]
-    pub swr_unit: Option<String>,
-    /// Space-separated surrogate keys for cache purging
-    #[serde(rename = "surrogateKeys")]
-    pub surrogate_keys: Option<String>,
-}
-
-/// Node data for redirect nodes.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct RedirectNodeData {
-    /// Target URL
-    pub url: String,
-    /// HTTP status code (301, 302, 307, 308)
-    #[serde(rename = "statusCode")]
-    pub status_code: Option<u16>,
-    /// Whether to preserve the query string
-    #[serde(rename = "preserveQuery")]
-    pub preserve_query: Option<bool>,
-}
-
-/// Node data for transform nodes.
-/// Transforms field values using various operations.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct TransformNodeData {
-    /// Transform operation: lowercase, uppercase, urlDecode, base64Decode, htmlDecode, removeWhitespace, extract
-    pub operation: String,
-    /// Source field to transform: path, query, body, userAgent, header, cookie
-    pub field: String,
-    /// Regex pattern for extract operation (capture group 1 is used)
-    pub pattern: Option<String>,
-    /// Output variable name to store the result
-    #[serde(rename = "outputVar")]
-    pub output_var: Option<String>,
-}
+pub use cc_core::payload::*;
 
 // ============================================================================
 // Tests
@@ -480,6 +246,9 @@ mod tests {
             tcp_keepalive_time: Some(7200),
             tcp_keepalive_interval: Some(75),
             tcp_keepalive_probes: Some(9),
+            edge_auth_secret: None,
+            fallback: None,
+            retry: None,
         };
 
         // Serialize to JSON
@@ -805,7 +574,7 @@ mod tests {
         assert_eq!(data.field, "header");
         assert_eq!(data.operator, "equals");
         assert_eq!(data.value, "application/json");
-        assert_eq!(data.header_name, Some("X-Content-Type".to_string()));
+        assert_eq!(data.names.header_name, Some("X-Content-Type".to_string()));
     }
 
     #[test]
@@ -821,7 +590,7 @@ mod tests {
         assert_eq!(data.field, "path");
         assert_eq!(data.operator, "contains");
         assert_eq!(data.value, "/api/");
-        assert_eq!(data.header_name, None);
+        assert_eq!(data.names.header_name, None);
     }
 
     #[test]
@@ -856,14 +625,14 @@ mod tests {
         let cond1 = &data.conditions[0];
         assert_eq!(cond1.field, "header");
         assert_eq!(cond1.operator, "exists");
-        assert_eq!(cond1.header_name, Some("Authorization".to_string()));
+        assert_eq!(cond1.names.header_name, Some("Authorization".to_string()));
 
         // Verify second condition - check Authorization starts with "Bearer "
         let cond2 = &data.conditions[1];
         assert_eq!(cond2.field, "header");
         assert_eq!(cond2.operator, "startsWith");
         assert_eq!(cond2.value, "Bearer ");
-        assert_eq!(cond2.header_name, Some("Authorization".to_string()));
+        assert_eq!(cond2.names.header_name, Some("Authorization".to_string()));
     }
 
     #[test]
@@ -914,7 +683,7 @@ mod tests {
         assert_eq!(cond_data.field, "header");
         assert_eq!(cond_data.operator, "equals");
         assert_eq!(cond_data.value, "secret-token");
-        assert_eq!(cond_data.header_name, Some("X-API-Key".to_string()));
+        assert_eq!(cond_data.names.header_name, Some("X-API-Key".to_string()));
     }
 
     // ========================================================================
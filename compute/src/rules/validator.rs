@@ -0,0 +1,91 @@
+//! Load-time graph validation.
+//!
+//! Runs the cc-core validator (the same checks the editor runs before a
+//! deploy) on every graph loaded from the Config Store, and applies the
+//! deployed validation policy: reject a graph with errors, or log the
+//! diagnostics and keep evaluating it.
+
+use cc_core::{validate_at, Diagnostic};
+use chrono::Utc;
+use serde::Deserialize;
+
+use super::loader::LoadError;
+use super::types::GraphPayload;
+
+/// What to do with a graph that has validation errors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationPolicy {
+    /// Refuse to load the graph (the request then fails open)
+    Reject,
+    /// Log the diagnostics and evaluate the graph anyway
+    #[default]
+    Warn,
+}
+
+/// Validate a graph and apply the policy.
+///
+/// Diagnostics are always logged. Warnings (including nodes whose
+/// schedule has ended) never reject a graph. Returns the diagnostics so
+/// callers can report them.
+pub fn check_graph(graph: &GraphPayload, policy: ValidationPolicy) -> Result<Vec<Diagnostic>, LoadError> {
+    let diagnostics = validate_at(graph, Some(Utc::now().timestamp()));
+    for diagnostic in &diagnostics {
+        println!("Graph validation {}", diagnostic);
+    }
+
+    if policy == ValidationPolicy::Reject && diagnostics.iter().any(Diagnostic::is_error) {
+        return Err(LoadError::Rejected(diagnostics));
+    }
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BAD_REGEX: &str = r#"{
+        "nodes": [
+            {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+            {"id": "cond", "type": "condition", "position": {"x": 100, "y": 0},
+             "data": {"field": "path", "operator": "matches", "value": "(unclosed"}}
+        ],
+        "edges": [{"id": "e1", "source": "req", "target": "cond"}]
+    }"#;
+
+    #[test]
+    fn test_reject_policy_rejects_errors() {
+        let graph: GraphPayload = serde_json::from_str(BAD_REGEX).unwrap();
+
+        match check_graph(&graph, ValidationPolicy::Reject) {
+            Err(LoadError::Rejected(diagnostics)) => {
+                assert_eq!(diagnostics.len(), 1);
+                assert_eq!(diagnostics[0].node_id.as_deref(), Some("cond"));
+            }
+            other => panic!("expected rejection, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_warn_policy_keeps_graph() {
+        let graph: GraphPayload = serde_json::from_str(BAD_REGEX).unwrap();
+
+        let diagnostics = check_graph(&graph, ValidationPolicy::Warn).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_reject_policy_allows_warnings() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "req2", "type": "request", "position": {"x": 0, "y": 0}, "data": {}}
+            ],
+            "edges": []
+        }"#;
+        let graph: GraphPayload = serde_json::from_str(json).unwrap();
+
+        let diagnostics = check_graph(&graph, ValidationPolicy::Reject).unwrap();
+        assert!(!diagnostics[0].is_error());
+    }
+}
@@ -4,8 +4,8 @@
 
 use crate::graph::{Edge, Graph};
 use crate::nodes::{
-    ActionType, ChallengeType, ConditionValue, HeaderOp, LogSeverity, Node, NodeKind, Operator,
-    RateLimitMode, RateWindow, RequestField,
+    ActionType, ConditionValue, HeaderOp, Node, NodeKind, Operator, RateLimitKey, RateLimitMode,
+    RateWindow, RequestField,
 };
 use flate2::write::GzEncoder;
 use flate2::Compression;
@@ -21,14 +21,15 @@ fn generate_test_graph(num_nodes: usize) -> Graph {
         id: 0,
         kind: NodeKind::Request,
         position: (0.0, 0.0),
+        editor_id: None,
+        active: None,
     };
     graph.add_node(request_node);
 
-    let mut current_id = 1u32;
-    let mut last_condition_id = 0u32;
 
     // Generate a variety of nodes to simulate realistic graphs
     for i in 1..num_nodes {
+        let current_id = i as u32;
         let node = match i % 8 {
             0 => Node {
                 id: current_id,
@@ -38,6 +39,8 @@ fn generate_test_graph(num_nodes: usize) -> Graph {
                     value: ConditionValue::String("/admin".to_string()),
                 },
                 position: (i as f32 * 200.0, 0.0),
+                editor_id: None,
+                active: None,
             },
             1 => Node {
                 id: current_id,
@@ -51,6 +54,8 @@ fn generate_test_graph(num_nodes: usize) -> Graph {
                     ]),
                 },
                 position: (i as f32 * 200.0, 100.0),
+                editor_id: None,
+                active: None,
             },
             2 => Node {
                 id: current_id,
@@ -60,16 +65,22 @@ fn generate_test_graph(num_nodes: usize) -> Graph {
                     value: ConditionValue::String("bot".to_string()),
                 },
                 position: (i as f32 * 200.0, 200.0),
+                editor_id: None,
+                active: None,
             },
             3 => Node {
                 id: current_id,
                 kind: NodeKind::And { input_count: 2 },
                 position: (i as f32 * 200.0, 300.0),
+                editor_id: None,
+                active: None,
             },
             4 => Node {
                 id: current_id,
                 kind: NodeKind::Or { input_count: 3 },
                 position: (i as f32 * 200.0, 400.0),
+                editor_id: None,
+                active: None,
             },
             5 => Node {
                 id: current_id,
@@ -79,8 +90,11 @@ fn generate_test_graph(num_nodes: usize) -> Graph {
                     window: RateWindow::SixtySecs,
                     threshold: 100,
                     penalty_ttl_seconds: 300,
+                    key: RateLimitKey::Ip,
                 },
                 position: (i as f32 * 200.0, 500.0),
+                editor_id: None,
+                active: None,
             },
             6 => Node {
                 id: current_id,
@@ -91,6 +105,8 @@ fn generate_test_graph(num_nodes: usize) -> Graph {
                     },
                 },
                 position: (i as f32 * 200.0, 600.0),
+                editor_id: None,
+                active: None,
             },
             7 => Node {
                 id: current_id,
@@ -100,17 +116,13 @@ fn generate_test_graph(num_nodes: usize) -> Graph {
                     value: Some("passed".to_string()),
                 },
                 position: (i as f32 * 200.0, 700.0),
+                editor_id: None,
+                active: None,
             },
             _ => unreachable!(),
         };
 
-        // Track last condition for edge generation
-        if matches!(node.kind, NodeKind::Condition { .. }) {
-            last_condition_id = current_id;
-        }
-
         graph.add_node(node);
-        current_id += 1;
     }
 
     // Add some edges to make the graph realistic
@@ -0,0 +1,116 @@
+//! Request body inspection for the `body`, `bodyLength`, `json:<pointer>`
+//! and `form:<field>` fields.
+//!
+//! The edge buffers a body only when the graph reads one of these fields,
+//! and then no more than a configured number of bytes. A body longer than
+//! that is truncated: `body` is its beginning, `bodyLength` its full
+//! length, and JSON and form fields have no value, since a cut-off
+//! document can't be parsed reliably. The failure policy decides whether
+//! such a request is inspected this way or refused.
+
+use crate::params::query_param;
+use crate::{RequestField, Value};
+use std::cell::OnceCell;
+
+/// A request body buffered for inspection.
+#[derive(Debug, Clone, Default)]
+pub struct RequestBody {
+    /// The buffered bytes as text (invalid UTF-8 replaced)
+    text: String,
+    /// Length of the whole body in bytes
+    length: u64,
+    /// The body is longer than what was buffered
+    truncated: bool,
+    /// Body parsed as JSON on first use; `None` when it isn't JSON
+    json: OnceCell<Option<serde_json::Value>>,
+}
+
+impl RequestBody {
+    /// A body of `length` bytes of which `bytes` were buffered.
+    pub fn new(bytes: &[u8], length: u64, truncated: bool) -> Self {
+        Self {
+            text: String::from_utf8_lossy(bytes).into_owned(),
+            length,
+            truncated,
+            json: OnceCell::new(),
+        }
+    }
+
+    /// A body buffered in full (editor preview, tests).
+    pub fn complete(text: &str) -> Self {
+        Self::new(text.as_bytes(), text.len() as u64, false)
+    }
+
+    /// Whether the body is longer than what was buffered.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Value of a body field; other fields have no value here.
+    pub fn field(&self, field: &RequestField) -> Value {
+        match field {
+            RequestField::Body => Value::String(self.text.clone()),
+            RequestField::BodyLength => Value::Number(self.length as f64),
+            RequestField::BodyJson { .. } | RequestField::BodyForm { .. } if self.truncated => Value::None,
+            RequestField::BodyJson { pointer } => self
+                .json()
+                .and_then(|json| json.pointer(pointer))
+                .map(json_value)
+                .unwrap_or(Value::None),
+            // Form bodies are encoded like query strings
+            RequestField::BodyForm { name } => query_param(&self.text, name),
+            _ => Value::None,
+        }
+    }
+
+    fn json(&self) -> Option<&serde_json::Value> {
+        self.json.get_or_init(|| serde_json::from_str(&self.text).ok()).as_ref()
+    }
+}
+
+/// Scalars keep their type; arrays and objects are compared as JSON text.
+fn json_value(json: &serde_json::Value) -> Value {
+    match json {
+        serde_json::Value::Null => Value::None,
+        serde_json::Value::Bool(b) => Value::Bool(*b),
+        serde_json::Value::Number(n) => n.as_f64().map(Value::Number).unwrap_or(Value::None),
+        serde_json::Value::String(s) => Value::String(s.clone()),
+        other => Value::String(other.to_string()),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(pointer: &str) -> RequestField {
+        RequestField::BodyJson { pointer: pointer.to_string() }
+    }
+
+    #[test]
+    fn test_json_and_form_fields() {
+        let body = RequestBody::complete(r#"{"user": {"name": "<script>", "age": 42}, "tags": ["a", "b"]}"#);
+        assert_eq!(body.field(&json("/user/name")), Value::String("<script>".to_string()));
+        assert_eq!(body.field(&json("/user/age")), Value::Number(42.0));
+        assert_eq!(body.field(&json("/tags")).as_text(), r#"["a","b"]"#);
+        assert_eq!(body.field(&json("/missing")), Value::None);
+
+        let form = RequestBody::complete("user=admin&q=1%27+OR+1%3D1");
+        let field = |name: &str| form.field(&RequestField::BodyForm { name: name.to_string() });
+        assert_eq!(field("q"), Value::String("1' OR 1=1".to_string()));
+        assert_eq!(form.field(&json("/q")), Value::None);
+    }
+
+    #[test]
+    fn test_truncated_body_keeps_prefix_only() {
+        let body = RequestBody::new(br#"{"q": "abc"#, 5000, true);
+        assert_eq!(body.field(&RequestField::Body), Value::String(r#"{"q": "abc"#.to_string()));
+        assert_eq!(body.field(&RequestField::BodyLength), Value::Number(5000.0));
+        assert_eq!(body.field(&json("/q")), Value::None);
+        assert_eq!(body.field(&RequestField::BodyForm { name: "q".to_string() }), Value::None);
+    }
+}
@@ -0,0 +1,1541 @@
+//! Conversion from the editor's graph format into a typed [`Graph`].
+//!
+//! The editor stores React Flow nodes (string ids, loosely typed `data`)
+//! and edges that pick an output with a `sourceHandle` name. This module
+//! turns that into cc-core nodes with numeric ids and port indices, so
+//! the editor preview and the edge run the exact same graph.
+//!
+//! Node data that doesn't parse becomes [`NodeKind::Invalid`] rather than
+//! an error: the interpreter then applies the same per-type fallback the
+//! edge has always used (fail open for flow nodes, 500 for actions).
+
+use crate::payload::{
+    ActionNodeData, BackendNodeData, BackendPoolNodeData, CacheNodeData, ConditionNodeData,
+    FieldNames, GraphNode, GraphPayload, HeaderNodeData, RateLimitNodeData, RedirectNodeData,
+    RespondNodeData, RuleGroupCondition, RuleGroupNodeData, RuleListNodeData, RuleSubGroup,
+    SplitNodeData, SwitchNodeData, TransformNodeData,
+};
+use crate::{
+    parse_time, ActionType, ActiveWindow, CacheMode, ChallengeType, ConditionGroup, ConditionValue, Edge, Graph, HeaderOp, Logic, LogSeverity,
+    ListRule, Node, NodeId, NodeKind, Operator, PoolStrategy, RateLimitKey, RateLimitMode, RateWindow,
+    RequestField, RuleCondition, SplitBucket, SplitKey, SwitchCase, SwitchMatch, TarpitThen,
+    TransformOp,
+};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+/// Rate counter used for editor rate limit nodes (must exist on the service)
+pub const RATE_COUNTER_NAME: &str = "cc_rate_counter";
+
+/// Cookie that pins clients to a pool member when a pool doesn't name one
+pub const DEFAULT_POOL_COOKIE: &str = "cc_pool";
+
+/// Content type of a respond node that doesn't set one
+const DEFAULT_RESPOND_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Delay of a tarpit node that doesn't set one
+const DEFAULT_TARPIT_DELAY_MS: u32 = 5000;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ConvertError {
+    #[error("Duplicate node id: {0}")]
+    DuplicateNodeId(String),
+}
+
+/// Convert an editor graph into a typed graph.
+///
+/// Edges whose source or target doesn't exist, or whose `sourceHandle`
+/// isn't an output of the source node, can never be followed and are
+/// dropped. The rest are ordered by `priority`, keeping editor order for
+/// equal priorities; that is the order the interpreter runs them in.
+pub fn from_payload(payload: &GraphPayload) -> Result<Graph, ConvertError> {
+    let mut graph = Graph::new("");
+    let mut ids: HashMap<&str, NodeId> = HashMap::new();
+
+    for node in &payload.nodes {
+        if ids.contains_key(node.id.as_str()) {
+            return Err(ConvertError::DuplicateNodeId(node.id.clone()));
+        }
+        let id = graph.add_node(Node {
+            id: 0, // Assigned by add_node
+            kind: convert_node(node),
+            position: (node.position.x as f32, node.position.y as f32),
+            editor_id: Some(node.id.clone()),
+            active: active_window(&node.data).ok().flatten(),
+        });
+        ids.insert(&node.id, id);
+    }
+
+    let mut edges: Vec<_> = payload.edges.iter().collect();
+    edges.sort_by_key(|e| e.priority.unwrap_or(u32::MAX));
+
+    for edge in edges {
+        let (Some(&from_node), Some(&to_node)) =
+            (ids.get(edge.source.as_str()), ids.get(edge.target.as_str()))
+        else {
+            continue;
+        };
+        let Some(kind) = graph.get_node(from_node).map(|n| &n.kind) else {
+            continue;
+        };
+        let Some(from_port) = output_port(kind, edge.source_handle.as_deref()) else {
+            continue;
+        };
+        graph.edges.push(Edge {
+            from_node,
+            from_port,
+            to_node,
+            to_port: 0,
+        });
+    }
+
+    Ok(graph)
+}
+
+/// Port index for an editor source handle.
+pub(crate) fn output_port(kind: &NodeKind, handle: Option<&str>) -> Option<u8> {
+    match kind {
+        // The request and response nodes have a single output; any handle leaves it
+        NodeKind::Request | NodeKind::Response => Some(0),
+        NodeKind::Invalid { node_type, .. } => invalid_port(node_type, handle),
+        _ => {
+            let handle = handle?;
+            kind.outputs()
+                .iter()
+                .position(|p| p.name == handle)
+                .and_then(|i| u8::try_from(i).ok())
+        }
+    }
+}
+
+/// Edges kept for a node whose data didn't parse: only the path its
+/// fallback follows (port 0).
+fn invalid_port(node_type: &str, handle: Option<&str>) -> Option<u8> {
+    match node_type {
+        "rateLimit" => (handle == Some("ok")).then_some(0),
+        "cache" => (handle == Some("next")).then_some(0),
+        "transform" => (handle == Some("value_out")).then_some(0),
+        "switch" => (handle == Some("default")).then_some(0),
+        "ruleList" => (handle == Some("noMatch")).then_some(0),
+        "request" | "condition" | "ruleGroup" | "action" | "backend" | "backendPool"
+        | "redirect" | "respond" => None,
+        // Header and unknown node types continue on any edge
+        _ => Some(0),
+    }
+}
+
+pub(crate) fn convert_node(node: &GraphNode) -> NodeKind {
+    if let Err(error) = active_window(&node.data) {
+        return NodeKind::Invalid { node_type: node.node_type.clone(), error };
+    }
+    let kind = match node.node_type.as_str() {
+        "request" => Ok(NodeKind::Request),
+        "response" => Ok(NodeKind::Response),
+        "condition" => parse(&node.data).and_then(condition_kind),
+        "ruleGroup" => parse(&node.data).and_then(rule_group_kind),
+        "ruleList" => parse(&node.data).and_then(rule_list_kind),
+        "switch" => parse(&node.data).and_then(switch_kind),
+        "action" => parse(&node.data).map(action_kind),
+        "redirect" => parse(&node.data).map(redirect_kind),
+        "respond" => parse(&node.data).map(respond_kind),
+        "backend" => parse::<BackendNodeData>(&node.data).map(|config| NodeKind::Backend {
+            config: Box::new(config),
+        }),
+        "backendPool" => parse(&node.data).and_then(backend_pool_kind),
+        "rateLimit" => parse(&node.data).and_then(rate_limit_kind),
+        "header" => parse(&node.data).and_then(header_kind),
+        "cache" => parse(&node.data).and_then(cache_kind),
+        "transform" => parse(&node.data).and_then(transform_kind),
+        "split" => parse(&node.data).and_then(split_kind),
+        _ => Err("Unknown node type".to_string()),
+    };
+
+    kind.unwrap_or_else(|error| NodeKind::Invalid {
+        node_type: node.node_type.clone(),
+        error,
+    })
+}
+
+fn parse<T: DeserializeOwned>(data: &serde_json::Value) -> Result<T, String> {
+    serde_json::from_value(data.clone()).map_err(|e| e.to_string())
+}
+
+/// The `activeFrom` / `activeUntil` window any node's data may carry.
+/// Both are dates or RFC 3339 times (UTC without an offset); an
+/// `activeUntil` date includes that whole day. Empty values are ignored.
+pub(crate) fn active_window(data: &serde_json::Value) -> Result<Option<ActiveWindow>, String> {
+    let bound = |key: &str, end: bool| -> Result<Option<i64>, String> {
+        let text = match data.get(key) {
+            None | Some(serde_json::Value::Null) => return Ok(None),
+            Some(serde_json::Value::String(text)) if text.trim().is_empty() => return Ok(None),
+            Some(serde_json::Value::String(text)) => Some(text.as_str()),
+            Some(_) => None,
+        };
+        text.and_then(|text| parse_time(text, end))
+            .map(|secs| Some(secs.floor() as i64))
+            .ok_or_else(|| format!("{} {} is not a date or time like 2026-10-16T08:00:00Z", key, data[key]))
+    };
+    let window = ActiveWindow { from: bound("activeFrom", false)?, until: bound("activeUntil", true)? };
+    match window {
+        ActiveWindow { from: None, until: None } => Ok(None),
+        ActiveWindow { from: Some(from), until: Some(until) } if from > until => {
+            Err("activeFrom is after activeUntil".to_string())
+        }
+        window => Ok(Some(window)),
+    }
+}
+
+/// Editor field, where "header" and "responseHeader" mean the (request or
+/// response) header named by `headerName`, "queryParam", "cookie" and
+/// "form" the parameter named by `paramName`, and "json" the `pointer`.
+/// "hour", "weekday" and "today" read the clock in `timeZone`.
+///
+/// Graphs saved before the dedicated keys name all of these with
+/// `headerName`, which is used when the key is missing.
+fn field(field: &str, names: &FieldNames) -> RequestField {
+    let fallback = names.header_name.as_deref();
+    let name = |key: &Option<String>| key.as_deref().or(fallback).unwrap_or("header").to_string();
+    match field {
+        "header" => RequestField::Header { name: name(&None) },
+        "responseHeader" => RequestField::ResponseHeader { name: name(&None) },
+        "queryParam" => RequestField::QueryParam { name: name(&names.param_name) },
+        "cookie" => RequestField::Cookie { name: name(&names.param_name) },
+        "json" => RequestField::BodyJson { pointer: name(&names.pointer) },
+        "form" => RequestField::BodyForm { name: name(&names.param_name) },
+        "hour" | "weekday" | "today" => match names.time_zone.as_deref().or(fallback) {
+            Some(zone) if !zone.is_empty() => RequestField::from_key(&format!("{}:{}", field, zone)),
+            _ => RequestField::from_key(field),
+        },
+        _ => RequestField::from_key(field),
+    }
+}
+
+fn operator(key: &str) -> Result<Operator, String> {
+    Operator::from_key(key).ok_or_else(|| format!("Unknown operator: {}", key))
+}
+
+fn condition_kind(data: ConditionNodeData) -> Result<NodeKind, String> {
+    let operator = operator(&data.operator)?;
+    Ok(NodeKind::Condition {
+        field: field(&data.field, &data.names),
+        value: ConditionValue::from_editor(&operator, &data.value),
+        operator,
+    })
+}
+
+fn rule_conditions(conditions: &[RuleGroupCondition]) -> Result<Vec<RuleCondition>, String> {
+    conditions
+        .iter()
+        .map(|c| {
+            let operator = operator(&c.operator)?;
+            Ok(RuleCondition {
+                field: field(&c.field, &c.names),
+                value: ConditionValue::from_editor(&operator, &c.value),
+                operator,
+                negate: c.negate,
+            })
+        })
+        .collect()
+}
+
+fn sub_groups(groups: &[RuleSubGroup]) -> Result<Vec<ConditionGroup>, String> {
+    groups
+        .iter()
+        .map(|g| {
+            Ok(ConditionGroup {
+                logic: Logic::from_key(&g.logic),
+                conditions: rule_conditions(&g.conditions)?,
+                groups: sub_groups(&g.groups)?,
+            })
+        })
+        .collect()
+}
+
+fn rule_group_kind(data: RuleGroupNodeData) -> Result<NodeKind, String> {
+    Ok(NodeKind::RuleGroup {
+        name: data.name,
+        logic: Logic::from_key(&data.logic),
+        conditions: rule_conditions(&data.conditions)?,
+        groups: sub_groups(&data.groups)?,
+    })
+}
+
+fn rule_list_kind(data: RuleListNodeData) -> Result<NodeKind, String> {
+    let rules = data
+        .rules
+        .into_iter()
+        .map(|r| {
+            Ok(ListRule {
+                conditions: rule_conditions(&r.conditions).map_err(|e| format!("rule {}: {}", r.id, e))?,
+                groups: sub_groups(&r.groups).map_err(|e| format!("rule {}: {}", r.id, e))?,
+                logic: Logic::from_key(r.logic.as_deref().unwrap_or("AND")),
+                action: r.action.map(action),
+                id: r.id,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(NodeKind::RuleList { name: data.name, rules })
+}
+
+fn switch_kind(data: SwitchNodeData) -> Result<NodeKind, String> {
+    let cases = data
+        .cases
+        .into_iter()
+        .map(|c| {
+            let match_type = match c.match_type.as_deref().unwrap_or("exact") {
+                "exact" => SwitchMatch::Exact,
+                "prefix" => SwitchMatch::Prefix,
+                "cidr" => SwitchMatch::Cidr,
+                other => return Err(format!("Unknown match: {}", other)),
+            };
+            Ok(SwitchCase {
+                handle: c.id,
+                match_type,
+                values: c.value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(NodeKind::Switch {
+        field: field(&data.field, &data.names),
+        cases,
+    })
+}
+
+fn action_kind(data: ActionNodeData) -> NodeKind {
+    NodeKind::Action { action: action(data) }
+}
+
+fn action(data: ActionNodeData) -> ActionType {
+    match data.action.as_str() {
+        "allow" => ActionType::Allow,
+        "redirect" => ActionType::Redirect {
+            url: data.url.unwrap_or_else(|| "/".to_string()),
+            status_code: data.status_code.unwrap_or(302),
+            preserve_query: data.preserve_query.unwrap_or(true),
+        },
+        "log" => ActionType::Log {
+            message: data.message.unwrap_or_default(),
+            severity: LogSeverity::from_key(data.severity.as_deref().unwrap_or_default()),
+            rule: data.rule_name,
+            fields: data
+                .fields
+                .iter()
+                .filter(|f| !f.is_empty())
+                .map(|f| RequestField::from_key(f))
+                .collect(),
+        },
+        "tarpit" => ActionType::Tarpit {
+            delay_ms: data.delay_ms.unwrap_or(DEFAULT_TARPIT_DELAY_MS),
+            then: match data.then.as_deref() {
+                Some("forward") => TarpitThen::Forward,
+                _ => TarpitThen::Block {
+                    status_code: data.status_code.unwrap_or(403),
+                    message: data.message.unwrap_or_else(|| "Blocked".to_string()),
+                },
+            },
+        },
+        "challenge" => ActionType::Challenge {
+            challenge_type: ChallengeType::from_key(data.challenge_type.as_deref().unwrap_or_default()),
+        },
+        // "block" and anything the edge doesn't implement yet
+        _ => ActionType::Block {
+            status_code: data.status_code.unwrap_or(403),
+            message: data.message.unwrap_or_else(|| "Blocked".to_string()),
+        },
+    }
+}
+
+fn redirect_kind(data: RedirectNodeData) -> NodeKind {
+    NodeKind::Action {
+        action: ActionType::Redirect {
+            url: data.url,
+            status_code: data.status_code.unwrap_or(302),
+            preserve_query: data.preserve_query.unwrap_or(true),
+        },
+    }
+}
+
+fn respond_kind(data: RespondNodeData) -> NodeKind {
+    NodeKind::Action {
+        action: ActionType::Respond {
+            status_code: data.status_code.unwrap_or(200),
+            content_type: data.content_type.unwrap_or_else(|| DEFAULT_RESPOND_CONTENT_TYPE.to_string()),
+            headers: data
+                .headers
+                .into_iter()
+                .filter(|h| !h.name.is_empty())
+                .map(|h| (h.name, h.value))
+                .collect(),
+            body: data.body.unwrap_or_default(),
+        },
+    }
+}
+
+fn backend_pool_kind(data: BackendPoolNodeData) -> Result<NodeKind, String> {
+    if data.members.is_empty() {
+        return Err("Backend pool has no members".to_string());
+    }
+    let strategy = match data.strategy.as_deref().unwrap_or("weightedRandom") {
+        "weightedRandom" => PoolStrategy::WeightedRandom,
+        "hash" => PoolStrategy::Hash {
+            field: match data.hash_key.as_deref().unwrap_or("clientIp") {
+                "header" => RequestField::Header {
+                    name: data.header_name.ok_or("hashKey 'header' requires headerName")?,
+                },
+                other => RequestField::from_key(other),
+            },
+        },
+        "cookie" => PoolStrategy::Cookie {
+            name: data.cookie_name.unwrap_or_else(|| DEFAULT_POOL_COOKIE.to_string()),
+        },
+        other => return Err(format!("Unknown strategy: {}", other)),
+    };
+    Ok(NodeKind::BackendPool { name: data.name, members: data.members, strategy })
+}
+
+fn split_kind(data: SplitNodeData) -> Result<NodeKind, String> {
+    if data.buckets.is_empty() {
+        return Err("Split has no buckets".to_string());
+    }
+    let key = match data.key_by.as_deref().unwrap_or("clientIp") {
+        "clientIp" | "ip" => SplitKey::Ip,
+        "header" => SplitKey::Header {
+            name: data.header_name.ok_or("keyBy 'header' requires headerName")?,
+        },
+        "cookie" => SplitKey::Cookie {
+            name: data.cookie_name.ok_or("keyBy 'cookie' requires cookieName")?,
+        },
+        other => match other.strip_prefix("var:") {
+            Some(name) => SplitKey::Var { name: name.to_string() },
+            None => return Err(format!("Unknown keyBy: {}", other)),
+        },
+    };
+    Ok(NodeKind::Split {
+        name: data.name.unwrap_or_else(|| "split".to_string()),
+        buckets: data
+            .buckets
+            .into_iter()
+            .map(|b| SplitBucket {
+                label: b.label.unwrap_or_else(|| b.id.clone()),
+                handle: b.id,
+                weight: b.weight,
+            })
+            .collect(),
+        key,
+    })
+}
+
+fn rate_limit_kind(data: RateLimitNodeData) -> Result<NodeKind, String> {
+    let key = match data.key_by.as_str() {
+        "ip" => RateLimitKey::Ip,
+        "fingerprint" => RateLimitKey::Fingerprint,
+        "header" => RateLimitKey::Header {
+            name: data.header_name.ok_or("keyBy 'header' requires headerName")?,
+        },
+        "path" => RateLimitKey::Path,
+        other => match RequestField::from_key(other) {
+            RequestField::Var { name } => RateLimitKey::Var { name },
+            RequestField::QueryParam { name } => RateLimitKey::QueryParam { name },
+            RequestField::Cookie { name } => RateLimitKey::Cookie { name },
+            _ => return Err(format!("Unknown keyBy: {}", other)),
+        },
+    };
+
+    // The rate counter only has 1s/10s/60s windows: hourly limits are
+    // approximated per minute, with a longer penalty
+    let (window, threshold, penalty_ttl_seconds) = match data.window_unit.as_str() {
+        "second" => (RateWindow::OneSec, data.limit, 120),
+        "hour" => (RateWindow::SixtySecs, data.limit / 60, 600),
+        _ => (RateWindow::SixtySecs, data.limit, 120),
+    };
+
+    Ok(NodeKind::RateLimit {
+        mode: RateLimitMode::CheckRate,
+        counter_name: RATE_COUNTER_NAME.to_string(),
+        window,
+        threshold,
+        penalty_ttl_seconds,
+        key,
+    })
+}
+
+fn header_kind(data: HeaderNodeData) -> Result<NodeKind, String> {
+    let operation = match data.operation.as_str() {
+        "set" => HeaderOp::Set,
+        "append" => HeaderOp::Append,
+        "remove" => HeaderOp::Remove,
+        other => return Err(format!("Unknown header operation: {}", other)),
+    };
+    Ok(NodeKind::Header {
+        operation,
+        name: data.name,
+        value: data.value,
+    })
+}
+
+fn cache_kind(data: CacheNodeData) -> Result<NodeKind, String> {
+    let mode = match data.mode.as_str() {
+        "pass" => CacheMode::Pass,
+        "configure" => {
+            let ttl = data.ttl.map(|ttl| {
+                ttl * match data.ttl_unit.as_deref().unwrap_or("seconds") {
+                    "minutes" => 60,
+                    "hours" => 3600,
+                    "days" => 86400,
+                    _ => 1,
+                }
+            });
+            let stale_while_revalidate = data.stale_while_revalidate.map(|swr| {
+                swr * match data.swr_unit.as_deref().unwrap_or("seconds") {
+                    "minutes" => 60,
+                    "hours" => 3600,
+                    _ => 1,
+                }
+            });
+            // Surrogate keys are space-separated
+            let surrogate_keys = data
+                .surrogate_keys
+                .as_deref()
+                .unwrap_or("")
+                .split_whitespace()
+                .map(|s| s.to_string())
+                .collect();
+            CacheMode::Configure {
+                ttl,
+                stale_while_revalidate,
+                surrogate_keys,
+            }
+        }
+        other => return Err(format!("Unknown cache mode: {}", other)),
+    };
+    Ok(NodeKind::Cache { mode })
+}
+
+fn transform_kind(data: TransformNodeData) -> Result<NodeKind, String> {
+    let operation = TransformOp::from_key(&data.operation)
+        .ok_or_else(|| format!("Unknown transform operation: {}", data.operation))?;
+    Ok(NodeKind::Transform {
+        operation,
+        field: RequestField::from_key(&data.field),
+        pattern: data.pattern,
+        output_var: data.output_var,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        execute, execute_response, range_point, ExecutionResult, ExecutionState, HeaderMod, Plan, RequestBody,
+        RequestContext,
+    };
+
+    fn payload(json: &str) -> GraphPayload {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn plan(json: &str) -> Plan {
+        Plan::compile(&from_payload(&payload(json)).unwrap()).unwrap()
+    }
+
+    fn run(json: &str, request: &RequestContext) -> ExecutionResult {
+        execute(&plan(json), request, &mut ExecutionState::new())
+    }
+
+    const CONDITION_GRAPH: &str = r#"{
+        "nodes": [
+            {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+            {"id": "cond", "type": "condition", "position": {"x": 100, "y": 0},
+             "data": {"field": "path", "operator": "startsWith", "value": "/api"}},
+            {"id": "block", "type": "action", "position": {"x": 200, "y": 0},
+             "data": {"action": "block", "statusCode": 429, "message": "Slow down"}},
+            {"id": "allow", "type": "action", "position": {"x": 200, "y": 100},
+             "data": {"action": "allow"}}
+        ],
+        "edges": [
+            {"id": "e1", "source": "req", "target": "cond"},
+            {"id": "e2", "source": "cond", "target": "block", "sourceHandle": "true"},
+            {"id": "e3", "source": "cond", "target": "allow", "sourceHandle": "false"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_condition_handles_map_to_ports() {
+        let graph = from_payload(&payload(CONDITION_GRAPH)).unwrap();
+        assert_eq!(graph.nodes.len(), 4);
+        assert_eq!(graph.nodes[1].editor_id.as_deref(), Some("cond"));
+
+        let ports: Vec<u8> = graph.edges.iter().map(|e| e.from_port).collect();
+        assert_eq!(ports, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn test_condition_graph_execution() {
+        let mut request = RequestContext::mock();
+        request.path = "/api/users".to_string();
+        assert_eq!(
+            run(CONDITION_GRAPH, &request),
+            ExecutionResult::Block { status_code: 429, message: "Slow down".to_string() }
+        );
+
+        request.path = "/home".to_string();
+        assert_eq!(run(CONDITION_GRAPH, &request), ExecutionResult::Allow);
+    }
+
+    #[test]
+    fn test_rule_group_with_header_and_cidr() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "rg", "type": "ruleGroup", "position": {"x": 100, "y": 0},
+                 "data": {"name": "Internal", "logic": "and", "conditions": [
+                    {"id": "c1", "field": "clientIp", "operator": "inCidr", "value": "10.0.0.0/8, 192.168.0.0/16"},
+                    {"id": "c2", "field": "header", "headerName": "X-Debug", "operator": "equals", "value": "1"}
+                 ]}},
+                {"id": "block", "type": "action", "position": {"x": 200, "y": 0}, "data": {"action": "block"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "rg"},
+                {"id": "e2", "source": "rg", "target": "block", "sourceHandle": "match"}
+            ]
+        }"#;
+
+        let mut request = RequestContext::mock();
+        assert_eq!(run(json, &request), ExecutionResult::NoMatch);
+
+        request.headers.insert("x-debug".to_string(), "1".to_string());
+        assert_eq!(
+            run(json, &request),
+            ExecutionResult::Block { status_code: 403, message: "Blocked".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_rule_group_nested_groups_and_negation() {
+        // (path startsWith /admin AND NOT clientIp inCidr office) OR x-debug exists
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "rg", "type": "ruleGroup", "position": {"x": 100, "y": 0},
+                 "data": {"name": "Admin", "logic": "OR", "conditions": [
+                    {"id": "c1", "field": "header", "headerName": "X-Debug", "operator": "exists", "value": ""}
+                 ], "groups": [
+                    {"logic": "AND", "conditions": [
+                        {"id": "c2", "field": "path", "operator": "startsWith", "value": "/admin"},
+                        {"id": "c3", "field": "clientIp", "operator": "inCidr", "value": "10.0.0.0/8", "negate": true}
+                    ]}
+                 ]}},
+                {"id": "block", "type": "action", "position": {"x": 200, "y": 0}, "data": {"action": "block"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "rg"},
+                {"id": "e2", "source": "rg", "target": "block", "sourceHandle": "match"}
+            ]
+        }"#;
+        let plan = plan(json);
+        let blocked = ExecutionResult::Block { status_code: 403, message: "Blocked".to_string() };
+        let mut request = RequestContext::mock();
+
+        request.path = "/admin".to_string();
+        assert_eq!(execute(&plan, &request, &mut ExecutionState::new()), blocked);
+
+        request.client_ip = Some("10.1.2.3".parse().unwrap());
+        assert_eq!(execute(&plan, &request, &mut ExecutionState::new()), ExecutionResult::NoMatch);
+
+        // The header settles the OR, so the nested group is never checked
+        request.headers.insert("x-debug".to_string(), "1".to_string());
+        let mut state = ExecutionState::new();
+        assert_eq!(execute(&plan, &request, &mut state), blocked);
+        assert!(!state.trace.iter().any(|line| line.contains("path")));
+    }
+
+    #[test]
+    fn test_query_param_and_cookie_fields() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "rg", "type": "ruleGroup", "position": {"x": 100, "y": 0},
+                 "data": {"name": "Abuse", "logic": "OR", "conditions": [
+                    {"id": "c1", "field": "queryParam", "paramName": "q", "operator": "contains", "value": "<script>"},
+                    {"id": "c2", "field": "queryParamCount", "operator": "greaterThan", "value": "3"},
+                    {"id": "c3", "field": "cookie", "paramName": "session", "operator": "equals", "value": "stolen token"},
+                    {"id": "c4", "field": "queryParam", "headerName": "debug", "operator": "exists", "value": ""}
+                 ]}},
+                {"id": "block", "type": "action", "position": {"x": 200, "y": 0}, "data": {"action": "block"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "rg"},
+                {"id": "e2", "source": "rg", "target": "block", "sourceHandle": "match"}
+            ]
+        }"#;
+        let plan = plan(json);
+        let blocked = ExecutionResult::Block { status_code: 403, message: "Blocked".to_string() };
+        let run = |request: &RequestContext| execute(&plan, request, &mut ExecutionState::new());
+
+        let mut request = RequestContext::mock();
+        request.query = Some("?q=hello&page=2".to_string());
+        assert_eq!(run(&request), ExecutionResult::NoMatch);
+
+        // A repeated parameter can't hide the payload in its second copy
+        request.query = Some("?q=hello&q=%3Cscript%3Ealert(1)".to_string());
+        assert_eq!(run(&request), blocked);
+
+        request.query = Some("?a=1&a=2&b&c=3".to_string());
+        assert_eq!(run(&request), blocked);
+
+        // Graphs saved before paramName named the parameter with headerName
+        request.query = Some("?debug".to_string());
+        assert_eq!(run(&request), blocked);
+
+        request.query = None;
+        request.headers.insert("cookie".to_string(), "theme=dark; session=stolen%20token".to_string());
+        assert_eq!(run(&request), blocked);
+    }
+
+    #[test]
+    fn test_body_fields_buffered_only_when_read() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "rg", "type": "ruleGroup", "position": {"x": 100, "y": 0},
+                 "data": {"name": "Injection", "logic": "OR", "conditions": [
+                    {"id": "c1", "field": "json:/user/name", "operator": "matches", "value": "(?i)<script"},
+                    {"id": "c2", "field": "form:q", "operator": "contains", "value": "' OR "}
+                 ]}},
+                {"id": "block", "type": "action", "position": {"x": 200, "y": 0}, "data": {"action": "block"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "rg"},
+                {"id": "e2", "source": "rg", "target": "block", "sourceHandle": "match"}
+            ]
+        }"#;
+        assert!(!plan(CONDITION_GRAPH).reads_body());
+        let plan = plan(json);
+        assert!(plan.reads_body());
+
+        let blocked = ExecutionResult::Block { status_code: 403, message: "Blocked".to_string() };
+        let mut request = RequestContext::mock();
+        assert_eq!(execute(&plan, &request, &mut ExecutionState::new()), ExecutionResult::NoMatch);
+
+        request.body = Some(RequestBody::complete(r#"{"user": {"name": "<SCRIPT>alert(1)"}}"#));
+        assert_eq!(execute(&plan, &request, &mut ExecutionState::new()), blocked);
+
+        request.body = Some(RequestBody::complete("q=1%27+OR+1%3D1"));
+        assert_eq!(execute(&plan, &request, &mut ExecutionState::new()), blocked);
+
+        // Past the cap only the beginning is inspected, and no fields are parsed
+        request.body = Some(RequestBody::new(b"q=1%27+OR+1%3D1", 1 << 20, true));
+        assert_eq!(execute(&plan, &request, &mut ExecutionState::new()), ExecutionResult::NoMatch);
+    }
+
+    #[test]
+    fn test_clock_fields_and_between() {
+        let graph = |zone: &str| {
+            format!(
+                r#"{{
+                "nodes": [
+                    {{"id": "req", "type": "request", "position": {{"x": 0, "y": 0}}, "data": {{}}}},
+                    {{"id": "rg", "type": "ruleGroup", "position": {{"x": 100, "y": 0}},
+                     "data": {{"name": "Admin hours", "logic": "OR", "conditions": [
+                        {{"id": "c1", "field": "weekday", "timeZone": "{zone}", "operator": "between", "value": "Sat..Sun"}},
+                        {{"id": "c2", "field": "hour", "timeZone": "{zone}", "operator": "between", "value": "18..7"}},
+                        {{"id": "c3", "field": "now", "operator": "between", "value": "2026-12-24..2026-12-26"}}
+                     ]}}}},
+                    {{"id": "block", "type": "action", "position": {{"x": 200, "y": 0}}, "data": {{"action": "block"}}}}
+                ],
+                "edges": [
+                    {{"id": "e1", "source": "req", "target": "rg"}},
+                    {{"id": "e2", "source": "rg", "target": "block", "sourceHandle": "match"}}
+                ]
+            }}"#
+            )
+        };
+        let plan = plan(&graph("Europe/Berlin"));
+        let blocked = ExecutionResult::Block { status_code: 403, message: "Blocked".to_string() };
+        let run = |now: &str| {
+            let mut request = RequestContext::mock();
+            request.now = range_point(now, false).map(|secs| secs as i64);
+            execute(&plan, &request, &mut ExecutionState::new())
+        };
+
+        // Friday 10:30 in Berlin (summer time)
+        assert_eq!(run("2026-10-16T08:30:00Z"), ExecutionResult::NoMatch);
+        // Friday 19:30, Saturday 10:30, and Christmas day
+        assert_eq!(run("2026-10-16T17:30:00Z"), blocked);
+        assert_eq!(run("2026-10-17T08:30:00Z"), blocked);
+        assert_eq!(run("2026-12-25T12:00:00Z"), blocked);
+        // Without a clock the fields have no value
+        assert_eq!(
+            execute(&plan, &RequestContext::mock(), &mut ExecutionState::new()),
+            ExecutionResult::NoMatch
+        );
+
+        let graph = from_payload(&payload(&graph("Mars/Olympus"))).unwrap();
+        let errors = Plan::compile(&graph).unwrap_err();
+        assert!(errors[0].message.contains("Unknown time zone 'Mars/Olympus'"));
+    }
+
+    #[test]
+    fn test_inactive_nodes_pass_through() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "incident", "type": "ruleGroup", "position": {"x": 100, "y": 0},
+                 "data": {"name": "Incident", "logic": "AND", "activeUntil": "2026-10-31",
+                          "conditions": [{"id": "c1", "field": "path", "operator": "startsWith", "value": "/api/"}]}},
+                {"id": "block", "type": "action", "position": {"x": 200, "y": 0}, "data": {"action": "block"}},
+                {"id": "launch", "type": "action", "position": {"x": 200, "y": 100},
+                 "data": {"action": "challenge", "activeFrom": "2026-11-01T00:00:00Z"}},
+                {"id": "allow", "type": "action", "position": {"x": 300, "y": 100}, "data": {"action": "allow"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "incident"},
+                {"id": "e2", "source": "incident", "target": "block", "sourceHandle": "match"},
+                {"id": "e3", "source": "incident", "target": "launch", "sourceHandle": "noMatch"},
+                {"id": "e4", "source": "launch", "target": "allow", "sourceHandle": "passed"}
+            ]
+        }"#;
+        let plan = plan(json);
+        let mut state = ExecutionState::new();
+        let mut run = |now: Option<&str>| {
+            let mut request = RequestContext::mock();
+            request.now = now.and_then(|now| range_point(now, false)).map(|secs| secs as i64);
+            execute(&plan, &request, &mut state)
+        };
+
+        let blocked = ExecutionResult::Block { status_code: 403, message: "Blocked".to_string() };
+        assert_eq!(run(Some("2026-10-16T12:00:00Z")), blocked);
+        assert_eq!(run(None), blocked);
+        // After the incident the group follows noMatch, into the challenge now active
+        assert!(matches!(run(Some("2026-11-02T12:00:00Z")), ExecutionResult::Challenge { .. }));
+        assert_eq!(state.inactive_nodes, vec!["incident"]);
+        assert!(state.trace.iter().any(|line| line.contains("inactive (active until 2026-10-31T23:59:59Z)")));
+
+        // Before its launch the challenge lets requests continue as if passed
+        let mut request = RequestContext::mock();
+        request.path = "/home".to_string();
+        request.now = Some(range_point("2026-10-16T12:00:00Z", false).unwrap() as i64);
+        assert_eq!(execute(&plan, &request, &mut state), ExecutionResult::Allow);
+        assert_eq!(state.inactive_nodes, vec!["launch"]);
+    }
+
+    #[test]
+    fn test_header_mods_collected_before_backend() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "hdr", "type": "header", "position": {"x": 100, "y": 0},
+                 "data": {"operation": "set", "name": "X-Edge", "value": "1"}},
+                {"id": "origin", "type": "backend", "position": {"x": 200, "y": 0},
+                 "data": {"name": "origin", "host": "origin.example.com"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "hdr"},
+                {"id": "e2", "source": "hdr", "target": "origin", "sourceHandle": "next"}
+            ]
+        }"#;
+
+        let mut state = ExecutionState::new();
+        let result = execute(&plan(json), &RequestContext::mock(), &mut state);
+
+        assert!(matches!(result, ExecutionResult::Route { ref backend } if backend.name == "origin"));
+        assert_eq!(
+            state.header_mods,
+            vec![crate::HeaderMod::Set { name: "X-Edge".to_string(), value: "1".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_header_and_cache_mods_kept_when_allowed() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "hdr", "type": "header", "position": {"x": 100, "y": 0},
+                 "data": {"operation": "set", "name": "X-Edge", "value": "1"}},
+                {"id": "cache", "type": "cache", "position": {"x": 200, "y": 0},
+                 "data": {"mode": "configure", "ttl": 5, "ttlUnit": "minutes"}},
+                {"id": "allow", "type": "action", "position": {"x": 300, "y": 0}, "data": {"action": "allow"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "hdr"},
+                {"id": "e2", "source": "hdr", "target": "cache", "sourceHandle": "next"},
+                {"id": "e3", "source": "cache", "target": "allow", "sourceHandle": "next"}
+            ]
+        }"#;
+
+        // The edge forwards allowed requests with these applied, as it does routed ones
+        let mut state = ExecutionState::new();
+        assert_eq!(execute(&plan(json), &RequestContext::mock(), &mut state), ExecutionResult::Allow);
+        assert_eq!(
+            state.header_mods,
+            vec![crate::HeaderMod::Set { name: "X-Edge".to_string(), value: "1".to_string() }]
+        );
+        assert_eq!(state.cache_settings.ttl, Some(300));
+    }
+
+    #[test]
+    fn test_transform_variables_feed_conditions_and_headers() {
+        // lowercase(path) -> extract the first segment -> condition and header
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "lower", "type": "transform", "position": {"x": 100, "y": 0},
+                 "data": {"operation": "lowercase", "field": "path", "outputVar": "normalized_path"}},
+                {"id": "first", "type": "transform", "position": {"x": 200, "y": 0},
+                 "data": {"operation": "extract", "field": "var:normalized_path", "pattern": "^/([^/]+)", "outputVar": "section"}},
+                {"id": "hdr", "type": "header", "position": {"x": 300, "y": 0},
+                 "data": {"operation": "set", "name": "X-Section", "value": "var:section"}},
+                {"id": "cond", "type": "condition", "position": {"x": 400, "y": 0},
+                 "data": {"field": "var:normalized_path", "operator": "startsWith", "value": "/admin"}},
+                {"id": "block", "type": "action", "position": {"x": 500, "y": 0}, "data": {"action": "block"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "lower"},
+                {"id": "e2", "source": "lower", "target": "first", "sourceHandle": "value_out"},
+                {"id": "e3", "source": "first", "target": "hdr", "sourceHandle": "value_out"},
+                {"id": "e4", "source": "hdr", "target": "cond", "sourceHandle": "next"},
+                {"id": "e5", "source": "cond", "target": "block", "sourceHandle": "true"}
+            ]
+        }"#;
+
+        let mut request = RequestContext::mock();
+        request.path = "/ADMIN/users".to_string();
+        let mut state = ExecutionState::new();
+        let result = execute(&plan(json), &request, &mut state);
+
+        assert!(matches!(result, ExecutionResult::Block { status_code: 403, .. }));
+        assert_eq!(
+            state.header_mods,
+            vec![crate::HeaderMod::Set { name: "X-Section".to_string(), value: "admin".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_decoded_variables_kept_to_one_header_line() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "decode", "type": "transform", "position": {"x": 100, "y": 0},
+                 "data": {"operation": "urlDecode", "field": "path", "outputVar": "decoded_path"}},
+                {"id": "hdr", "type": "header", "position": {"x": 200, "y": 0},
+                 "data": {"operation": "set", "name": "X-Path", "value": "var:decoded_path"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "decode"},
+                {"id": "e2", "source": "decode", "target": "hdr", "sourceHandle": "value_out"}
+            ]
+        }"#;
+
+        let mut request = RequestContext::mock();
+        request.path = "/a%0D%0ASet-Cookie:%20admin=1".to_string();
+        let mut state = ExecutionState::new();
+        execute(&plan(json), &request, &mut state);
+
+        assert_eq!(
+            state.header_mods,
+            vec![crate::HeaderMod::Set { name: "X-Path".to_string(), value: "/aSet-Cookie: admin=1".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_templates_filled_in_from_request() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "hdr", "type": "header", "position": {"x": 100, "y": 0},
+                 "data": {"operation": "set", "name": "X-Client-Country", "value": "${country}"}},
+                {"id": "cond", "type": "condition", "position": {"x": 200, "y": 0},
+                 "data": {"field": "country", "operator": "equals", "value": "US"}},
+                {"id": "eu", "type": "redirect", "position": {"x": 300, "y": 0},
+                 "data": {"url": "https://${host}/eu${path}"}},
+                {"id": "block", "type": "action", "position": {"x": 300, "y": 100},
+                 "data": {"action": "block", "message": "Blocked (request ${requestId})"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "hdr"},
+                {"id": "e2", "source": "hdr", "target": "cond", "sourceHandle": "next"},
+                {"id": "e3", "source": "cond", "target": "block", "sourceHandle": "true"},
+                {"id": "e4", "source": "cond", "target": "eu", "sourceHandle": "false"}
+            ]
+        }"#;
+
+        let mut request = RequestContext::mock();
+        let mut state = ExecutionState::new();
+        assert_eq!(
+            execute(&plan(json), &request, &mut state),
+            ExecutionResult::Block {
+                status_code: 403,
+                message: "Blocked (request 0192f3a4-7b1c-7d2e-9f00-1a2b3c4d5e6f)".to_string(),
+            }
+        );
+        assert_eq!(
+            state.header_mods,
+            vec![crate::HeaderMod::Set { name: "X-Client-Country".to_string(), value: "US".to_string() }]
+        );
+
+        request.country = Some("DE".to_string());
+        request.path = "/shop".to_string();
+        assert!(matches!(
+            run(json, &request),
+            ExecutionResult::Redirect { ref url, .. } if *url == format!("https://{}/eu/shop", request.host)
+        ));
+    }
+
+    #[test]
+    fn test_challenge_continues_once_passed() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "chal", "type": "action", "position": {"x": 100, "y": 0},
+                 "data": {"action": "challenge", "challengeType": "proofOfWork"}},
+                {"id": "origin", "type": "backend", "position": {"x": 200, "y": 0},
+                 "data": {"name": "origin", "host": "origin.example.com"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "chal"},
+                {"id": "e2", "source": "chal", "target": "origin", "sourceHandle": "passed"}
+            ]
+        }"#;
+
+        let mut request = RequestContext::mock();
+        assert_eq!(
+            run(json, &request),
+            ExecutionResult::Challenge { challenge_type: ChallengeType::ProofOfWork }
+        );
+
+        request.challenge_passed = true;
+        assert!(matches!(run(json, &request), ExecutionResult::Route { ref backend } if backend.name == "origin"));
+    }
+
+    #[test]
+    fn test_log_node_records_event_and_continues() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "log", "type": "action", "position": {"x": 100, "y": 0},
+                 "data": {"action": "log", "message": "Scanner on ${path}", "severity": "warning",
+                          "ruleName": "Scanners", "fields": ["clientIp", "userAgent"]}}
+            ],
+            "edges": [{"id": "e1", "source": "req", "target": "log"}]
+        }"#;
+
+        let request = RequestContext::mock();
+        let mut state = ExecutionState::new();
+        // Monitoring only: nothing after the log node, so the request isn't matched
+        assert_eq!(execute(&plan(json), &request, &mut state), ExecutionResult::NoMatch);
+
+        let event = &state.events[0];
+        assert_eq!(event.message, format!("Scanner on {}", request.path));
+        assert_eq!(event.severity, crate::LogSeverity::Warning);
+        assert_eq!(event.rule.as_deref(), Some("Scanners"));
+        assert_eq!(event.fields["userAgent"], request.user_agent);
+        assert_eq!(event.fields.len(), 2);
+    }
+
+    #[test]
+    fn test_tarpit_then_block_or_forward() {
+        let graph = |data: &str| {
+            format!(
+                r#"{{
+                    "nodes": [
+                        {{"id": "req", "type": "request", "position": {{"x": 0, "y": 0}}, "data": {{}}}},
+                        {{"id": "pit", "type": "action", "position": {{"x": 100, "y": 0}}, "data": {}}}
+                    ],
+                    "edges": [{{"id": "e1", "source": "req", "target": "pit"}}]
+                }}"#,
+                data
+            )
+        };
+        let request = RequestContext::mock();
+
+        assert_eq!(
+            run(&graph(r#"{"action": "tarpit", "message": "Try later (${requestId})", "statusCode": 429}"#), &request),
+            ExecutionResult::Tarpit {
+                delay_ms: 5000,
+                then: crate::TarpitThen::Block {
+                    status_code: 429,
+                    message: format!("Try later ({})", request.request_id.clone().unwrap()),
+                },
+            }
+        );
+        assert_eq!(
+            run(&graph(r#"{"action": "tarpit", "delayMs": 2000, "then": "forward"}"#), &request),
+            ExecutionResult::Tarpit { delay_ms: 2000, then: crate::TarpitThen::Forward }
+        );
+    }
+
+    #[test]
+    fn test_respond_fills_in_body_and_headers() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "api", "type": "respond", "position": {"x": 100, "y": 0}, "data": {
+                    "statusCode": 429,
+                    "contentType": "application/json",
+                    "headers": [{"name": "Retry-After", "value": "60"}, {"name": "X-Request-Id", "value": "${requestId}"}],
+                    "body": "{\"error\":\"rate_limited\",\"ip\":\"${clientIp}\"}"
+                }}
+            ],
+            "edges": [{"id": "e1", "source": "req", "target": "api"}]
+        }"#;
+        let request = RequestContext::mock();
+        let request_id = request.request_id.clone().unwrap();
+
+        assert_eq!(
+            run(json, &request),
+            ExecutionResult::Respond {
+                status_code: 429,
+                content_type: "application/json".to_string(),
+                headers: vec![
+                    ("Retry-After".to_string(), "60".to_string()),
+                    ("X-Request-Id".to_string(), request_id),
+                ],
+                body: r#"{"error":"rate_limited","ip":"192.168.1.100"}"#.to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_templates_from_request_kept_to_one_header_line() {
+        let graph = |action: &str| {
+            format!(
+                r#"{{
+                    "nodes": [
+                        {{"id": "req", "type": "request", "position": {{"x": 0, "y": 0}}, "data": {{}}}},
+                        {{"id": "out", "position": {{"x": 100, "y": 0}}, {}}}
+                    ],
+                    "edges": [{{"id": "e1", "source": "req", "target": "out"}}]
+                }}"#,
+                action
+            )
+        };
+        let mut request = RequestContext::mock();
+        request.query = Some("?next=%0D%0ASet-Cookie:%20admin=1".to_string());
+
+        assert!(matches!(
+            run(&graph(r#""type": "redirect", "data": {"url": "/login?next=${queryParam:next}"}"#), &request),
+            ExecutionResult::Redirect { ref url, .. } if url == "/login?next=Set-Cookie: admin=1"
+        ));
+        let respond = r#""type": "respond", "data": {"headers": [{"name": "X-Next", "value": "${queryParam:next}"}]}"#;
+        assert!(matches!(
+            run(&graph(respond), &request),
+            ExecutionResult::Respond { ref headers, .. } if headers[0].1 == "Set-Cookie: admin=1"
+        ));
+    }
+
+    #[test]
+    fn test_response_phase_sets_headers_and_replaces_errors() {
+        let plan = plan(r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "resp", "type": "response", "position": {"x": 0, "y": 200}, "data": {}},
+                {"id": "hsts", "type": "header", "position": {"x": 100, "y": 200},
+                 "data": {"operation": "set", "name": "Strict-Transport-Security", "value": "max-age=31536000"}},
+                {"id": "server", "type": "header", "position": {"x": 100, "y": 300},
+                 "data": {"operation": "remove", "name": "Server"}},
+                {"id": "err", "type": "condition", "position": {"x": 100, "y": 400},
+                 "data": {"field": "responseStatus", "operator": "greaterOrEqual", "value": "500"}},
+                {"id": "page", "type": "respond", "position": {"x": 200, "y": 400},
+                 "data": {"statusCode": 503, "contentType": "text/html", "body": "<h1>Sorry</h1>"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "resp", "target": "hsts"},
+                {"id": "e2", "source": "resp", "target": "server"},
+                {"id": "e3", "source": "resp", "target": "err"},
+                {"id": "e4", "source": "err", "sourceHandle": "true", "target": "page"}
+            ]
+        }"#);
+        let mut request = RequestContext::mock();
+        let mut state = ExecutionState::new();
+
+        // The request phase doesn't run response nodes
+        assert_eq!(execute(&plan, &request, &mut state), ExecutionResult::NoMatch);
+        assert!(state.header_mods.is_empty());
+
+        request.response_status = Some(200);
+        assert_eq!(execute_response(&plan, &request, &mut state), ExecutionResult::NoMatch);
+        assert_eq!(
+            state.header_mods,
+            vec![
+                HeaderMod::Set {
+                    name: "Strict-Transport-Security".to_string(),
+                    value: "max-age=31536000".to_string(),
+                },
+                HeaderMod::Remove { name: "Server".to_string() },
+            ]
+        );
+
+        request.response_status = Some(502);
+        assert!(matches!(
+            execute_response(&plan, &request, &mut state),
+            ExecutionResult::Respond { status_code: 503, ref body, .. } if body == "<h1>Sorry</h1>"
+        ));
+        assert_eq!(state.header_mods.len(), 2);
+    }
+
+    #[test]
+    fn test_backend_pool_sticks_to_cookie_member() {
+        let plan = plan(r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "pool", "type": "backendPool", "position": {"x": 100, "y": 0}, "data": {
+                    "name": "origins",
+                    "strategy": "cookie",
+                    "members": [
+                        {"name": "blue", "host": "blue.example.com", "weight": 1},
+                        {"name": "green", "host": "green.example.com", "weight": 1},
+                        {"name": "old", "host": "old.example.com", "weight": 1, "down": true}
+                    ]
+                }}
+            ],
+            "edges": [{"id": "e1", "source": "req", "target": "pool"}]
+        }"#);
+        let route = |request: &RequestContext, state: &mut ExecutionState| match execute(&plan, request, state) {
+            ExecutionResult::Route { backend } => backend.name,
+            other => panic!("expected a route, got {:?}", other),
+        };
+        let mut request = RequestContext::mock();
+        let mut state = ExecutionState::new();
+
+        // A new client gets a member and a cookie naming it
+        let first = route(&request, &mut state);
+        assert_eq!(state.sticky_cookie, Some(("cc_pool".to_string(), first.clone())));
+
+        // The cookie keeps it there; a cookie naming a down member doesn't
+        request.headers.insert("cookie".to_string(), "cc_pool=green".to_string());
+        assert_eq!(route(&request, &mut state), "green");
+        assert_eq!(state.sticky_cookie, None);
+        request.headers.insert("cookie".to_string(), "cc_pool=old".to_string());
+        assert_ne!(route(&request, &mut state), "old");
+        assert!(state.sticky_cookie.is_some());
+
+        // Any copy of the cookie naming an up member keeps the client there
+        request.headers.insert("cookie".to_string(), "cc_pool=old; theme=dark; cc_pool=blue".to_string());
+        assert_eq!(route(&request, &mut state), "blue");
+        assert_eq!(state.sticky_cookie, None);
+    }
+
+    #[test]
+    fn test_rule_list_first_match_wins() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "waf", "type": "ruleList", "position": {"x": 100, "y": 0}, "data": {
+                    "name": "waf",
+                    "rules": [
+                        {"id": "allow-office", "conditions": [
+                            {"id": "c1", "field": "clientIp", "operator": "inCidr", "value": "10.0.0.0/8"}
+                        ], "action": {"action": "allow"}},
+                        {"id": "admin", "conditions": [
+                            {"id": "c1", "field": "path", "operator": "startsWith", "value": "/admin"}
+                        ], "action": {"action": "block", "statusCode": 403, "message": "No admin (${requestId})"}},
+                        {"id": "api", "logic": "OR", "conditions": [
+                            {"id": "c1", "field": "path", "operator": "startsWith", "value": "/api"},
+                            {"id": "c2", "field": "host", "operator": "equals", "value": "api.example.com"}
+                        ]}
+                    ]
+                }},
+                {"id": "api-origin", "type": "backend", "position": {"x": 200, "y": 0},
+                 "data": {"name": "api", "host": "api.internal"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "waf"},
+                {"id": "e2", "source": "waf", "sourceHandle": "api", "target": "api-origin"}
+            ]
+        }"#;
+        let plan = plan(json);
+        let mut request = RequestContext::mock();
+        let mut state = ExecutionState::new();
+        let request_id = request.request_id.clone().unwrap();
+
+        request.path = "/admin/users".to_string();
+        assert_eq!(
+            execute(&plan, &request, &mut state),
+            ExecutionResult::Block { status_code: 403, message: format!("No admin ({})", request_id) }
+        );
+        assert_eq!(state.matched_rules["waf"], "admin");
+
+        request.path = "/api/users".to_string();
+        assert!(matches!(execute(&plan, &request, &mut state), ExecutionResult::Route { .. }));
+        assert_eq!(state.matched_rules["waf"], "api");
+
+        // An earlier rule wins even when a later one matches too
+        request.client_ip = Some("10.1.2.3".parse().unwrap());
+        assert_eq!(execute(&plan, &request, &mut state), ExecutionResult::Allow);
+        assert_eq!(state.matched_rules["waf"], "allow-office");
+
+        request.client_ip = Some("203.0.113.7".parse().unwrap());
+        request.path = "/".to_string();
+        assert_eq!(execute(&plan, &request, &mut state), ExecutionResult::NoMatch);
+        assert!(state.matched_rules.is_empty());
+    }
+
+    #[test]
+    fn test_switch_routes_by_country() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "geo", "type": "switch", "position": {"x": 100, "y": 0}, "data": {
+                    "field": "country",
+                    "cases": [
+                        {"id": "eu", "value": "DE, FR, NL"},
+                        {"id": "us", "match": "exact", "value": "US"}
+                    ]
+                }},
+                {"id": "eu-origin", "type": "backend", "position": {"x": 200, "y": 0},
+                 "data": {"name": "eu", "host": "eu.example.com"}},
+                {"id": "us-origin", "type": "backend", "position": {"x": 200, "y": 100},
+                 "data": {"name": "us", "host": "us.example.com"}},
+                {"id": "other", "type": "backend", "position": {"x": 200, "y": 200},
+                 "data": {"name": "global", "host": "global.example.com"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "geo"},
+                {"id": "e2", "source": "geo", "sourceHandle": "eu", "target": "eu-origin"},
+                {"id": "e3", "source": "geo", "sourceHandle": "us", "target": "us-origin"},
+                {"id": "e4", "source": "geo", "sourceHandle": "default", "target": "other"}
+            ]
+        }"#;
+        let mut request = RequestContext::mock();
+        for (country, backend) in [("FR", "eu"), ("US", "us"), ("JP", "global")] {
+            request.country = Some(country.to_string());
+            assert!(matches!(
+                run(json, &request),
+                ExecutionResult::Route { backend: b } if b.name == backend
+            ), "{}", country);
+        }
+    }
+
+    #[test]
+    fn test_split_buckets_users_stably() {
+        let plan = plan(r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "split", "type": "split", "position": {"x": 100, "y": 0}, "data": {
+                    "name": "new-origin",
+                    "keyBy": "header",
+                    "headerName": "x-user",
+                    "buckets": [
+                        {"id": "control", "weight": 95},
+                        {"id": "canary", "label": "canary", "weight": 5}
+                    ]
+                }},
+                {"id": "old", "type": "backend", "position": {"x": 200, "y": 0},
+                 "data": {"name": "old", "host": "old.example.com"}},
+                {"id": "new", "type": "backend", "position": {"x": 200, "y": 100},
+                 "data": {"name": "new", "host": "new.example.com"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "split"},
+                {"id": "e2", "source": "split", "sourceHandle": "control", "target": "old"},
+                {"id": "e3", "source": "split", "sourceHandle": "canary", "target": "new"}
+            ]
+        }"#);
+        let mut request = RequestContext::mock();
+        let mut state = ExecutionState::new();
+        let mut route = |user: &str| {
+            request.headers.insert("x-user".to_string(), user.to_string());
+            match execute(&plan, &request, &mut state) {
+                ExecutionResult::Route { backend } => (backend.name, state.buckets["new-origin"].clone()),
+                other => panic!("expected a route, got {:?}", other),
+            }
+        };
+
+        let canaries = (0..2000).filter(|i| route(&format!("user-{}", i)).0 == "new").count();
+        assert!((60..140).contains(&canaries), "{} canaries", canaries);
+
+        // A user always lands in the same bucket, which is logged
+        let first = route("user-7");
+        assert_eq!(route("user-7"), first);
+        assert_eq!(first.1, if first.0 == "new" { "canary" } else { "control" });
+    }
+
+    #[test]
+    fn test_fan_out_runs_branches_in_priority_order() {
+        // The cache branch (priority 1) runs first and dead-ends; the header
+        // branch then reaches the backend. Both side effects apply.
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "hdr", "type": "header", "position": {"x": 100, "y": 0},
+                 "data": {"operation": "set", "name": "X-Edge", "value": "1"}},
+                {"id": "cache", "type": "cache", "position": {"x": 100, "y": 100}, "data": {"mode": "pass"}},
+                {"id": "origin", "type": "backend", "position": {"x": 200, "y": 0},
+                 "data": {"name": "origin", "host": "origin.example.com"}},
+                {"id": "other", "type": "backend", "position": {"x": 200, "y": 100},
+                 "data": {"name": "other", "host": "other.example.com"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "hdr", "priority": 2},
+                {"id": "e2", "source": "req", "target": "cache", "priority": 1},
+                {"id": "e3", "source": "hdr", "target": "origin", "sourceHandle": "next"},
+                {"id": "e4", "source": "hdr", "target": "other", "sourceHandle": "next"}
+            ]
+        }"#;
+
+        let mut state = ExecutionState::new();
+        let result = execute(&plan(json), &RequestContext::mock(), &mut state);
+
+        assert!(matches!(result, ExecutionResult::Route { ref backend } if backend.name == "origin"));
+        assert!(state.cache_settings.pass);
+        assert_eq!(state.header_mods.len(), 1);
+    }
+
+    #[test]
+    fn test_edge_priority_picks_decision() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "block", "type": "action", "position": {"x": 100, "y": 0}, "data": {"action": "block"}},
+                {"id": "allow", "type": "action", "position": {"x": 100, "y": 100}, "data": {"action": "allow"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "block"},
+                {"id": "e2", "source": "req", "target": "allow", "priority": 1}
+            ]
+        }"#;
+
+        assert_eq!(run(json, &RequestContext::mock()), ExecutionResult::Allow);
+    }
+
+    #[test]
+    fn test_invalid_action_data_blocks_with_500() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "act", "type": "action", "position": {"x": 100, "y": 0}, "data": {"statusCode": 403}}
+            ],
+            "edges": [{"id": "e1", "source": "req", "target": "act"}]
+        }"#;
+
+        assert_eq!(
+            run(json, &RequestContext::mock()),
+            ExecutionResult::Block { status_code: 500, message: "Invalid action config".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_failure_policy_for_invalid_nodes() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "act", "type": "action", "position": {"x": 100, "y": 0}, "data": {"statusCode": 403}}
+            ],
+            "edges": [{"id": "e1", "source": "req", "target": "act"}]
+        }"#;
+
+        let mut state = ExecutionState::new();
+        state.failure_policy.invalid_node = Some(crate::FailureMode::Open);
+        assert_eq!(execute(&plan(json), &RequestContext::mock(), &mut state), ExecutionResult::NoMatch);
+
+        state.failure_policy.invalid_node = Some(crate::FailureMode::Closed);
+        assert_eq!(
+            execute(&plan(json), &RequestContext::mock(), &mut state),
+            ExecutionResult::FailClosed { reason: "invalid_node:act".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_failure_policy_for_rate_limiter_errors() {
+        struct Unavailable;
+        impl crate::RateLimiter for Unavailable {
+            fn check_rate(&mut self, _: &str, _: &str, _: RateWindow, _: u32, _: u32) -> Result<bool, String> {
+                Err("unavailable".to_string())
+            }
+            fn in_penalty_box(&mut self, _: &str, _: &str) -> Result<bool, String> {
+                Err("unavailable".to_string())
+            }
+            fn add_to_penalty_box(&mut self, _: &str, _: &str, _: u32) -> Result<(), String> {
+                Err("unavailable".to_string())
+            }
+        }
+
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "rl", "type": "rateLimit", "position": {"x": 100, "y": 0},
+                 "data": {"limit": 10, "windowUnit": "second", "keyBy": "ip"}},
+                {"id": "allow", "type": "action", "position": {"x": 200, "y": 0}, "data": {"action": "allow"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "rl"},
+                {"id": "e2", "source": "rl", "target": "allow", "sourceHandle": "ok"}
+            ]
+        }"#;
+
+        let mut state = ExecutionState::with_rate_limiter(Box::new(Unavailable));
+        assert_eq!(execute(&plan(json), &RequestContext::mock(), &mut state), ExecutionResult::Allow);
+
+        state.failure_policy.rate_limit = crate::FailureMode::Closed;
+        assert_eq!(
+            execute(&plan(json), &RequestContext::mock(), &mut state),
+            ExecutionResult::FailClosed { reason: "rate_limit_error".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_unknown_handles_and_missing_nodes_dropped() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "cond", "type": "condition", "position": {"x": 100, "y": 0},
+                 "data": {"field": "method", "operator": "equals", "value": "GET"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "cond"},
+                {"id": "e2", "source": "cond", "target": "req", "sourceHandle": "maybe"},
+                {"id": "e3", "source": "cond", "target": "gone", "sourceHandle": "true"}
+            ]
+        }"#;
+
+        let graph = from_payload(&payload(json)).unwrap();
+        assert_eq!(graph.edges.len(), 1);
+    }
+
+    #[test]
+    fn test_cycle_stops_at_step_limit() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "hdr", "type": "header", "position": {"x": 100, "y": 0},
+                 "data": {"operation": "append", "name": "X-Loop", "value": "1"}},
+                {"id": "cond", "type": "condition", "position": {"x": 200, "y": 0},
+                 "data": {"field": "method", "operator": "equals", "value": "POST"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "hdr"},
+                {"id": "e2", "source": "hdr", "target": "cond", "sourceHandle": "next"},
+                {"id": "e3", "source": "cond", "target": "hdr", "sourceHandle": "false"}
+            ]
+        }"#;
+
+        let mut state = ExecutionState::new();
+        let result = execute(&plan(json), &RequestContext::mock(), &mut state);
+
+        assert_eq!(result, ExecutionResult::StepLimitExceeded);
+        assert_eq!(state.header_mods.len(), crate::MAX_STEPS / 2);
+        assert!(state.trace.last().unwrap().starts_with("Step limit of 1000 exceeded"));
+    }
+
+    #[test]
+    fn test_duplicate_node_id_rejected() {
+        let json = r#"{
+            "nodes": [
+                {"id": "a", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "a", "type": "request", "position": {"x": 0, "y": 0}, "data": {}}
+            ],
+            "edges": []
+        }"#;
+
+        assert!(matches!(
+            from_payload(&payload(json)),
+            Err(ConvertError::DuplicateNodeId(id)) if id == "a"
+        ));
+    }
+}
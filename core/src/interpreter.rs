@@ -1,16 +1,70 @@
 //! Graph interpreter - executes security rule graphs.
 //!
-//! The interpreter evaluates a node graph against a request context,
-//! determining whether to allow, block, or challenge the request.
+//! Execution starts at the Request node (or, for the response phase, at the
+//! Response node once the origin has replied) and follows one edge at a time:
+//! each node decides which of its output ports to continue on (a condition
+//! takes `true` or `false`, a rate limit `exceeded` or `ok`, ...) until a
+//! terminal node (action, backend) produces the result. The same engine
+//! runs in the editor preview (via WASM) and at the edge, which plugs in
+//! its own [`RequestSource`] and [`RateLimiter`].
 
 use crate::{
-    Graph, Node, NodeKind, NodeId, Value,
-    RequestField, Operator, ConditionValue, RateLimitMode, ActionType,
+    NodeKind, Value, ChallengeType, LogSeverity, TarpitThen,
+    RequestField, RateLimitMode, RateLimitKey, RateWindow, ActionType,
+    HeaderOp, CacheMode, Logic, Plan, PlanNode, FailureMode, FailurePolicy, PoolStrategy, SplitKey,
+    CompiledGroup, RequestBody, Template, ActiveWindow, header_safe,
 };
-use std::collections::{HashMap, HashSet};
+use crate::params::{cookie, query_param, query_param_count, query_param_names};
+use crate::payload::BackendNodeData;
+use crate::pool::{choose_bucket, choose_member, member_named};
+use crate::time::time_field;
+use crate::transform::apply_transform;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::IpAddr;
-use regex::Regex;
-use ipnet::IpNet;
+
+/// Source of request field values.
+///
+/// [`RequestContext`] implements this for previews and tests; the edge
+/// service implements it over the live request.
+pub trait RequestSource {
+    /// Value of a field, or [`Value::None`] when the request doesn't have it.
+    fn get_field(&self, field: &RequestField) -> Value;
+
+    /// Whether the client has passed a challenge (holds a valid clearance).
+    /// Challenge nodes let such requests continue instead of challenging.
+    fn challenge_passed(&self) -> bool {
+        false
+    }
+
+    /// Current time in Unix seconds, for clock fields and node schedules.
+    /// Without one, scheduled nodes are always active.
+    fn now(&self) -> Option<i64> {
+        None
+    }
+}
+
+/// Rate limiting backend used by rate limit nodes.
+///
+/// Without one, [`ExecutionState`] keeps counters and penalty boxes in memory.
+pub trait RateLimiter {
+    /// Count a request for `entry` and report whether it is over the limit
+    /// (or already penalized).
+    fn check_rate(
+        &mut self,
+        counter_name: &str,
+        entry: &str,
+        window: RateWindow,
+        limit: u32,
+        penalty_ttl_seconds: u32,
+    ) -> Result<bool, String>;
+
+    /// Whether `entry` is currently in the penalty box.
+    fn in_penalty_box(&mut self, counter_name: &str, entry: &str) -> Result<bool, String>;
+
+    /// Put `entry` into the penalty box.
+    fn add_to_penalty_box(&mut self, counter_name: &str, entry: &str, ttl_seconds: u32) -> Result<(), String>;
+}
 
 /// Request data available during graph execution.
 #[derive(Debug, Clone, Default)]
@@ -57,8 +111,14 @@ pub struct RequestContext {
     // Request
     pub method: String,
     pub path: String,
+    /// Query string including the leading '?'
+    pub query: Option<String>,
     pub host: String,
+    pub scheme: Option<String>,
     pub user_agent: String,
+    pub pop: Option<String>,
+    pub ddos_detected: Option<bool>,
+    pub request_id: Option<String>,
 
     // TLS
     pub ja3: Option<String>,
@@ -66,6 +126,19 @@ pub struct RequestContext {
 
     // Headers
     pub headers: HashMap<String, String>,
+
+    /// Buffered body, when the graph reads body fields
+    pub body: Option<RequestBody>,
+
+    /// Current time in Unix seconds, for clock fields
+    pub now: Option<i64>,
+
+    /// The client holds a valid challenge clearance
+    pub challenge_passed: bool,
+
+    // Backend response (response phase only)
+    pub response_status: Option<u16>,
+    pub response_headers: HashMap<String, String>,
 }
 
 impl RequestContext {
@@ -114,13 +187,24 @@ impl RequestContext {
             // Request
             method: "GET".to_string(),
             path: "/api/users".to_string(),
+            query: None,
             host: "example.com".to_string(),
+            scheme: Some("https".to_string()),
             user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36".to_string(),
+            pop: Some("SJC".to_string()),
+            ddos_detected: Some(false),
+            request_id: Some("0192f3a4-7b1c-7d2e-9f00-1a2b3c4d5e6f".to_string()),
             // TLS
             ja3: Some("e7d705a3286e19ea42f587b344ee6865".to_string()),
             ja4: Some("t13d1516h2_8daaf6152771_b186095e22b6".to_string()),
             // Headers
             headers: HashMap::new(),
+            body: None,
+            now: None,
+            challenge_passed: false,
+            // Response
+            response_status: None,
+            response_headers: HashMap::new(),
         }
     }
 
@@ -165,25 +249,141 @@ impl RequestContext {
             // Request
             RequestField::Method => Value::String(self.method.clone()),
             RequestField::Path => Value::String(self.path.clone()),
+            RequestField::Query => self.query.clone().map(Value::String).unwrap_or(Value::None),
+            RequestField::QueryParam { name } => query_param(self.query.as_deref().unwrap_or_default(), name),
+            RequestField::QueryParamCount => query_param_count(self.query.as_deref().unwrap_or_default()),
+            RequestField::QueryParamNames => query_param_names(self.query.as_deref().unwrap_or_default()),
+            RequestField::Cookie { name } => cookie(&self.header_value(&["cookie"]).as_text(), name),
             RequestField::Host => Value::String(self.host.clone()),
+            RequestField::Scheme => {
+                Value::String(self.scheme.clone().unwrap_or_else(|| "https".to_string()))
+            }
             RequestField::UserAgent => Value::String(self.user_agent.clone()),
-            // TLS
+            RequestField::Pop => self.pop.clone().map(Value::String).unwrap_or(Value::None),
+            RequestField::DdosDetected => self.ddos_detected.map(Value::Bool).unwrap_or(Value::None),
+            RequestField::RequestId => self.request_id.clone().map(Value::String).unwrap_or(Value::None),
+            // TLS (read from the headers Fastly adds, as at the edge)
+            RequestField::TlsVersion => self.header_value(&["fastly-ssl-protocol", "tls-client-protocol"]),
+            RequestField::TlsCipher => self.header_value(&["fastly-ssl-cipher", "tls-client-cipher"]),
             RequestField::Ja3 => self.ja3.clone().map(Value::String).unwrap_or(Value::None),
             RequestField::Ja4 => self.ja4.clone().map(Value::String).unwrap_or(Value::None),
+            RequestField::H2Fingerprint => self.header_value(&["fastly-client-h2-fingerprint"]),
+            RequestField::OhFingerprint => self.header_value(&["fastly-client-oh-fingerprint"]),
+            // Body
+            RequestField::Body | RequestField::BodyLength | RequestField::BodyJson { .. } |
+            RequestField::BodyForm { .. } => self.body.as_ref().map_or(Value::None, |b| b.field(field)),
+            // Clock
+            RequestField::Now | RequestField::Hour { .. } | RequestField::Weekday { .. } |
+            RequestField::Today { .. } => self.now.map_or(Value::None, |now| time_field(field, now)),
             // Header
-            RequestField::Header { name } => {
-                self.headers.get(name).cloned().map(Value::String).unwrap_or(Value::None)
+            RequestField::Header { name } => self.header_value(&[name.as_str()]),
+            // Response
+            RequestField::ResponseStatus => {
+                self.response_status.map(|s| Value::Number(s as f64)).unwrap_or(Value::None)
             }
+            RequestField::ResponseHeader { name } => self
+                .response_headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| Value::String(v.clone()))
+                .unwrap_or(Value::None),
+            // Variables live in the execution state, see `field_value`
+            RequestField::Var { .. } => Value::None,
         }
     }
+
+    /// First present header out of `names` (case-insensitive)
+    fn header_value(&self, names: &[&str]) -> Value {
+        names
+            .iter()
+            .find_map(|name| {
+                self.headers
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                    .map(|(_, v)| Value::String(v.clone()))
+            })
+            .unwrap_or(Value::None)
+    }
+}
+
+impl RequestSource for RequestContext {
+    fn get_field(&self, field: &RequestField) -> Value {
+        RequestContext::get_field(self, field)
+    }
+
+    fn challenge_passed(&self) -> bool {
+        self.challenge_passed
+    }
+
+    fn now(&self) -> Option<i64> {
+        self.now
+    }
+}
+
+/// Header modification to apply before forwarding (to the response, in the
+/// response phase)
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeaderMod {
+    Set { name: String, value: String },
+    Append { name: String, value: String },
+    Remove { name: String },
+}
+
+/// Cache settings collected during graph traversal
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheSettings {
+    /// Bypass cache entirely
+    pub pass: bool,
+    /// Time-to-live in seconds
+    pub ttl: Option<u64>,
+    /// Stale-while-revalidate duration in seconds
+    pub stale_while_revalidate: Option<u64>,
+    /// Surrogate keys for cache purging
+    pub surrogate_keys: Vec<String>,
+}
+
+/// Security event recorded by a log node
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SecurityEvent {
+    pub message: String,
+    pub severity: LogSeverity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// Values of the fields the node logs, by editor field name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
 }
 
 /// Runtime state during graph execution.
+///
+/// Rate counters and penalty boxes persist across executions; header mods,
+/// cache settings, events, matched rules, buckets, inactive nodes, variables
+/// and the trace are reset for every request.
 #[derive(Default)]
 pub struct ExecutionState {
-    pub outputs: HashMap<(NodeId, u8), Value>,
     pub rate_counters: HashMap<String, HashMap<String, u32>>,
     pub penalty_boxes: HashMap<String, HashSet<String>>,
+    /// Header modifications collected during traversal
+    pub header_mods: Vec<HeaderMod>,
+    /// Cache settings collected during traversal
+    pub cache_settings: CacheSettings,
+    /// Security events recorded by log nodes during traversal
+    pub events: Vec<SecurityEvent>,
+    /// Transform results stored by variable name
+    pub variables: HashMap<String, String>,
+    /// Id of the rule that matched in each rule list, by list name
+    pub matched_rules: BTreeMap<String, String>,
+    /// Bucket label each split node put the request in, by split name
+    pub buckets: BTreeMap<String, String>,
+    /// Nodes passed through because they were outside their active window
+    pub inactive_nodes: Vec<String>,
+    /// Cookie (name, member) a cookie-strategy pool wants set on the response
+    pub sticky_cookie: Option<(String, String)>,
+    /// Human-readable log of the path taken through the graph
+    pub trace: Vec<String>,
+    /// How execution handles rate limiter errors, invalid nodes and the step limit
+    pub failure_policy: FailurePolicy,
+    rate_limiter: Option<Box<dyn RateLimiter>>,
 }
 
 impl ExecutionState {
@@ -191,12 +391,29 @@ impl ExecutionState {
         Self::default()
     }
 
-    pub fn get_output(&self, node_id: NodeId, port: u8) -> Option<&Value> {
-        self.outputs.get(&(node_id, port))
+    /// State that sends rate limit checks to `limiter` instead of in-memory counters.
+    pub fn with_rate_limiter(limiter: Box<dyn RateLimiter>) -> Self {
+        Self {
+            rate_limiter: Some(limiter),
+            ..Self::default()
+        }
+    }
+
+    /// Clear the per-request outputs before a new execution.
+    fn begin_request(&mut self) {
+        self.header_mods.clear();
+        self.cache_settings = CacheSettings::default();
+        self.events.clear();
+        self.variables.clear();
+        self.matched_rules.clear();
+        self.buckets.clear();
+        self.inactive_nodes.clear();
+        self.sticky_cookie = None;
+        self.trace.clear();
     }
 
-    pub fn set_output(&mut self, node_id: NodeId, port: u8, value: Value) {
-        self.outputs.insert((node_id, port), value);
+    fn log(&mut self, message: String) {
+        self.trace.push(message);
     }
 
     pub fn is_in_penalty_box(&self, box_name: &str, entry: &str) -> bool {
@@ -234,248 +451,557 @@ impl ExecutionState {
 pub enum ExecutionResult {
     Allow,
     Block { status_code: u16, message: String },
-    Challenge { challenge_type: String },
-    Tarpit { delay_ms: u32 },
-    Log { message: String, severity: String },
+    Challenge { challenge_type: ChallengeType },
+    Tarpit { delay_ms: u32, then: TarpitThen },
+    Redirect { url: String, status_code: u16, preserve_query: bool },
+    /// A synthetic response, with its body and header values filled in
+    Respond {
+        status_code: u16,
+        content_type: String,
+        headers: Vec<(String, String)>,
+        body: String,
+    },
+    /// Forward to a named backend
     Forward { backend: String },
+    /// Route to a dynamically configured backend
+    Route { backend: Box<BackendNodeData> },
+    /// Execution ran off the end of the graph without reaching a terminal node
+    NoMatch,
+    /// Execution took more than [`MAX_STEPS`] steps (the graph loops)
+    StepLimitExceeded,
+    /// A failure the [`FailurePolicy`] says to fail closed on
+    FailClosed { reason: String },
+}
+
+/// Most nodes a single execution may visit. Reaching it means execution is
+/// going around a cycle, which would otherwise never end.
+pub const MAX_STEPS: usize = 1000;
+
+/// What a node decided: continue on an output port, or stop with a result.
+enum Step {
+    /// Follow the edges from this port (`None` = any port)
+    Follow(Option<u8>),
+    Done(ExecutionResult),
 }
 
-/// Execute a graph against a request context.
+/// Execute a compiled graph against a request.
+///
+/// When a port has several edges, their targets run one after another in
+/// edge order (see [`crate::convert`] for edge priorities), each branch to
+/// its end before the next one starts. The first branch that reaches a
+/// decision ends execution; side effects (headers, cache settings,
+/// variables) of branches that ran before it are kept. A branch that runs
+/// off the end of the graph falls through to the next one, and
+/// [`ExecutionResult::NoMatch`] is returned when none decide.
 pub fn execute(
-    graph: &Graph,
-    request: &RequestContext,
+    plan: &Plan,
+    request: &dyn RequestSource,
     state: &mut ExecutionState,
 ) -> ExecutionResult {
-    let order = match graph.topological_sort() {
-        Ok(order) => order,
-        Err(_) => return ExecutionResult::Allow,
+    state.begin_request();
+
+    let Some(entry) = plan.entry() else {
+        state.log("No request node found".to_string());
+        return ExecutionResult::NoMatch;
     };
+    state.log(format!("Starting evaluation from request node {}", plan.node(entry).label));
+    run(plan, entry, request, state)
+}
 
-    for node_id in order {
-        if let Some(node) = graph.get_node(node_id) {
-            execute_node(graph, node, request, state);
+/// Execute the response phase of a compiled graph, from its Response node.
+///
+/// `request` also answers the response fields (status, response headers).
+/// Header nodes collect modifications for the response; a respond or block
+/// action replaces it. Any other result (or [`ExecutionResult::NoMatch`])
+/// keeps the origin's response. Per-request state is reset as for
+/// [`execute`], so read the request phase's outputs first.
+pub fn execute_response(
+    plan: &Plan,
+    request: &dyn RequestSource,
+    state: &mut ExecutionState,
+) -> ExecutionResult {
+    state.begin_request();
 
-            if let Some(result) = check_action_result(graph, node, state) {
-                return result;
+    let Some(entry) = plan.response_entry() else {
+        return ExecutionResult::NoMatch;
+    };
+    state.log(format!("Starting response phase from response node {}", plan.node(entry).label));
+    run(plan, entry, request, state)
+}
+
+fn run(plan: &Plan, entry: usize, request: &dyn RequestSource, state: &mut ExecutionState) -> ExecutionResult {
+    // Branches still to run, the next one on top: (from, to)
+    let mut pending: Vec<(Option<usize>, usize)> = vec![(None, entry)];
+    let mut steps = 0;
+
+    while let Some((from, index)) = pending.pop() {
+        let node = plan.node(index);
+        if steps == MAX_STEPS {
+            state.log(format!("Step limit of {} exceeded at node {}", MAX_STEPS, node.label));
+            if state.failure_policy.step_limit.is_closed() {
+                return fail_closed("step_limit".to_string(), state);
             }
+            return ExecutionResult::StepLimitExceeded;
+        }
+        steps += 1;
+
+        if let Some(from) = from {
+            state.log(format!("Following edge {} -> {}", plan.node(from).label, node.label));
+        }
+        state.log(format!("Evaluating node {} ({})", node.label, node.kind.display_name()));
+
+        let step = match (node.active, request.now()) {
+            (Some(window), Some(now)) if !window.contains(now) => pass_through(node, window, state),
+            _ => execute_node(node, request, state),
+        };
+        let port = match step {
+            Step::Follow(port) => port,
+            Step::Done(ExecutionResult::NoMatch) => continue,
+            Step::Done(result) => return result,
+        };
+
+        let targets = node.next(port);
+        if targets.is_empty() {
+            state.log(format!("No matching edges from {} (port: {:?})", node.label, port));
         }
+        pending.extend(targets.iter().rev().map(|&to| (Some(index), to)));
     }
 
-    ExecutionResult::Allow
+    ExecutionResult::NoMatch
 }
 
-fn execute_node(
-    graph: &Graph,
-    node: &Node,
-    request: &RequestContext,
-    state: &mut ExecutionState,
-) {
-    let inputs = gather_inputs(graph, node.id, state);
+/// Step of a node outside its active window: on along the port it takes
+/// when it doesn't act, or the end of the branch for terminal nodes.
+fn pass_through(node: &PlanNode, window: ActiveWindow, state: &mut ExecutionState) -> Step {
+    state.log(format!("Node {} is inactive ({}), passing through", node.label, window));
+    state.inactive_nodes.push(node.label.clone());
+    match node.kind.pass_through_port() {
+        Some(port) => Step::Follow(Some(port)),
+        None => Step::Done(ExecutionResult::NoMatch),
+    }
+}
 
-    let outputs = match &node.kind {
-        NodeKind::Request => vec![Value::Bool(true)], // Just a marker
+fn fail_closed(reason: String, state: &mut ExecutionState) -> ExecutionResult {
+    state.log(format!("Failing closed: {}", reason));
+    ExecutionResult::FailClosed { reason }
+}
 
-        NodeKind::Condition { field, operator, value } => {
-            let field_value = request.get_field(field);
-            let matched = evaluate_condition(&field_value, operator, value);
-            vec![Value::Bool(matched)]
+fn execute_node(node: &PlanNode, request: &dyn RequestSource, state: &mut ExecutionState) -> Step {
+    match &node.kind {
+        NodeKind::Request | NodeKind::Response => Step::Follow(None),
+
+        NodeKind::Condition { .. } => {
+            let c = &node.conditions[0];
+            let field_value = field_value(&c.field, request, state);
+            let matched = c.matches(&field_value);
+            state.log(format!(
+                "Condition {} {} {} (actual: {}) matches: {}",
+                c.field, c.operator.key(), c.value.as_text(), field_value.as_text(), matched
+            ));
+            Step::Follow(Some(if matched { 0 } else { 1 }))
         }
 
-        NodeKind::And { input_count } => {
-            let result = (0..*input_count as usize)
-                .all(|i| inputs.get(i).map(|v| v.is_truthy()).unwrap_or(false));
-            vec![Value::Bool(result)]
+        NodeKind::Switch { field, cases } => {
+            let field_value = field_value(field, request, state);
+            let port = node.switch.as_ref().map_or(cases.len() as u8, |t| t.port(&field_value));
+            let handle = cases.get(port as usize).map_or("default", |c| c.handle.as_str());
+            state.log(format!("Switch {} (actual: {}) -> {}", field, field_value.as_text(), handle));
+            Step::Follow(Some(port))
         }
 
-        NodeKind::Or { input_count } => {
-            let result = (0..*input_count as usize)
-                .any(|i| inputs.get(i).map(|v| v.is_truthy()).unwrap_or(false));
-            vec![Value::Bool(result)]
+        NodeKind::RuleGroup { name, .. } => {
+            let matched = node.group.as_ref().is_none_or(|g| evaluate_rule_group(g, request, state));
+            state.log(format!(
+                "RuleGroup '{}' matches: {}",
+                name.as_deref().unwrap_or("unnamed"), matched
+            ));
+            Step::Follow(Some(if matched { 0 } else { 1 }))
         }
 
-        NodeKind::Not => {
-            let input = inputs.get(0).map(|v| v.is_truthy()).unwrap_or(false);
-            vec![Value::Bool(!input)]
+        NodeKind::And { .. } | NodeKind::Or { .. } | NodeKind::Not | NodeKind::Comment { .. } => {
+            state.log(format!("{} nodes do not take part in flow execution", node.kind.display_name()));
+            Step::Done(ExecutionResult::NoMatch)
         }
 
-        NodeKind::RateLimit { mode, counter_name, threshold, penalty_ttl_seconds: _, .. } => {
-            let entry = request.client_ip.map(|ip| ip.to_string()).unwrap_or_default();
-
-            match mode {
-                RateLimitMode::CheckRate => {
-                    let rate = state.increment_rate(counter_name, &entry);
-                    vec![Value::Bool(rate > *threshold)]
+        NodeKind::RateLimit { mode, counter_name, window, threshold, penalty_ttl_seconds, key } => {
+            let entry = rate_limit_entry(key, request, state);
+            let hit = match check_rate_limit(state, *mode, counter_name, &entry, *window, *threshold, *penalty_ttl_seconds) {
+                Ok(hit) => hit,
+                Err(e) if state.failure_policy.rate_limit.is_closed() => {
+                    state.log(format!("Rate limit error: {}", e));
+                    return Step::Done(fail_closed("rate_limit_error".to_string(), state));
                 }
-                RateLimitMode::CheckRateAndPenalize => {
-                    let rate = state.increment_rate(counter_name, &entry);
-                    let exceeded = rate > *threshold;
-                    if exceeded {
-                        state.add_to_penalty_box(counter_name, &entry);
-                    }
-                    vec![Value::Bool(exceeded)]
-                }
-                RateLimitMode::InPenaltyBox => {
-                    let in_box = state.is_in_penalty_box(counter_name, &entry);
-                    vec![Value::Bool(in_box)]
-                }
-                RateLimitMode::AddToPenaltyBox => {
-                    let trigger = inputs.get(0).map(|v| v.is_truthy()).unwrap_or(false);
-                    if trigger {
-                        state.add_to_penalty_box(counter_name, &entry);
-                    }
-                    vec![]
+                Err(e) => {
+                    state.log(format!("Rate limit error: {}, failing open", e));
+                    false
                 }
+            };
+            state.log(format!("Rate limit {} for entry {}: {}", mode.display_name(), entry, hit));
+            match mode {
+                RateLimitMode::AddToPenaltyBox => Step::Follow(Some(0)),
+                _ => Step::Follow(Some(if hit { 0 } else { 1 })),
             }
         }
 
-        NodeKind::Action { .. } | NodeKind::Forward { .. } | NodeKind::Header { .. } => vec![],
+        NodeKind::Action { action } => {
+            match run_action(action, node.template.as_ref(), &node.header_templates, request, state) {
+                Some(result) => Step::Done(result),
+                None => Step::Follow(Some(0)),
+            }
+        }
 
-        NodeKind::Comment { .. } => vec![],
-    };
+        NodeKind::RuleList { name, rules } => {
+            let name = name.as_deref().unwrap_or("unnamed");
+            let matched = node.rules.iter().position(|compiled| evaluate_rule_group(&compiled.group, request, state));
+            let Some(i) = matched else {
+                state.log(format!("RuleList '{}': no rule matches", name));
+                return Step::Follow(Some(rules.len() as u8));
+            };
+            let (rule, compiled) = (&rules[i], &node.rules[i]);
+            state.log(format!("RuleList '{}': rule {} matches", name, rule.id));
+            state.matched_rules.insert(name.to_string(), rule.id.clone());
+            let result = rule.action.as_ref().and_then(|action| {
+                run_action(action, compiled.template.as_ref(), &compiled.header_templates, request, state)
+            });
+            match result {
+                Some(result) => Step::Done(result),
+                None => Step::Follow(Some(i as u8)),
+            }
+        }
 
-    for (port, value) in outputs.into_iter().enumerate() {
-        state.set_output(node.id, port as u8, value);
-    }
-}
+        NodeKind::Forward { backend } => Step::Done(ExecutionResult::Forward { backend: backend.clone() }),
 
-fn evaluate_condition(field_value: &Value, operator: &Operator, cond_value: &ConditionValue) -> bool {
-    match operator {
-        Operator::Equals => match (field_value, cond_value) {
-            (Value::String(a), ConditionValue::String(b)) => a == b,
-            (Value::Number(a), ConditionValue::Number(b)) => (a - b).abs() < f64::EPSILON,
-            (Value::Bool(a), ConditionValue::Bool(b)) => a == b,
-            _ => false,
-        },
-        Operator::NotEquals => !evaluate_condition(field_value, &Operator::Equals, cond_value),
+        NodeKind::Backend { config } => {
+            state.log(format!(
+                "Routing to backend: {} ({}:{})",
+                config.name, config.host, config.port.unwrap_or(443)
+            ));
+            Step::Done(ExecutionResult::Route { backend: config.clone() })
+        }
 
-        Operator::Contains => match (field_value, cond_value) {
-            (Value::String(a), ConditionValue::String(b)) => a.contains(b),
-            _ => false,
-        },
-        Operator::NotContains => !evaluate_condition(field_value, &Operator::Contains, cond_value),
+        NodeKind::BackendPool { name, members, strategy } => {
+            let request_id = request.get_field(&RequestField::RequestId).as_text();
+            let chosen = match strategy {
+                PoolStrategy::WeightedRandom => choose_member(members, &request_id),
+                PoolStrategy::Hash { field } => {
+                    choose_member(members, &field_value(field, request, state).as_text())
+                }
+                PoolStrategy::Cookie { name: cookie } => {
+                    let sticky = request.get_field(&RequestField::Cookie { name: cookie.clone() }).texts();
+                    // A client stays on its member for as long as that member is up
+                    match sticky.iter().find_map(|m| member_named(members, m)) {
+                        Some(index) => Some(index),
+                        None => {
+                            let index = choose_member(members, &request_id);
+                            if let Some(i) = index {
+                                state.sticky_cookie = Some((cookie.clone(), members[i].backend.name.clone()));
+                            }
+                            index
+                        }
+                    }
+                }
+            };
+            match chosen {
+                Some(i) => {
+                    let config = &members[i].backend;
+                    state.log(format!(
+                        "Pool {}: routing to {} ({}:{})",
+                        name, config.name, config.host, config.port.unwrap_or(443)
+                    ));
+                    Step::Done(ExecutionResult::Route { backend: Box::new(config.clone()) })
+                }
+                None => {
+                    state.log(format!("Pool {}: all members are down", name));
+                    Step::Done(ExecutionResult::NoMatch)
+                }
+            }
+        }
 
-        Operator::StartsWith => match (field_value, cond_value) {
-            (Value::String(a), ConditionValue::String(b)) => a.starts_with(b),
-            _ => false,
-        },
-        Operator::EndsWith => match (field_value, cond_value) {
-            (Value::String(a), ConditionValue::String(b)) => a.ends_with(b),
-            _ => false,
-        },
+        NodeKind::Header { operation, name, .. } => {
+            // Values can come from the request (decoded parameters, urlDecode
+            // transforms), so they are kept to a single header line
+            let value = header_safe(&render(node.template.as_ref(), request, state));
+            let header_mod = match operation {
+                HeaderOp::Set => {
+                    state.log(format!("Header SET: {} = {}", name, value));
+                    HeaderMod::Set { name: name.clone(), value }
+                }
+                HeaderOp::Append => {
+                    state.log(format!("Header APPEND: {} += {}", name, value));
+                    HeaderMod::Append { name: name.clone(), value }
+                }
+                HeaderOp::Remove => {
+                    state.log(format!("Header REMOVE: {}", name));
+                    HeaderMod::Remove { name: name.clone() }
+                }
+            };
+            state.header_mods.push(header_mod);
+            Step::Follow(Some(0))
+        }
 
-        Operator::Matches => match (field_value, cond_value) {
-            (Value::String(text), ConditionValue::String(pattern)) => {
-                // Compile regex and check for match
-                Regex::new(pattern)
-                    .map(|re| re.is_match(text))
-                    .unwrap_or(false)
+        NodeKind::Cache { mode } => {
+            match mode {
+                CacheMode::Pass => {
+                    state.log("Cache: BYPASS (pass mode)".to_string());
+                    state.cache_settings.pass = true;
+                }
+                CacheMode::Configure { ttl, stale_while_revalidate, surrogate_keys } => {
+                    if let Some(ttl) = ttl {
+                        state.log(format!("Cache: TTL = {}s", ttl));
+                        state.cache_settings.ttl = Some(*ttl);
+                    }
+                    if let Some(swr) = stale_while_revalidate {
+                        state.log(format!("Cache: SWR = {}s", swr));
+                        state.cache_settings.stale_while_revalidate = Some(*swr);
+                    }
+                    if !surrogate_keys.is_empty() {
+                        state.log(format!("Cache: Surrogate keys = {:?}", surrogate_keys));
+                        state.cache_settings.surrogate_keys = surrogate_keys.clone();
+                    }
+                }
             }
-            _ => false,
+            Step::Follow(Some(0))
         }
 
-        Operator::GreaterThan => match (field_value, cond_value) {
-            (Value::Number(a), ConditionValue::Number(b)) => a > b,
-            _ => false,
-        },
-        Operator::LessThan => match (field_value, cond_value) {
-            (Value::Number(a), ConditionValue::Number(b)) => a < b,
-            _ => false,
-        },
-        Operator::GreaterOrEqual => match (field_value, cond_value) {
-            (Value::Number(a), ConditionValue::Number(b)) => a >= b,
-            _ => false,
-        },
-        Operator::LessOrEqual => match (field_value, cond_value) {
-            (Value::Number(a), ConditionValue::Number(b)) => a <= b,
-            _ => false,
-        },
+        NodeKind::Transform { operation, field, output_var, .. } => {
+            let input = field_value(field, request, state).as_text();
+            let result = apply_transform(*operation, &input, node.pattern.as_ref(), &mut state.trace);
+            state.log(format!("Transform: {:?} on '{}' (input: '{}')", operation, field, input));
+            match output_var {
+                Some(var) => {
+                    state.log(format!("Transform result stored in '{}': '{}'", var, result));
+                    state.variables.insert(var.clone(), result);
+                }
+                None => state.log(format!("Transform result (no var): '{}'", result)),
+            }
+            Step::Follow(Some(0))
+        }
 
-        Operator::In => match (field_value, cond_value) {
-            (Value::String(a), ConditionValue::List(list)) => list.contains(a),
-            _ => false,
-        },
-        Operator::NotIn => !evaluate_condition(field_value, &Operator::In, cond_value),
-
-        Operator::InCidr => match (field_value, cond_value) {
-            (Value::Ip(ip), ConditionValue::CidrList(cidrs)) => {
-                cidrs.iter().any(|cidr| {
-                    cidr.parse::<IpNet>()
-                        .map(|net| net.contains(ip))
-                        .unwrap_or(false)
-                })
+        NodeKind::Split { name, buckets, key } => {
+            let header = |name: &str| request.get_field(&RequestField::Header { name: name.to_string() }).as_text();
+            let mut value = match key {
+                SplitKey::Ip => String::new(),
+                SplitKey::Header { name } => header(name),
+                SplitKey::Cookie { name } => request
+                    .get_field(&RequestField::Cookie { name: name.clone() })
+                    .texts()
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default(),
+                SplitKey::Var { name } => state.variables.get(name).cloned().unwrap_or_default(),
+            };
+            if value.is_empty() {
+                value = request.get_field(&RequestField::ClientIp).as_text();
             }
-            (Value::Ip(ip), ConditionValue::String(cidr)) => {
-                // Single CIDR as string
-                cidr.parse::<IpNet>()
-                    .map(|net| net.contains(ip))
-                    .unwrap_or(false)
+            let weights: Vec<u32> = buckets.iter().map(|b| b.weight).collect();
+            match choose_bucket(&weights, name, &value) {
+                Some(i) => {
+                    state.log(format!("Split {}: bucket {}", name, buckets[i].label));
+                    state.buckets.insert(name.clone(), buckets[i].label.clone());
+                    Step::Follow(Some(i as u8))
+                }
+                None => {
+                    state.log(format!("Split {}: every bucket has weight 0", name));
+                    Step::Done(ExecutionResult::NoMatch)
+                }
             }
-            _ => false,
         }
 
-        Operator::Exists => !matches!(field_value, Value::None),
-        Operator::NotExists => matches!(field_value, Value::None),
+        NodeKind::Invalid { node_type, error } => {
+            state.log(format!("Invalid {} node: {}", node_type, error));
+            match state.failure_policy.invalid_node {
+                Some(FailureMode::Closed) => {
+                    return Step::Done(fail_closed(format!("invalid_node:{}", node.label), state));
+                }
+                // Skip the node, continuing wherever the converter kept a path
+                Some(FailureMode::Open) => return Step::Follow(Some(0)),
+                None => {}
+            }
+            match node_type.as_str() {
+                // Terminal nodes with broken config fail closed
+                "action" | "redirect" | "respond" => Step::Done(ExecutionResult::Block {
+                    status_code: 500,
+                    message: format!("Invalid {} config", node_type),
+                }),
+                // Flow nodes fail open along the edges the converter kept
+                "rateLimit" | "header" | "cache" | "transform" | "switch" | "ruleList" => {
+                    Step::Follow(Some(0))
+                }
+                "request" | "condition" | "ruleGroup" | "backend" | "backendPool" => {
+                    Step::Done(ExecutionResult::NoMatch)
+                }
+                _ => Step::Follow(Some(0)),
+            }
+        }
     }
 }
 
-fn gather_inputs(graph: &Graph, node_id: NodeId, state: &ExecutionState) -> Vec<Value> {
-    let node = match graph.get_node(node_id) {
-        Some(n) => n,
-        None => return vec![],
-    };
-
-    let input_count = node.kind.inputs().len();
-    let mut inputs = vec![Value::None; input_count];
+/// Whether a group's conditions and nested groups, combined with its logic,
+/// match; an empty group always matches.
+///
+/// Conditions are checked before nested groups, and evaluation stops as soon
+/// as the result is known: at the first miss under AND, the first hit under
+/// OR or NOT.
+fn evaluate_rule_group(group: &CompiledGroup, request: &dyn RequestSource, state: &mut ExecutionState) -> bool {
+    if group.conditions.is_empty() && group.groups.is_empty() {
+        return true;
+    }
+    // The result that settles the group: a miss for AND, a hit otherwise
+    let decisive = group.logic != Logic::And;
+    let settled = group.conditions.iter().any(|c| {
+        let field_value = field_value(&c.field, request, state);
+        let matched = c.holds(&field_value);
+        state.log(format!(
+            "Checking {}{} {} {} (actual: {}): {}",
+            if c.negate { "NOT " } else { "" },
+            c.field, c.operator.key(), c.value.as_text(), field_value.as_text(), matched
+        ));
+        matched == decisive
+    }) || group.groups.iter().any(|g| evaluate_rule_group(g, request, state) == decisive);
+
+    match group.logic {
+        Logic::Or => settled,
+        Logic::And | Logic::Not => !settled,
+    }
+}
 
-    for edge in graph.get_incoming_edges(node_id) {
-        if let Some(value) = state.get_output(edge.from_node, edge.from_port) {
-            if (edge.to_port as usize) < inputs.len() {
-                inputs[edge.to_port as usize] = value.clone();
-            }
-        }
+/// Value of a field: transform variables come from the execution state,
+/// everything else from the request. An unset variable has no value.
+fn field_value(field: &RequestField, request: &dyn RequestSource, state: &ExecutionState) -> Value {
+    match field {
+        RequestField::Var { name } => state.variables.get(name).cloned().map(Value::String).unwrap_or(Value::None),
+        other => request.get_field(other),
     }
+}
 
-    inputs
+/// A template (header value, redirect URL, response body, block message) filled in
+fn render(template: Option<&Template>, request: &dyn RequestSource, state: &ExecutionState) -> String {
+    template
+        .map(|t| t.render(|field| field_value(field, request, state)))
+        .unwrap_or_default()
 }
 
-fn check_action_result(graph: &Graph, node: &Node, state: &ExecutionState) -> Option<ExecutionResult> {
-    // Check if trigger input is true
-    let trigger = gather_inputs(graph, node.id, state)
-        .get(0)
-        .map(|v| v.is_truthy())
-        .unwrap_or(false);
+/// Run an action with its parsed templates. `None` means execution
+/// continues: after a log action, or a challenge the client already passed.
+fn run_action(
+    action: &ActionType,
+    template: Option<&Template>,
+    header_templates: &[Template],
+    request: &dyn RequestSource,
+    state: &mut ExecutionState,
+) -> Option<ExecutionResult> {
+    let result = match action {
+        ActionType::Block { status_code, .. } => ExecutionResult::Block {
+            status_code: *status_code,
+            message: render(template, request, state),
+        },
+        ActionType::Challenge { challenge_type } => {
+            if request.challenge_passed() {
+                state.log("Challenge already passed, continuing".to_string());
+                return None;
+            }
+            ExecutionResult::Challenge { challenge_type: *challenge_type }
+        }
+        ActionType::Tarpit { delay_ms, then } => ExecutionResult::Tarpit {
+            delay_ms: *delay_ms,
+            then: match then {
+                TarpitThen::Block { status_code, .. } => TarpitThen::Block {
+                    status_code: *status_code,
+                    message: render(template, request, state),
+                },
+                TarpitThen::Forward => TarpitThen::Forward,
+            },
+        },
+        ActionType::Log { severity, rule, fields, .. } => {
+            let event = SecurityEvent {
+                message: render(template, request, state),
+                severity: *severity,
+                rule: rule.clone(),
+                fields: fields
+                    .iter()
+                    .map(|f| (f.to_string(), field_value(f, request, state).as_text()))
+                    .collect(),
+            };
+            state.log(format!("Log event: {:?}", event));
+            state.events.push(event);
+            return None;
+        }
+        ActionType::Redirect { status_code, preserve_query, .. } => ExecutionResult::Redirect {
+            url: header_safe(&render(template, request, state)),
+            status_code: *status_code,
+            preserve_query: *preserve_query,
+        },
+        ActionType::Respond { status_code, content_type, headers, .. } => ExecutionResult::Respond {
+            status_code: *status_code,
+            content_type: content_type.clone(),
+            headers: headers
+                .iter()
+                .zip(header_templates)
+                .map(|((name, _), value)| {
+                    (name.clone(), header_safe(&value.render(|field| field_value(field, request, state))))
+                })
+                .collect(),
+            body: render(template, request, state),
+        },
+        ActionType::Allow => ExecutionResult::Allow,
+    };
+    state.log(format!("Action: {:?}", result));
+    Some(result)
+}
 
-    if !trigger {
-        return None;
+/// Client identifier for a rate limit node
+fn rate_limit_entry(key: &RateLimitKey, request: &dyn RequestSource, state: &ExecutionState) -> String {
+    let text = |field: RequestField| {
+        let value = field_value(&field, request, state).as_text();
+        (!value.is_empty()).then_some(value)
+    };
+    match key {
+        RateLimitKey::Ip => text(RequestField::ClientIp),
+        RateLimitKey::Fingerprint => text(RequestField::Ja4).or_else(|| text(RequestField::Ja3)),
+        RateLimitKey::Header { name } => text(RequestField::Header { name: name.clone() }),
+        RateLimitKey::Path => text(RequestField::Path),
+        RateLimitKey::QueryParam { name } => text(RequestField::QueryParam { name: name.clone() }),
+        RateLimitKey::Cookie { name } => text(RequestField::Cookie { name: name.clone() }),
+        RateLimitKey::Var { name } => text(RequestField::Var { name: name.clone() }),
     }
+    .unwrap_or_else(|| "unknown".to_string())
+}
 
-    match &node.kind {
-        NodeKind::Action { action } => match action {
-            ActionType::Block { status_code, message } => {
-                Some(ExecutionResult::Block {
-                    status_code: *status_code,
-                    message: message.clone(),
-                })
-            }
-            ActionType::Challenge { challenge_type } => {
-                Some(ExecutionResult::Challenge {
-                    challenge_type: format!("{:?}", challenge_type),
-                })
-            }
-            ActionType::Tarpit { delay_ms } => {
-                Some(ExecutionResult::Tarpit { delay_ms: *delay_ms })
+/// Run a rate limit check against the limiter, or in-memory counters without one.
+fn check_rate_limit(
+    state: &mut ExecutionState,
+    mode: RateLimitMode,
+    counter_name: &str,
+    entry: &str,
+    window: RateWindow,
+    threshold: u32,
+    penalty_ttl_seconds: u32,
+) -> Result<bool, String> {
+    if let Some(limiter) = state.rate_limiter.as_mut() {
+        return match mode {
+            RateLimitMode::CheckRate | RateLimitMode::CheckRateAndPenalize => {
+                limiter.check_rate(counter_name, entry, window, threshold, penalty_ttl_seconds)
             }
-            ActionType::Log { message, severity } => {
-                Some(ExecutionResult::Log {
-                    message: message.clone(),
-                    severity: format!("{:?}", severity),
-                })
+            RateLimitMode::InPenaltyBox => limiter.in_penalty_box(counter_name, entry),
+            RateLimitMode::AddToPenaltyBox => limiter
+                .add_to_penalty_box(counter_name, entry, penalty_ttl_seconds)
+                .map(|_| true),
+        };
+    }
+
+    Ok(match mode {
+        RateLimitMode::CheckRate => state.increment_rate(counter_name, entry) > threshold,
+        RateLimitMode::CheckRateAndPenalize => {
+            let exceeded = state.increment_rate(counter_name, entry) > threshold;
+            if exceeded {
+                state.add_to_penalty_box(counter_name, entry);
             }
-            ActionType::Allow => Some(ExecutionResult::Allow),
-        },
-        NodeKind::Forward { backend } => {
-            Some(ExecutionResult::Forward { backend: backend.clone() })
+            exceeded
         }
-        _ => None,
-    }
+        RateLimitMode::InPenaltyBox => state.is_in_penalty_box(counter_name, entry),
+        RateLimitMode::AddToPenaltyBox => {
+            state.add_to_penalty_box(counter_name, entry);
+            true
+        }
+    })
 }
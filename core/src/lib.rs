@@ -13,6 +13,12 @@
 //! - **Logic nodes** combine signals (AND, OR, NOT)
 //! - **Action nodes** determine the response (block, challenge, allow)
 //!
+//! The editor saves graphs in its own format ([`payload`]); [`convert`]
+//! turns them into a typed [`Graph`], which is compiled once into a
+//! [`Plan`] that both the editor preview and the edge service evaluate
+//! with [`execute`]. [`validate`] reports problems in an editor graph
+//! before it is deployed or loaded.
+//!
 //! ## Example
 //!
 //! ```text
@@ -22,25 +28,56 @@
 //!
 //! ## Modules
 //!
+//! - `payload` - Editor graph format (as stored in the Config Store)
+//! - `convert` - Editor graph to typed graph conversion
 //! - `graph` - Graph structure with nodes and edges
 //! - `nodes` - Node type definitions
 //! - `ports` - Port type definitions
 //! - `value` - Runtime values
+//! - `params` - Query string and cookie parsing for request fields
+//! - `body` - Request body fields (raw prefix, JSON, form) with a size cap
+//! - `time` - Clock fields in time zones and `between` range bounds
+//! - `plan` - Graphs compiled for execution (regexes, CIDRs, resolved edges)
 //! - `interpreter` - Graph execution engine
+//! - `pool` - Member selection for backend pool nodes
+//! - `policy` - Fail-open / fail-closed behavior per failure class
+//! - `template` - `${...}` templates in header values, redirect URLs and messages
+//! - `transform` - Value transformations for transform nodes
+//! - `validate` - Load-time graph diagnostics
 //! - `wasm` - WebAssembly bindings for JavaScript
 
+mod body;
 mod graph;
 mod nodes;
 mod ports;
 mod value;
 mod interpreter;
+mod params;
+mod plan;
+mod policy;
+mod pool;
+mod template;
+mod time;
+mod transform;
+mod validate;
+pub mod convert;
+pub mod payload;
 pub mod wasm;
 
 #[cfg(test)]
 mod bench_compression;
 
+pub use body::*;
 pub use graph::*;
 pub use nodes::*;
 pub use ports::*;
 pub use value::*;
 pub use interpreter::*;
+pub use params::*;
+pub use plan::*;
+pub use policy::*;
+pub use template::*;
+pub use time::*;
+pub use transform::*;
+pub use validate::*;
+pub use convert::{from_payload, ConvertError};
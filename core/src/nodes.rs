@@ -3,12 +3,21 @@
 //! Simplified node system with composable nodes:
 //! - **Condition**: Pick field + operator + value (all matching in one node)
 //! - **Action**: Pick action type (block/challenge/tarpit/log/allow)
+//! - **RuleList**: Ordered rules, each with an output or inline action
+//! - **Switch**: Pick one output by a field's value
 //! - **Logic**: AND/OR/NOT for combining conditions
 //! - **RateLimit**: Check/update rate counters
-//! - **Header**: Set or remove headers
-//! - **Forward**: Route to backend
+//! - **Header**: Set, append or remove headers
+//! - **Forward** / **Backend**: Route to a named or fully configured backend
+//! - **BackendPool**: Route to one of several weighted backends
+//! - **Split**: Send a stable, weighted share of users down each output
+//!
+//! Nodes are connected in flow order: execution starts at the Request node
+//! and follows one output port at a time (see [`crate::execute`]).
 
+use crate::payload::{BackendNodeData, PoolMember};
 use crate::ports::{InputPort, OutputPort, PortType};
+use crate::time::{format_utc, ClockZone};
 use serde::{Deserialize, Serialize};
 
 /// A node in the security rule graph.
@@ -20,6 +29,53 @@ pub struct Node {
     pub kind: NodeKind,
     /// Position in the editor (x, y)
     pub position: (f32, f32),
+    /// Editor node id this node was converted from (for logs and diagnostics)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor_id: Option<String>,
+    /// When the node takes part in execution; always when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<ActiveWindow>,
+}
+
+impl Node {
+    /// Identifier used in logs: the editor id when known, otherwise the numeric id.
+    pub fn label(&self) -> String {
+        match &self.editor_id {
+            Some(id) => id.clone(),
+            None => self.id.to_string(),
+        }
+    }
+}
+
+/// The time a node is active (editor `activeFrom` / `activeUntil`), in
+/// Unix seconds with both ends inclusive. Outside it the node passes
+/// requests through (see [`NodeKind::pass_through_port`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveWindow {
+    pub from: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl ActiveWindow {
+    pub fn contains(&self, now: i64) -> bool {
+        self.from.is_none_or(|from| now >= from) && self.until.is_none_or(|until| now <= until)
+    }
+
+    /// Whether the window is over for good at `now`.
+    pub fn has_ended(&self, now: i64) -> bool {
+        self.until.is_some_and(|until| now > until)
+    }
+}
+
+impl std::fmt::Display for ActiveWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.from, self.until) {
+            (Some(from), Some(until)) => write!(f, "active {} to {}", format_utc(from), format_utc(until)),
+            (Some(from), None) => write!(f, "active from {}", format_utc(from)),
+            (None, Some(until)) => write!(f, "active until {}", format_utc(until)),
+            (None, None) => f.write_str("always active"),
+        }
+    }
 }
 
 /// Unique node identifier
@@ -36,6 +92,10 @@ pub enum NodeKind {
     /// The request input - starting point of every graph
     Request,
 
+    /// The backend response - starting point of the response phase, which
+    /// runs after the origin replies
+    Response,
+
     // ═══════════════════════════════════════════════════════════════════════
     // CONDITION - The main matching node
     // ═══════════════════════════════════════════════════════════════════════
@@ -48,6 +108,16 @@ pub enum NodeKind {
         value: ConditionValue,
     },
 
+    /// Conditions and nested groups combined with one logic operator
+    /// (editor "ruleGroup")
+    RuleGroup {
+        name: Option<String>,
+        logic: Logic,
+        conditions: Vec<RuleCondition>,
+        #[serde(default)]
+        groups: Vec<ConditionGroup>,
+    },
+
     // ═══════════════════════════════════════════════════════════════════════
     // LOGIC - Combine conditions
     // ═══════════════════════════════════════════════════════════════════════
@@ -74,13 +144,16 @@ pub enum NodeKind {
         threshold: u32,
         /// For CheckRate with auto_penalize, or AddToPenaltyBox
         penalty_ttl_seconds: u32,
+        /// What identifies a client
+        #[serde(default)]
+        key: RateLimitKey,
     },
 
     // ═══════════════════════════════════════════════════════════════════════
     // ACTION - What to do when conditions match
     // ═══════════════════════════════════════════════════════════════════════
 
-    /// Single node for all actions (block, challenge, tarpit, log, allow)
+    /// Single node for all actions (block, challenge, tarpit, log, respond, allow)
     Action {
         action: ActionType,
     },
@@ -94,24 +167,72 @@ pub enum NodeKind {
         backend: String,
     },
 
+    /// Route to a dynamically configured backend (editor "backend")
+    Backend {
+        config: Box<BackendNodeData>,
+    },
+
+    /// Route to one member of a pool of backends (editor "backendPool")
+    BackendPool {
+        name: String,
+        members: Vec<PoolMember>,
+        strategy: PoolStrategy,
+    },
+
     // ═══════════════════════════════════════════════════════════════════════
     // TRANSFORM
     // ═══════════════════════════════════════════════════════════════════════
 
-    /// Set or remove a header
+    /// Set, append or remove a header
     Header {
         operation: HeaderOp,
         name: String,
-        /// Value for Set operation (ignored for Remove)
+        /// Value for Set/Append operations (ignored for Remove)
         value: Option<String>,
     },
 
+    /// Configure caching of the backend response
+    Cache { mode: CacheMode },
+
+    /// Transform a field value and store it in a variable
+    Transform {
+        operation: TransformOp,
+        field: RequestField,
+        /// Regex for the Extract operation (capture group 1 is used)
+        pattern: Option<String>,
+        output_var: Option<String>,
+    },
+
     // ═══════════════════════════════════════════════════════════════════════
     // UTILITY
     // ═══════════════════════════════════════════════════════════════════════
 
+    /// Ordered rules: the first whose conditions match continues on its own
+    /// output or takes its inline action; "noMatch" (the last port) otherwise
+    RuleList {
+        name: Option<String>,
+        rules: Vec<ListRule>,
+    },
+
+    /// Continue on the output of the case matching a field's value, or on
+    /// "default" (the last port) when none does
+    Switch {
+        field: RequestField,
+        cases: Vec<SwitchCase>,
+    },
+
+    /// Send each user down one bucket's output, by a stable hash of a key
+    Split {
+        name: String,
+        buckets: Vec<SplitBucket>,
+        key: SplitKey,
+    },
+
     /// Comment/documentation node
     Comment { text: String },
+
+    /// Editor node whose type is unknown or whose data could not be parsed
+    Invalid { node_type: String, error: String },
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -173,19 +294,72 @@ pub enum RequestField {
     // ═══════════════════════════════════════════════════════════════════════
     Method,
     Path,
+    Query,
+    /// Decoded value of a query parameter (editor key "queryParam:<name>")
+    QueryParam { name: String },
+    /// Number of query parameters, repeated ones counted each time
+    QueryParamCount,
+    /// Distinct query parameter names
+    QueryParamNames,
+    /// Decoded value of a cookie (editor key "cookie:<name>")
+    Cookie { name: String },
     Host,
+    Scheme,
     UserAgent,
+    Pop,              // Fastly POP handling the request
+    DdosDetected,
+    RequestId,        // Id the edge assigns the request (as in its logs)
 
     // ═══════════════════════════════════════════════════════════════════════
     // TLS FINGERPRINTS
     // ═══════════════════════════════════════════════════════════════════════
+    TlsVersion,
+    TlsCipher,
     Ja3,
     Ja4,
+    H2Fingerprint,
+    OhFingerprint,
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // REQUEST BODY (buffered only when the graph reads these)
+    // ═══════════════════════════════════════════════════════════════════════
+    /// Beginning of the body, up to the inspection cap
+    Body,
+    /// Length of the whole body in bytes
+    BodyLength,
+    /// Value at a JSON pointer in a JSON body (editor key "json:<pointer>")
+    BodyJson { pointer: String },
+    /// Decoded field of a urlencoded form body (editor key "form:<field>")
+    BodyForm { name: String },
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // CLOCK (zone: UTC offset, IANA name or POSIX rule; empty for UTC)
+    // ═══════════════════════════════════════════════════════════════════════
+    /// Current time in UTC, RFC 3339
+    Now,
+    /// Hour of the day, 0-23 (editor key "hour:<zone>")
+    Hour { zone: ClockZone },
+    /// Day of the week, Mon-Sun (editor key "weekday:<zone>")
+    Weekday { zone: ClockZone },
+    /// Current date, YYYY-MM-DD (editor key "today:<zone>")
+    Today { zone: ClockZone },
 
     // ═══════════════════════════════════════════════════════════════════════
     // CUSTOM HEADER
     // ═══════════════════════════════════════════════════════════════════════
     Header { name: String },
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // BACKEND RESPONSE (response phase only)
+    // ═══════════════════════════════════════════════════════════════════════
+    ResponseStatus,
+    /// Response header (editor key "responseHeader:<name>")
+    ResponseHeader { name: String },
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // TRANSFORM OUTPUT (editor key "var:<name>")
+    // ═══════════════════════════════════════════════════════════════════════
+    Var { name: String },
 }
 
 impl RequestField {
@@ -229,19 +403,223 @@ impl RequestField {
             // Request
             RequestField::Method => "Method",
             RequestField::Path => "Path",
+            RequestField::Query => "Query String",
+            RequestField::QueryParam { .. } => "Query Parameter",
+            RequestField::QueryParamCount => "Query Parameter Count",
+            RequestField::QueryParamNames => "Query Parameter Names",
+            RequestField::Cookie { .. } => "Cookie",
             RequestField::Host => "Host",
+            RequestField::Scheme => "Scheme",
             RequestField::UserAgent => "User Agent",
+            RequestField::Pop => "POP",
+            RequestField::DdosDetected => "DDoS Detected",
+            RequestField::RequestId => "Request ID",
             // TLS
+            RequestField::TlsVersion => "TLS Version",
+            RequestField::TlsCipher => "TLS Cipher",
             RequestField::Ja3 => "JA3",
             RequestField::Ja4 => "JA4",
+            RequestField::H2Fingerprint => "HTTP/2 Fingerprint",
+            RequestField::OhFingerprint => "Original Header Fingerprint",
+            // Body
+            RequestField::Body => "Body",
+            RequestField::BodyLength => "Body Length",
+            RequestField::BodyJson { .. } => "JSON Body Field",
+            RequestField::BodyForm { .. } => "Form Field",
+            // Clock
+            RequestField::Now => "Current Time (UTC)",
+            RequestField::Hour { .. } => "Hour of Day",
+            RequestField::Weekday { .. } => "Day of Week",
+            RequestField::Today { .. } => "Date",
             // Header
             RequestField::Header { .. } => "Header",
+            RequestField::ResponseStatus => "Response Status",
+            RequestField::ResponseHeader { .. } => "Response Header",
+            RequestField::Var { .. } => "Variable",
+        }
+    }
+
+    /// Field name as used in the editor graph format. Headers, query
+    /// parameters, cookies and variables return their name (the editor
+    /// writes a variable as `var:<name>`, a response header as
+    /// `responseHeader:<name>`, and so on).
+    pub fn key(&self) -> &str {
+        match self {
+            RequestField::ClientIp => "clientIp",
+            RequestField::Asn => "asn",
+            RequestField::Country => "country",
+            RequestField::CountryCode3 => "countryCode3",
+            RequestField::Continent => "continent",
+            RequestField::City => "city",
+            RequestField::Region => "region",
+            RequestField::PostalCode => "postalCode",
+            RequestField::Latitude => "latitude",
+            RequestField::Longitude => "longitude",
+            RequestField::MetroCode => "metroCode",
+            RequestField::UtcOffset => "utcOffset",
+            RequestField::ConnSpeed => "connSpeed",
+            RequestField::ConnType => "connType",
+            RequestField::ProxyType => "proxyType",
+            RequestField::ProxyDescription => "proxyDescription",
+            RequestField::IsHostingProvider => "isHostingProvider",
+            RequestField::IsBot => "isBot",
+            RequestField::BotName => "botName",
+            RequestField::IsMobile => "isMobile",
+            RequestField::IsTablet => "isTablet",
+            RequestField::IsDesktop => "isDesktop",
+            RequestField::IsSmartTV => "isSmartTV",
+            RequestField::IsGameConsole => "isGameConsole",
+            RequestField::DeviceName => "deviceName",
+            RequestField::DeviceBrand => "deviceBrand",
+            RequestField::DeviceModel => "deviceModel",
+            RequestField::BrowserName => "browserName",
+            RequestField::BrowserVersion => "browserVersion",
+            RequestField::OsName => "osName",
+            RequestField::OsVersion => "osVersion",
+            RequestField::Method => "method",
+            RequestField::Path => "path",
+            RequestField::Query => "query",
+            RequestField::QueryParamCount => "queryParamCount",
+            RequestField::QueryParamNames => "queryParamNames",
+            RequestField::Host => "host",
+            RequestField::Scheme => "scheme",
+            RequestField::UserAgent => "userAgent",
+            RequestField::Pop => "pop",
+            RequestField::DdosDetected => "ddosDetected",
+            RequestField::RequestId => "requestId",
+            RequestField::TlsVersion => "tlsVersion",
+            RequestField::TlsCipher => "tlsCipher",
+            RequestField::Ja3 => "ja3",
+            RequestField::Ja4 => "ja4",
+            RequestField::H2Fingerprint => "h2Fingerprint",
+            RequestField::OhFingerprint => "ohFingerprint",
+            RequestField::ResponseStatus => "responseStatus",
+            RequestField::Body => "body",
+            RequestField::BodyLength => "bodyLength",
+            RequestField::BodyJson { pointer: name } | RequestField::BodyForm { name } => name,
+            RequestField::Now => "now",
+            RequestField::Hour { .. } => "hour",
+            RequestField::Weekday { .. } => "weekday",
+            RequestField::Today { .. } => "today",
+            RequestField::Header { name }
+            | RequestField::ResponseHeader { name }
+            | RequestField::QueryParam { name }
+            | RequestField::Cookie { name }
+            | RequestField::Var { name } => name,
         }
     }
 
+    /// Parse an editor field name, including the aliases the editor has used.
+    /// `var:<name>` refers to a transform output variable, `queryParam:<name>`
+    /// and `cookie:<name>` to a query parameter and cookie, `json:<pointer>`
+    /// and `form:<field>` to a body field, and `hour:<zone>`, `weekday:<zone>`
+    /// and `today:<zone>` to the clock in a time zone; names that are not a
+    /// known field are treated as a header name.
+    pub fn from_key(key: &str) -> RequestField {
+        if let Some(name) = key.strip_prefix("var:") {
+            return RequestField::Var { name: name.to_string() };
+        }
+        if let Some(name) = key.strip_prefix("responseHeader:") {
+            return RequestField::ResponseHeader { name: name.to_string() };
+        }
+        if let Some(name) = key.strip_prefix("queryParam:") {
+            return RequestField::QueryParam { name: name.to_string() };
+        }
+        if let Some(name) = key.strip_prefix("cookie:") {
+            return RequestField::Cookie { name: name.to_string() };
+        }
+        if let Some(pointer) = key.strip_prefix("json:") {
+            return RequestField::BodyJson { pointer: pointer.to_string() };
+        }
+        if let Some(name) = key.strip_prefix("form:") {
+            return RequestField::BodyForm { name: name.to_string() };
+        }
+        if let Some((clock, zone)) = key.split_once(':') {
+            let zone = ClockZone::new(zone);
+            match clock {
+                "hour" => return RequestField::Hour { zone },
+                "weekday" => return RequestField::Weekday { zone },
+                "today" => return RequestField::Today { zone },
+                _ => {}
+            }
+        }
+        match key {
+            "clientIp" | "client-ip" | "ip" => RequestField::ClientIp,
+            "asn" => RequestField::Asn,
+            "country" => RequestField::Country,
+            "countryCode3" => RequestField::CountryCode3,
+            "continent" => RequestField::Continent,
+            "city" => RequestField::City,
+            "region" => RequestField::Region,
+            "postalCode" => RequestField::PostalCode,
+            "latitude" => RequestField::Latitude,
+            "longitude" => RequestField::Longitude,
+            "metroCode" => RequestField::MetroCode,
+            "utcOffset" => RequestField::UtcOffset,
+            "connSpeed" => RequestField::ConnSpeed,
+            "connType" => RequestField::ConnType,
+            "proxyType" => RequestField::ProxyType,
+            "proxyDescription" => RequestField::ProxyDescription,
+            "isHostingProvider" => RequestField::IsHostingProvider,
+            "isBot" => RequestField::IsBot,
+            "botName" => RequestField::BotName,
+            "isMobile" => RequestField::IsMobile,
+            "isTablet" => RequestField::IsTablet,
+            "isDesktop" => RequestField::IsDesktop,
+            "isSmartTV" => RequestField::IsSmartTV,
+            "isGameConsole" => RequestField::IsGameConsole,
+            "deviceName" => RequestField::DeviceName,
+            "deviceBrand" => RequestField::DeviceBrand,
+            "deviceModel" => RequestField::DeviceModel,
+            "browserName" => RequestField::BrowserName,
+            "browserVersion" => RequestField::BrowserVersion,
+            "osName" => RequestField::OsName,
+            "osVersion" => RequestField::OsVersion,
+            "method" => RequestField::Method,
+            "path" => RequestField::Path,
+            "query" => RequestField::Query,
+            "queryParamCount" => RequestField::QueryParamCount,
+            "queryParamNames" => RequestField::QueryParamNames,
+            "host" => RequestField::Host,
+            "scheme" => RequestField::Scheme,
+            "userAgent" | "user-agent" => RequestField::UserAgent,
+            "pop" | "datacenter" => RequestField::Pop,
+            "ddosDetected" => RequestField::DdosDetected,
+            "requestId" => RequestField::RequestId,
+            "responseStatus" => RequestField::ResponseStatus,
+            "body" => RequestField::Body,
+            "bodyLength" => RequestField::BodyLength,
+            "now" => RequestField::Now,
+            "hour" => RequestField::Hour { zone: ClockZone::UTC },
+            "weekday" => RequestField::Weekday { zone: ClockZone::UTC },
+            "today" => RequestField::Today { zone: ClockZone::UTC },
+            "tlsVersion" | "tls-version" => RequestField::TlsVersion,
+            "tlsCipher" | "tls-cipher" => RequestField::TlsCipher,
+            "ja3" => RequestField::Ja3,
+            "ja4" => RequestField::Ja4,
+            "h2Fingerprint" | "h2-fingerprint" => RequestField::H2Fingerprint,
+            "ohFingerprint" | "oh-fingerprint" => RequestField::OhFingerprint,
+            // Common headers exposed under friendlier names
+            "referer" | "referrer" => RequestField::header("referer"),
+            "accept" => RequestField::header("accept"),
+            "acceptLanguage" | "accept-language" => RequestField::header("accept-language"),
+            "acceptEncoding" | "accept-encoding" => RequestField::header("accept-encoding"),
+            "contentType" | "content-type" => RequestField::header("content-type"),
+            "cacheControl" | "cache-control" => RequestField::header("cache-control"),
+            "xForwardedFor" | "x-forwarded-for" => RequestField::header("x-forwarded-for"),
+            "xForwardedProto" | "x-forwarded-proto" => RequestField::header("x-forwarded-proto"),
+            "xRequestedWith" | "x-requested-with" => RequestField::header("x-requested-with"),
+            _ => RequestField::header(key),
+        }
+    }
+
+    fn header(name: &str) -> RequestField {
+        RequestField::Header { name: name.to_string() }
+    }
+
     /// Returns all non-header fields for the UI picker
     pub fn all_standard() -> &'static [RequestField] {
-        &[
+        const FIELDS: &[RequestField] = &[
             // Connection
             RequestField::ClientIp,
             RequestField::Asn,
@@ -280,12 +658,50 @@ impl RequestField {
             // Request
             RequestField::Method,
             RequestField::Path,
+            RequestField::Query,
+            RequestField::QueryParamCount,
+            RequestField::QueryParamNames,
             RequestField::Host,
+            RequestField::Scheme,
             RequestField::UserAgent,
+            RequestField::Pop,
+            RequestField::DdosDetected,
+            RequestField::RequestId,
             // TLS
+            RequestField::TlsVersion,
+            RequestField::TlsCipher,
             RequestField::Ja3,
             RequestField::Ja4,
-        ]
+            RequestField::H2Fingerprint,
+            RequestField::OhFingerprint,
+            // Body
+            RequestField::Body,
+            RequestField::BodyLength,
+            // Clock (UTC)
+            RequestField::Now,
+            RequestField::Hour { zone: ClockZone::UTC },
+            RequestField::Weekday { zone: ClockZone::UTC },
+            RequestField::Today { zone: ClockZone::UTC },
+            // Response
+            RequestField::ResponseStatus,
+        ];
+        FIELDS
+    }
+
+    /// Whether the field reads the request body, which is then buffered.
+    pub fn is_body(&self) -> bool {
+        matches!(
+            self,
+            RequestField::Body | RequestField::BodyLength | RequestField::BodyJson { .. } | RequestField::BodyForm { .. }
+        )
+    }
+
+    /// Time zone of a clock field; `None` for other fields.
+    pub fn time_zone(&self) -> Option<&ClockZone> {
+        match self {
+            RequestField::Hour { zone } | RequestField::Weekday { zone } | RequestField::Today { zone } => Some(zone),
+            _ => None,
+        }
     }
 
     /// Get field category for UI grouping
@@ -303,10 +719,36 @@ impl RequestField {
             RequestField::IsGameConsole | RequestField::DeviceName | RequestField::DeviceBrand |
             RequestField::DeviceModel | RequestField::BrowserName | RequestField::BrowserVersion |
             RequestField::OsName | RequestField::OsVersion => "Device Detection",
-            RequestField::Method | RequestField::Path | RequestField::Host |
-            RequestField::UserAgent => "Request",
-            RequestField::Ja3 | RequestField::Ja4 => "TLS Fingerprint",
-            RequestField::Header { .. } => "Custom",
+            RequestField::Method | RequestField::Path | RequestField::Query |
+            RequestField::QueryParam { .. } | RequestField::QueryParamCount |
+            RequestField::QueryParamNames | RequestField::Cookie { .. } |
+            RequestField::Host | RequestField::Scheme | RequestField::UserAgent |
+            RequestField::Pop | RequestField::DdosDetected | RequestField::RequestId => "Request",
+            RequestField::TlsVersion | RequestField::TlsCipher | RequestField::Ja3 |
+            RequestField::Ja4 | RequestField::H2Fingerprint |
+            RequestField::OhFingerprint => "TLS Fingerprint",
+            RequestField::Body | RequestField::BodyLength | RequestField::BodyJson { .. } |
+            RequestField::BodyForm { .. } => "Body",
+            RequestField::Now | RequestField::Hour { .. } | RequestField::Weekday { .. } |
+            RequestField::Today { .. } => "Clock",
+            RequestField::ResponseStatus | RequestField::ResponseHeader { .. } => "Response",
+            RequestField::Header { .. } | RequestField::Var { .. } => "Custom",
+        }
+    }
+}
+
+impl std::fmt::Display for RequestField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestField::Var { name } => write!(f, "var:{}", name),
+            RequestField::ResponseHeader { name } => write!(f, "responseHeader:{}", name),
+            RequestField::QueryParam { name } => write!(f, "queryParam:{}", name),
+            RequestField::Cookie { name } => write!(f, "cookie:{}", name),
+            RequestField::BodyJson { pointer } => write!(f, "json:{}", pointer),
+            RequestField::BodyForm { name } => write!(f, "form:{}", name),
+            RequestField::Hour { zone } | RequestField::Weekday { zone } |
+            RequestField::Today { zone } if !zone.name().is_empty() => write!(f, "{}:{}", self.key(), zone.name()),
+            other => f.write_str(other.key()),
         }
     }
 }
@@ -334,7 +776,11 @@ pub enum Operator {
     NotIn,    // Value is not in list
 
     // IP-specific
-    InCidr,   // IP is in CIDR range(s)
+    InCidr,     // IP is in CIDR range(s)
+    NotInCidr,  // IP is not in any CIDR range
+
+    // Ranges (numbers, weekdays, dates and times)
+    Between,
 
     // Existence
     Exists,
@@ -358,11 +804,81 @@ impl Operator {
             Operator::In => "in list",
             Operator::NotIn => "not in list",
             Operator::InCidr => "in CIDR",
+            Operator::NotInCidr => "not in CIDR",
+            Operator::Between => "between",
             Operator::Exists => "exists",
             Operator::NotExists => "not exists",
         }
     }
 
+    /// Operator name as used in the editor graph format.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Operator::Equals => "equals",
+            Operator::NotEquals => "notEquals",
+            Operator::Contains => "contains",
+            Operator::NotContains => "notContains",
+            Operator::StartsWith => "startsWith",
+            Operator::EndsWith => "endsWith",
+            Operator::Matches => "matches",
+            Operator::GreaterThan => "greaterThan",
+            Operator::LessThan => "lessThan",
+            Operator::GreaterOrEqual => "greaterOrEqual",
+            Operator::LessOrEqual => "lessOrEqual",
+            Operator::In => "in",
+            Operator::NotIn => "notIn",
+            Operator::InCidr => "inCidr",
+            Operator::NotInCidr => "notInCidr",
+            Operator::Between => "between",
+            Operator::Exists => "exists",
+            Operator::NotExists => "notExists",
+        }
+    }
+
+    /// Parse an editor operator name, including short aliases.
+    pub fn from_key(key: &str) -> Option<Operator> {
+        let op = match key {
+            "equals" => Operator::Equals,
+            "notEquals" | "!=" => Operator::NotEquals,
+            "contains" => Operator::Contains,
+            "notContains" => Operator::NotContains,
+            "startsWith" | "starts" => Operator::StartsWith,
+            "endsWith" | "ends" => Operator::EndsWith,
+            "matches" => Operator::Matches,
+            "greaterThan" | ">" => Operator::GreaterThan,
+            "lessThan" | "<" => Operator::LessThan,
+            "greaterOrEqual" | ">=" => Operator::GreaterOrEqual,
+            "lessOrEqual" | "<=" => Operator::LessOrEqual,
+            "in" => Operator::In,
+            "notIn" | "!in" => Operator::NotIn,
+            "inCidr" => Operator::InCidr,
+            "notInCidr" => Operator::NotInCidr,
+            "between" => Operator::Between,
+            "exists" => Operator::Exists,
+            "notExists" => Operator::NotExists,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Whether the operator compares numbers
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Operator::GreaterThan | Operator::LessThan | Operator::GreaterOrEqual | Operator::LessOrEqual
+        )
+    }
+
+    /// Whether the operator holds when a value is absent from the field
+    /// ("not equals", "not in", ...)
+    pub fn is_negative(&self) -> bool {
+        matches!(
+            self,
+            Operator::NotEquals | Operator::NotContains | Operator::NotIn | Operator::NotInCidr |
+                Operator::NotExists
+        )
+    }
+
     /// Operators suitable for string fields
     pub fn string_operators() -> &'static [Operator] {
         &[
@@ -389,6 +905,7 @@ impl Operator {
             Operator::LessThan,
             Operator::GreaterOrEqual,
             Operator::LessOrEqual,
+            Operator::Between,
             Operator::In,
             Operator::NotIn,
         ]
@@ -402,6 +919,7 @@ impl Operator {
             Operator::In,
             Operator::NotIn,
             Operator::InCidr,
+            Operator::NotInCidr,
         ]
     }
 }
@@ -416,6 +934,99 @@ pub enum ConditionValue {
     CidrList(Vec<String>),  // For InCidr operator
 }
 
+impl ConditionValue {
+    /// Build a condition value from the editor's string form.
+    /// List operators split on commas; numeric operators parse a number.
+    pub fn from_editor(operator: &Operator, raw: &str) -> ConditionValue {
+        let items = || raw.split(',').map(|s| s.trim().to_string()).collect();
+        match operator {
+            Operator::In | Operator::NotIn => ConditionValue::List(items()),
+            Operator::InCidr | Operator::NotInCidr => ConditionValue::CidrList(items()),
+            op if op.is_numeric() => raw
+                .trim()
+                .parse::<f64>()
+                .map(ConditionValue::Number)
+                .unwrap_or_else(|_| ConditionValue::String(raw.to_string())),
+            _ => ConditionValue::String(raw.to_string()),
+        }
+    }
+
+    /// Text form used for string comparisons and logs.
+    pub fn as_text(&self) -> String {
+        match self {
+            ConditionValue::String(s) => s.clone(),
+            ConditionValue::Number(n) => crate::value::format_number(*n),
+            ConditionValue::Bool(b) => b.to_string(),
+            ConditionValue::List(items) | ConditionValue::CidrList(items) => items.join(","),
+        }
+    }
+
+    /// Individual items for list and CIDR operators.
+    pub fn items(&self) -> Vec<String> {
+        match self {
+            ConditionValue::List(items) | ConditionValue::CidrList(items) => items.clone(),
+            ConditionValue::String(s) => s.split(',').map(|s| s.trim().to_string()).collect(),
+            other => vec![other.as_text()],
+        }
+    }
+}
+
+/// A single condition inside a rule group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleCondition {
+    pub field: RequestField,
+    pub operator: Operator,
+    pub value: ConditionValue,
+    /// Match when the condition does not hold
+    #[serde(default)]
+    pub negate: bool,
+}
+
+/// Conditions and nested groups combined with one logic operator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConditionGroup {
+    pub logic: Logic,
+    pub conditions: Vec<RuleCondition>,
+    #[serde(default)]
+    pub groups: Vec<ConditionGroup>,
+}
+
+/// A rule in a rule list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListRule {
+    /// Editor rule id: the output handle, logged when the rule matches
+    pub id: String,
+    pub logic: Logic,
+    pub conditions: Vec<RuleCondition>,
+    #[serde(default)]
+    pub groups: Vec<ConditionGroup>,
+    /// Taken when the rule matches; a log action, or a challenge that was
+    /// passed, continues on the rule's output
+    pub action: Option<ActionType>,
+}
+
+/// How the conditions of a rule group are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Logic {
+    /// All conditions (and nested groups) match
+    And,
+    /// At least one condition or nested group matches
+    Or,
+    /// No condition or nested group matches
+    Not,
+}
+
+impl Logic {
+    /// Parse the editor's logic name (case-insensitive, defaults to AND).
+    pub fn from_key(key: &str) -> Logic {
+        match key.to_uppercase().as_str() {
+            "OR" => Logic::Or,
+            "NOT" => Logic::Not,
+            _ => Logic::And,
+        }
+    }
+}
+
 /// Rate limiting time windows
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RateWindow {
@@ -434,6 +1045,80 @@ impl RateWindow {
     }
 }
 
+/// What identifies a client for rate limiting
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RateLimitKey {
+    /// Client IP address
+    #[default]
+    Ip,
+    /// TLS fingerprint (JA4, falling back to JA3)
+    Fingerprint,
+    /// Value of a request header
+    Header { name: String },
+    /// Request path
+    Path,
+    /// Value of a query parameter
+    QueryParam { name: String },
+    /// Value of a request cookie
+    Cookie { name: String },
+    /// Value of a transform output variable
+    Var { name: String },
+}
+
+/// How a backend pool picks a member
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PoolStrategy {
+    /// Each request independently, in proportion to the weights
+    WeightedRandom,
+    /// Consistent hash of a request field, so a client keeps its member
+    Hash { field: RequestField },
+    /// Like WeightedRandom, then pinned to the member named in a cookie
+    Cookie { name: String },
+}
+
+/// An output of a switch node and the values that lead to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwitchCase {
+    /// Editor output handle
+    pub handle: String,
+    pub match_type: SwitchMatch,
+    pub values: Vec<String>,
+}
+
+/// How a switch case's values are compared with the field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwitchMatch {
+    Exact,
+    Prefix,
+    /// The field is an IP address in one of the CIDR ranges
+    Cidr,
+}
+
+/// An output of a split node and its share of the traffic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitBucket {
+    /// Editor output handle
+    pub handle: String,
+    /// Name the bucket is logged under
+    pub label: String,
+    pub weight: u32,
+}
+
+/// What identifies a user for a split. A request without the key is
+/// bucketed by client IP.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SplitKey {
+    /// Client IP address
+    #[default]
+    Ip,
+    /// Value of a request header
+    Header { name: String },
+    /// Value of a request cookie
+    Cookie { name: String },
+    /// Value of a transform output variable
+    Var { name: String },
+}
+
 /// Rate limiting modes
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RateLimitMode {
@@ -458,6 +1143,9 @@ impl RateLimitMode {
     }
 }
 
+/// Longest a tarpit holds a request; the edge shortens longer delays.
+pub const MAX_TARPIT_DELAY_MS: u32 = 10_000;
+
 /// Action types for the Action node
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ActionType {
@@ -468,12 +1156,31 @@ pub enum ActionType {
     Challenge {
         challenge_type: ChallengeType,
     },
+    /// Hold the request for `delay_ms`, then block or forward it
     Tarpit {
         delay_ms: u32,
+        then: TarpitThen,
     },
+    /// Record a security event and continue
     Log {
         message: String,
         severity: LogSeverity,
+        /// Rule name recorded with the event
+        rule: Option<String>,
+        /// Request fields whose values are recorded with the event
+        fields: Vec<RequestField>,
+    },
+    Redirect {
+        url: String,
+        status_code: u16,
+        preserve_query: bool,
+    },
+    /// Answer with a synthetic response (body and header values are templates)
+    Respond {
+        status_code: u16,
+        content_type: String,
+        headers: Vec<(String, String)>,
+        body: String,
     },
     Allow,
 }
@@ -485,6 +1192,8 @@ impl ActionType {
             ActionType::Challenge { .. } => "Challenge",
             ActionType::Tarpit { .. } => "Tarpit",
             ActionType::Log { .. } => "Log",
+            ActionType::Redirect { .. } => "Redirect",
+            ActionType::Respond { .. } => "Respond",
             ActionType::Allow => "Allow",
         }
     }
@@ -492,12 +1201,36 @@ impl ActionType {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChallengeType {
+    /// JavaScript check the browser passes without user input
     NonInteractive,
     Interactive,
     Captcha,
+    /// JavaScript proof-of-work (the browser searches for a hash)
+    ProofOfWork,
+}
+
+impl ChallengeType {
+    /// Parse an editor challenge type. Unknown types get the JS challenge.
+    pub fn from_key(key: &str) -> ChallengeType {
+        match key {
+            "proofOfWork" => ChallengeType::ProofOfWork,
+            "interactive" => ChallengeType::Interactive,
+            "captcha" => ChallengeType::Captcha,
+            _ => ChallengeType::NonInteractive,
+        }
+    }
+}
+
+/// What a tarpit does once the delay is over
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TarpitThen {
+    Block { status_code: u16, message: String },
+    /// Forward to the default backend, as if allowed
+    Forward,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LogSeverity {
     Debug,
     Info,
@@ -505,13 +1238,69 @@ pub enum LogSeverity {
     Error,
 }
 
+impl LogSeverity {
+    /// Parse an editor severity. Unknown severities log as info.
+    pub fn from_key(key: &str) -> LogSeverity {
+        match key {
+            "debug" => LogSeverity::Debug,
+            "warning" | "warn" => LogSeverity::Warning,
+            "error" => LogSeverity::Error,
+            _ => LogSeverity::Info,
+        }
+    }
+}
+
 /// Header operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HeaderOp {
     Set,
+    Append,
     Remove,
 }
 
+/// Cache behavior for the backend response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CacheMode {
+    /// Bypass the cache entirely
+    Pass,
+    /// Override cache lifetimes (all durations in seconds)
+    Configure {
+        ttl: Option<u64>,
+        stale_while_revalidate: Option<u64>,
+        surrogate_keys: Vec<String>,
+    },
+}
+
+/// Transform operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransformOp {
+    Lowercase,
+    Uppercase,
+    UrlDecode,
+    Base64Decode,
+    HtmlDecode,
+    RemoveWhitespace,
+    /// Regex capture group 1 of the node's pattern
+    Extract,
+}
+
+impl TransformOp {
+    /// Parse an editor transform operation name.
+    pub fn from_key(key: &str) -> Option<TransformOp> {
+        let op = match key {
+            "lowercase" => TransformOp::Lowercase,
+            "uppercase" => TransformOp::Uppercase,
+            "urlDecode" => TransformOp::UrlDecode,
+            "base64Decode" => TransformOp::Base64Decode,
+            "htmlDecode" => TransformOp::HtmlDecode,
+            "removeWhitespace" => TransformOp::RemoveWhitespace,
+            "extract" => TransformOp::Extract,
+            _ => return None,
+        };
+        Some(op)
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // Node metadata (for editor)
 // ═══════════════════════════════════════════════════════════════════════════
@@ -521,9 +1310,13 @@ impl NodeKind {
     pub fn display_name(&self) -> String {
         match self {
             NodeKind::Request => "Request".to_string(),
+            NodeKind::Response => "Response".to_string(),
             NodeKind::Condition { field, operator, .. } => {
                 format!("{} {}", field.display_name(), operator.display_name())
             }
+            NodeKind::RuleGroup { name, .. } => {
+                name.clone().unwrap_or_else(|| "Rule Group".to_string())
+            }
             NodeKind::And { .. } => "AND".to_string(),
             NodeKind::Or { .. } => "OR".to_string(),
             NodeKind::Not => "NOT".to_string(),
@@ -539,77 +1332,137 @@ impl NodeKind {
             }
             NodeKind::Action { action } => action.display_name().to_string(),
             NodeKind::Forward { backend } => format!("Forward: {}", backend),
+            NodeKind::Backend { config } => format!("Backend: {}", config.name),
+            NodeKind::BackendPool { name, .. } => format!("Pool: {}", name),
             NodeKind::Header { operation, name, .. } => {
                 match operation {
                     HeaderOp::Set => format!("Set: {}", name),
+                    HeaderOp::Append => format!("Append: {}", name),
                     HeaderOp::Remove => format!("Remove: {}", name),
                 }
             }
+            NodeKind::Cache { mode } => match mode {
+                CacheMode::Pass => "Cache: Pass".to_string(),
+                CacheMode::Configure { .. } => "Cache".to_string(),
+            },
+            NodeKind::Transform { operation, field, .. } => {
+                format!("{:?}: {}", operation, field.display_name())
+            }
+            NodeKind::RuleList { name, .. } => name.clone().unwrap_or_else(|| "Rule List".to_string()),
+            NodeKind::Switch { field, .. } => format!("Switch: {}", field.display_name()),
+            NodeKind::Split { name, .. } => format!("Split: {}", name),
             NodeKind::Comment { .. } => "Comment".to_string(),
+            NodeKind::Invalid { node_type, .. } => format!("Invalid: {}", node_type),
         }
     }
 
     /// Get the category for this node
     pub fn category(&self) -> NodeCategory {
         match self {
-            NodeKind::Request => NodeCategory::Input,
-            NodeKind::Condition { .. } => NodeCategory::Condition,
-            NodeKind::And { .. } | NodeKind::Or { .. } | NodeKind::Not => NodeCategory::Logic,
+            NodeKind::Request | NodeKind::Response => NodeCategory::Input,
+            NodeKind::Condition { .. }
+            | NodeKind::RuleGroup { .. }
+            | NodeKind::RuleList { .. }
+            | NodeKind::Switch { .. } => NodeCategory::Condition,
+            NodeKind::And { .. } | NodeKind::Or { .. } | NodeKind::Not | NodeKind::Split { .. } => {
+                NodeCategory::Logic
+            }
             NodeKind::RateLimit { .. } => NodeCategory::RateLimit,
             NodeKind::Action { .. } => NodeCategory::Action,
-            NodeKind::Forward { .. } => NodeCategory::Routing,
-            NodeKind::Header { .. } => NodeCategory::Transform,
-            NodeKind::Comment { .. } => NodeCategory::Utility,
+            NodeKind::Forward { .. } | NodeKind::Backend { .. } | NodeKind::BackendPool { .. } => {
+                NodeCategory::Routing
+            }
+            NodeKind::Header { .. } | NodeKind::Cache { .. } | NodeKind::Transform { .. } => {
+                NodeCategory::Transform
+            }
+            NodeKind::Comment { .. } | NodeKind::Invalid { .. } => NodeCategory::Utility,
         }
     }
 
     /// Get input port definitions for this node
     pub fn inputs(&self) -> Vec<InputPort> {
         match self {
-            NodeKind::Request => vec![],
-            NodeKind::Condition { .. } => vec![], // Self-contained, no inputs needed
+            NodeKind::Request | NodeKind::Response | NodeKind::Comment { .. } => vec![],
             NodeKind::And { input_count } | NodeKind::Or { input_count } => {
                 (0..*input_count)
                     .map(|i| InputPort::new(format!("in{}", i), PortType::Bool))
                     .collect()
             }
             NodeKind::Not => vec![InputPort::new("in", PortType::Bool)],
-            NodeKind::RateLimit { mode, .. } => {
-                match mode {
-                    RateLimitMode::AddToPenaltyBox => vec![InputPort::new("trigger", PortType::Bool)],
-                    _ => vec![], // Uses client IP implicitly
-                }
-            }
-            NodeKind::Action { .. } => vec![InputPort::new("trigger", PortType::Bool)],
-            NodeKind::Forward { .. } => vec![InputPort::new("trigger", PortType::Bool)],
-            NodeKind::Header { .. } => vec![InputPort::new("trigger", PortType::Bool)],
-            NodeKind::Comment { .. } => vec![],
+            _ => vec![InputPort::new("trigger", PortType::Flow)],
         }
     }
 
-    /// Get output port definitions for this node
+    /// Get output port definitions for this node.
+    ///
+    /// Port indices match the editor's source handles, so an edge's
+    /// `from_port` selects which branch execution continues on.
     pub fn outputs(&self) -> Vec<OutputPort> {
+        let flow = |names: &[&str]| -> Vec<OutputPort> {
+            names.iter().map(|n| OutputPort::new(*n, PortType::Flow)).collect()
+        };
         match self {
-            NodeKind::Request => vec![OutputPort::new("request", PortType::Any)],
-            NodeKind::Condition { .. } => vec![OutputPort::new("match", PortType::Bool)],
+            NodeKind::Request => flow(&["request"]),
+            NodeKind::Response => flow(&["response"]),
+            NodeKind::Condition { .. } => flow(&["true", "false"]),
+            NodeKind::RuleGroup { .. } => flow(&["match", "noMatch"]),
             NodeKind::And { .. } | NodeKind::Or { .. } | NodeKind::Not => {
                 vec![OutputPort::new("out", PortType::Bool)]
             }
             NodeKind::RateLimit { mode, .. } => {
                 match mode {
                     RateLimitMode::CheckRate | RateLimitMode::CheckRateAndPenalize => {
-                        vec![OutputPort::new("exceeded", PortType::Bool)]
-                    }
-                    RateLimitMode::InPenaltyBox => {
-                        vec![OutputPort::new("in_box", PortType::Bool)]
+                        flow(&["exceeded", "ok"])
                     }
-                    RateLimitMode::AddToPenaltyBox => vec![], // Side effect only
+                    RateLimitMode::InPenaltyBox => flow(&["in_box", "ok"]),
+                    RateLimitMode::AddToPenaltyBox => flow(&["next"]),
                 }
             }
-            NodeKind::Action { .. } => vec![], // Terminal
-            NodeKind::Forward { .. } => vec![], // Terminal
-            NodeKind::Header { .. } => vec![], // Terminal (or could chain)
-            NodeKind::Comment { .. } => vec![],
+            NodeKind::Header { .. } | NodeKind::Cache { .. } => flow(&["next"]),
+            NodeKind::Transform { .. } => flow(&["value_out"]),
+            NodeKind::RuleList { rules, .. } => rules
+                .iter()
+                .map(|r| r.id.as_str())
+                .chain(["noMatch"])
+                .map(|name| OutputPort::new(name, PortType::Flow))
+                .collect(),
+            NodeKind::Switch { cases, .. } => cases
+                .iter()
+                .map(|c| c.handle.as_str())
+                .chain(["default"])
+                .map(|name| OutputPort::new(name, PortType::Flow))
+                .collect(),
+            NodeKind::Split { buckets, .. } => {
+                buckets.iter().map(|b| OutputPort::new(b.handle.as_str(), PortType::Flow)).collect()
+            }
+            // A challenge the client has already passed continues
+            NodeKind::Action { action: ActionType::Challenge { .. } } => flow(&["passed"]),
+            NodeKind::Action { action: ActionType::Log { .. } } => flow(&["next"]),
+            // Terminal
+            NodeKind::Action { .. }
+            | NodeKind::Forward { .. }
+            | NodeKind::Backend { .. }
+            | NodeKind::BackendPool { .. } => vec![],
+            NodeKind::Comment { .. } | NodeKind::Invalid { .. } => vec![],
+        }
+    }
+
+    /// Port a node outside its active window passes requests through: the
+    /// one it takes when it doesn't act (`false`, `noMatch`, `default`,
+    /// `ok`, a challenge's `passed`, `next`). `None` for nodes that only end
+    /// execution (terminal actions, backends, splits), whose branch then
+    /// ends without a decision.
+    pub fn pass_through_port(&self) -> Option<u8> {
+        match self {
+            NodeKind::Condition { .. } | NodeKind::RuleGroup { .. } => Some(1),
+            NodeKind::RuleList { rules, .. } => Some(rules.len() as u8),
+            NodeKind::Switch { cases, .. } => Some(cases.len() as u8),
+            NodeKind::RateLimit { mode: RateLimitMode::AddToPenaltyBox, .. } => Some(0),
+            NodeKind::RateLimit { .. } => Some(1),
+            NodeKind::Request | NodeKind::Response | NodeKind::Header { .. } | NodeKind::Cache { .. } |
+            NodeKind::Transform { .. } => Some(0),
+            NodeKind::Action { action: ActionType::Challenge { .. } | ActionType::Log { .. } } => Some(0),
+            _ => None,
         }
     }
 
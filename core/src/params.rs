@@ -0,0 +1,145 @@
+//! Query string and cookie parsing for the `queryParam:<name>`,
+//! `cookie:<name>`, `queryParamCount` and `queryParamNames` fields, and
+//! for the cookies read by pools, splits and the challenge.
+//!
+//! Names and values are percent-decoded before they are compared, so
+//! `?q=%3Cscript%3E` matches a rule looking for `<script>`. A parameter
+//! or cookie given more than once yields all of its values, so a payload
+//! can't hide behind an innocent first copy.
+
+use crate::Value;
+
+/// Decoded `(name, value)` pairs of a query string, in order and with
+/// repeated names kept. A leading `?` is skipped, `+` decodes to a space,
+/// and a parameter without `=` has an empty value.
+pub fn query_pairs(query: &str) -> Vec<(String, String)> {
+    let query = query.strip_prefix('?').unwrap_or(query);
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name, true), percent_decode(value, true))
+        })
+        .collect()
+}
+
+/// Value of query parameter `name`: a string, or a list of every value in
+/// order when the parameter is repeated. A missing parameter has no value.
+pub fn query_param(query: &str, name: &str) -> Value {
+    values(
+        query_pairs(query)
+            .into_iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value)
+            .collect(),
+    )
+}
+
+/// Number of query parameters, repeated ones counted each time.
+pub fn query_param_count(query: &str) -> Value {
+    Value::Number(query_pairs(query).len() as f64)
+}
+
+/// Distinct query parameter names in the order they first appear.
+pub fn query_param_names(query: &str) -> Value {
+    let mut names: Vec<String> = Vec::new();
+    for (name, _) in query_pairs(query) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Value::List(names.into_iter().map(Value::String).collect())
+}
+
+/// Value of cookie `name` in a `Cookie` header (several headers joined
+/// with `;`): a string, or a list of every value when the cookie is sent
+/// more than once.
+pub fn cookie(header: &str, name: &str) -> Value {
+    values(cookie_values(header, name))
+}
+
+/// Every value of cookie `name` in a `Cookie` header, in order. Quotes
+/// around a value are removed and `%XX` escapes decoded; `+` is kept.
+pub fn cookie_values(header: &str, name: &str) -> Vec<String> {
+    header
+        .split(';')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            (key.trim() == name).then_some(value.trim())
+        })
+        .map(|value| {
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            percent_decode(value, false)
+        })
+        .collect()
+}
+
+fn values(mut found: Vec<String>) -> Value {
+    match found.len() {
+        0 => Value::None,
+        1 => Value::String(found.remove(0)),
+        _ => Value::List(found.into_iter().map(Value::String).collect()),
+    }
+}
+
+/// Decode `%XX` escapes (and `+` when `plus_as_space`). Malformed escapes
+/// are kept as they are; invalid UTF-8 becomes U+FFFD.
+fn percent_decode(text: &str, plus_as_space: bool) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1..i + 3)) {
+            (b'%', Some(&[hi, lo])) if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                out.push(hex_value(hi) << 4 | hex_value(lo));
+                i += 3;
+                continue;
+            }
+            (b'+', _) if plus_as_space => out.push(b' '),
+            (byte, _) => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn hex_value(digit: u8) -> u8 {
+    (digit as char).to_digit(16).unwrap_or(0) as u8
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_params_decoded_with_duplicates() {
+        let query = "?id=1&q=%3Cscript%3E+x&id=2&debug&&bad=%zz%+1";
+        assert_eq!(query_param(query, "q"), Value::String("<script> x".to_string()));
+        assert_eq!(query_param(query, "id").as_text(), "1,2");
+        assert_eq!(query_param(query, "debug"), Value::String(String::new()));
+        assert_eq!(query_param(query, "bad"), Value::String("%zz% 1".to_string()));
+        assert_eq!(query_param(query, "missing"), Value::None);
+        assert_eq!(query_param_count(query), Value::Number(5.0));
+        assert_eq!(query_param_names(query).as_text(), "id,q,debug,bad");
+        assert_eq!(query_param_count(""), Value::Number(0.0));
+    }
+
+    #[test]
+    fn test_cookie_values() {
+        let header = "session=abc%20def; theme=\"dark\"; a+b=1; session=second";
+        assert_eq!(cookie(header, "theme"), Value::String("dark".to_string()));
+        assert_eq!(cookie(header, "a+b"), Value::String("1".to_string()));
+        assert_eq!(cookie(header, "session").as_text(), "abc def,second");
+        assert_eq!(cookie(header, "missing"), Value::None);
+        assert_eq!(cookie_values("a=1; cc_pool=green; b=2", "cc_pool"), vec!["green"]);
+        assert!(cookie_values("a=1", "cc_pool").is_empty());
+    }
+}
@@ -0,0 +1,524 @@
+//! Editor graph format (React Flow nodes + edges).
+//!
+//! These types match the editor's node/edge structure exactly, so the
+//! same JSON is stored in the Config Store, loaded at the edge and
+//! previewed in the editor. See [`crate::convert`] for turning a payload
+//! into a typed [`crate::Graph`].
+
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// Graph Structure (matches editor's React Flow format)
+// ============================================================================
+
+/// The complete graph payload from the editor.
+/// This is the single format used for storage; it is converted into a
+/// typed graph before evaluation.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GraphPayload {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// A single node in the visual graph.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GraphNode {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub node_type: String,
+    pub position: NodePosition,
+    pub data: serde_json::Value,
+}
+
+/// Node position for layout (preserved for editor reload).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NodePosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// An edge connecting two nodes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GraphEdge {
+    pub id: String,
+    pub source: String,
+    pub target: String,
+    #[serde(rename = "sourceHandle")]
+    pub source_handle: Option<String>,
+    #[serde(rename = "targetHandle")]
+    pub target_handle: Option<String>,
+    #[serde(rename = "type")]
+    pub edge_type: Option<String>,
+    /// Execution order among edges leaving the same node (lowest first);
+    /// edges without one run after those with one, in editor order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+}
+
+// ============================================================================
+// Node Data Types (parsed from GraphNode.data)
+// ============================================================================
+
+/// Node data for condition nodes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConditionNodeData {
+    pub field: String,
+    pub operator: String,
+    pub value: String,
+    #[serde(flatten)]
+    pub names: FieldNames,
+}
+
+/// What a condition or switch field reads, for the fields that need a name.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FieldNames {
+    /// Header name (when field is "header" or "responseHeader"); graphs
+    /// saved before the keys below name every field with it
+    #[serde(rename = "headerName")]
+    pub header_name: Option<String>,
+    /// Query parameter, cookie or form field name
+    #[serde(rename = "paramName", skip_serializing_if = "Option::is_none")]
+    pub param_name: Option<String>,
+    /// JSON pointer (when field is "json")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+    /// Time zone of the "hour", "weekday" and "today" fields
+    #[serde(rename = "timeZone", skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+}
+
+/// Node data for ruleGroup nodes (inline conditions with match/noMatch outputs).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RuleGroupNodeData {
+    pub name: Option<String>,
+    pub logic: String,
+    pub conditions: Vec<RuleGroupCondition>,
+    /// Nested groups, combined with the conditions under `logic`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<RuleSubGroup>,
+}
+
+/// A group of conditions nested in a rule group, with its own logic.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RuleSubGroup {
+    /// "AND", "OR" or "NOT"
+    pub logic: String,
+    #[serde(default)]
+    pub conditions: Vec<RuleGroupCondition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<RuleSubGroup>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RuleGroupCondition {
+    pub id: String,
+    pub field: String,
+    pub operator: String,
+    pub value: String,
+    #[serde(flatten)]
+    pub names: FieldNames,
+    /// Match when the condition does not hold
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub negate: bool,
+}
+
+/// Node data for ruleList nodes (ordered rules, first match wins).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RuleListNodeData {
+    pub name: Option<String>,
+    pub rules: Vec<RuleListEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RuleListEntry {
+    /// Rule id: the output handle, and what is logged when the rule matches
+    pub id: String,
+    /// "AND" (default), "OR" or "NOT"
+    pub logic: Option<String>,
+    pub conditions: Vec<RuleGroupCondition>,
+    /// Nested groups, combined with the conditions under `logic`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<RuleSubGroup>,
+    /// Inline action taken when the rule matches (instead of its output)
+    pub action: Option<ActionNodeData>,
+}
+
+/// Node data for switch nodes (one output handle per case, plus "default").
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SwitchNodeData {
+    pub field: String,
+    #[serde(flatten)]
+    pub names: FieldNames,
+    pub cases: Vec<SwitchCaseData>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SwitchCaseData {
+    /// Output handle id
+    pub id: String,
+    /// "exact" (default), "prefix" or "cidr"
+    #[serde(rename = "match")]
+    pub match_type: Option<String>,
+    /// Comma-separated values
+    pub value: String,
+}
+
+/// Node data for action nodes.
+/// Supports: block, allow, challenge, log, redirect, tarpit
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ActionNodeData {
+    pub action: String,
+    #[serde(rename = "statusCode")]
+    pub status_code: Option<u16>,
+    /// Block or log message (a template, e.g. "Blocked (request ${requestId})")
+    pub message: Option<String>,
+    /// Redirect URL (when action is "redirect"; a template)
+    pub url: Option<String>,
+    /// Preserve query string on redirect (when action is "redirect")
+    #[serde(rename = "preserveQuery")]
+    pub preserve_query: Option<bool>,
+    /// "javascript" (default) or "proofOfWork" (when action is "challenge")
+    #[serde(rename = "challengeType")]
+    pub challenge_type: Option<String>,
+    /// debug, info (default), warning or error (when action is "log")
+    pub severity: Option<String>,
+    /// Rule name recorded with the event (when action is "log")
+    #[serde(rename = "ruleName")]
+    pub rule_name: Option<String>,
+    /// Fields whose values are recorded with the event (when action is "log")
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// How long to hold the request (when action is "tarpit")
+    #[serde(rename = "delayMs")]
+    pub delay_ms: Option<u32>,
+    /// "block" (default) or "forward" after the delay (when action is "tarpit")
+    pub then: Option<String>,
+}
+
+/// Node data for backend nodes - full parity with Fastly BackendBuilder.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BackendNodeData {
+    // Basic
+    pub name: String,
+    pub host: String,
+    pub port: Option<u16>,
+
+    // Timeouts (ms)
+    #[serde(rename = "connectTimeout")]
+    pub connect_timeout: Option<u64>,
+    #[serde(rename = "firstByteTimeout")]
+    pub first_byte_timeout: Option<u64>,
+    #[serde(rename = "betweenBytesTimeout")]
+    pub between_bytes_timeout: Option<u64>,
+
+    // SSL/TLS
+    #[serde(rename = "useTLS")]
+    pub use_tls: Option<bool>,
+    #[serde(rename = "verifyCertificate")]
+    pub verify_certificate: Option<bool>,
+    #[serde(rename = "sniHostname")]
+    pub sni_hostname: Option<String>,
+    #[serde(rename = "caCertificate")]
+    pub ca_certificate: Option<String>,
+    #[serde(rename = "clientCertificate")]
+    pub client_certificate: Option<String>,
+    #[serde(rename = "clientKey")]
+    pub client_key: Option<String>,
+    #[serde(rename = "minTLSVersion")]
+    pub min_tls_version: Option<String>,
+    #[serde(rename = "maxTLSVersion")]
+    pub max_tls_version: Option<String>,
+
+    // Host
+    #[serde(rename = "overrideHost")]
+    pub override_host: Option<String>,
+    #[serde(rename = "preferIPv6")]
+    pub prefer_ipv6: Option<bool>,
+
+    // Connection Pooling
+    #[serde(rename = "enablePooling")]
+    pub enable_pooling: Option<bool>,
+    #[serde(rename = "keepaliveTime")]
+    pub keepalive_time: Option<u64>,
+    #[serde(rename = "maxConnections")]
+    pub max_connections: Option<u32>,
+    #[serde(rename = "maxConnectionUses")]
+    pub max_connection_uses: Option<u32>,
+    #[serde(rename = "maxConnectionLifetime")]
+    pub max_connection_lifetime: Option<u64>,
+
+    // TCP Keepalive
+    #[serde(rename = "tcpKeepalive")]
+    pub tcp_keepalive: Option<bool>,
+    #[serde(rename = "tcpKeepaliveTime")]
+    pub tcp_keepalive_time: Option<u64>,
+    #[serde(rename = "tcpKeepaliveInterval")]
+    pub tcp_keepalive_interval: Option<u64>,
+    #[serde(rename = "tcpKeepaliveProbes")]
+    pub tcp_keepalive_probes: Option<u32>,
+
+    // Edge Auth - HMAC signature for origin verification
+    #[serde(rename = "edgeAuthSecret")]
+    pub edge_auth_secret: Option<String>,
+
+    // Failover
+    /// Backend tried once after the attempts on this one have failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Box<BackendNodeData>>,
+    /// When and how often to try again (default: a single attempt)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+}
+
+/// Retry policy of a backend node.
+///
+/// Requests with a body are never retried (the body is streamed to the
+/// first attempt and can't be sent again), and by default neither are
+/// non-idempotent methods.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    /// Attempts on this backend, including the first
+    pub max_attempts: u32,
+    /// Whether a connection error or timeout counts as a failure
+    pub retry_on_error: bool,
+    /// Response status codes that count as failures
+    pub retry_on_status: Vec<u16>,
+    /// Only retry GET, HEAD, OPTIONS, PUT and DELETE requests
+    pub idempotent_only: bool,
+}
+
+/// Most attempts a retry policy makes on one backend, whatever it says.
+pub const MAX_BACKEND_ATTEMPTS: u32 = 5;
+
+impl RetryPolicy {
+    /// Attempts on the backend: `max_attempts` kept to 1..=[`MAX_BACKEND_ATTEMPTS`].
+    pub fn attempts(&self) -> u32 {
+        self.max_attempts.clamp(1, MAX_BACKEND_ATTEMPTS)
+    }
+
+    /// Whether an attempt counts as a failure: its response status (`None`
+    /// for a connection error or timeout) is one the policy retries on.
+    pub fn is_failure(&self, status: Option<u16>) -> bool {
+        match status {
+            Some(status) => self.retry_on_status.contains(&status),
+            None => self.retry_on_error,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            retry_on_error: true,
+            retry_on_status: vec![502, 503, 504],
+            idempotent_only: true,
+        }
+    }
+}
+
+/// Node data for backendPool nodes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BackendPoolNodeData {
+    /// Pool name (for logs)
+    pub name: String,
+    pub members: Vec<PoolMember>,
+    /// How a member is picked: "weightedRandom" (default), "hash" or "cookie"
+    pub strategy: Option<String>,
+    /// What "hash" hashes: "clientIp" (default) or "header"
+    #[serde(rename = "hashKey")]
+    pub hash_key: Option<String>,
+    /// Header name when hashKey is "header"
+    #[serde(rename = "headerName")]
+    pub header_name: Option<String>,
+    /// Cookie that pins a client to a member when strategy is "cookie"
+    #[serde(rename = "cookieName")]
+    pub cookie_name: Option<String>,
+}
+
+/// A backend in a pool, with its share of the traffic.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PoolMember {
+    #[serde(flatten)]
+    pub backend: BackendNodeData,
+    /// Relative weight (default 1); 0 takes no traffic
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Skipped when picking a member (e.g. while it's drained)
+    #[serde(default)]
+    pub down: bool,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Node data for split nodes (one output handle per bucket).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SplitNodeData {
+    /// Split name, logged with the bucket; also salts the hash so
+    /// different splits bucket users independently
+    pub name: Option<String>,
+    pub buckets: Vec<SplitBucketData>,
+    /// What a user is bucketed by: "clientIp" (default), "header", "cookie",
+    /// or "var:<name>" for a transform output variable
+    #[serde(rename = "keyBy")]
+    pub key_by: Option<String>,
+    /// Header name when keyBy is "header"
+    #[serde(rename = "headerName")]
+    pub header_name: Option<String>,
+    /// Cookie name when keyBy is "cookie"
+    #[serde(rename = "cookieName")]
+    pub cookie_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SplitBucketData {
+    /// Output handle id
+    pub id: String,
+    pub label: Option<String>,
+    /// Relative share of the traffic (e.g. 95 and 5)
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+/// Node data for rateLimit nodes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RateLimitNodeData {
+    /// Maximum requests allowed in the window
+    pub limit: u32,
+    /// Time window: "second", "minute", or "hour"
+    #[serde(rename = "windowUnit")]
+    pub window_unit: String,
+    /// Key to identify clients: "ip", "fingerprint", "header", "path",
+    /// or "var:<name>" for a transform output variable
+    #[serde(rename = "keyBy")]
+    pub key_by: String,
+    /// Header name when keyBy is "header"
+    #[serde(rename = "headerName")]
+    pub header_name: Option<String>,
+}
+
+/// Node data for header nodes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HeaderNodeData {
+    /// Operation: "set", "append", or "remove"
+    pub operation: String,
+    /// Header name
+    pub name: String,
+    /// Header value (for set/append operations), a template such as
+    /// "${country}"; "var:<name>" is the same as "${var.name}"
+    pub value: Option<String>,
+}
+
+/// Node data for cache control nodes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CacheNodeData {
+    /// Mode: "configure" or "pass"
+    pub mode: String,
+    /// Time-to-live value
+    pub ttl: Option<u64>,
+    /// TTL unit: "seconds", "minutes", "hours", "days"
+    #[serde(rename = "ttlUnit")]
+    pub ttl_unit: Option<String>,
+    /// Stale-while-revalidate duration
+    #[serde(rename = "staleWhileRevalidate")]
+    pub stale_while_revalidate: Option<u64>,
+    /// SWR unit: "seconds", "minutes", "hours"
+    #[serde(rename = "swrUnit")]
+    pub swr_unit: Option<String>,
+    /// Space-separated surrogate keys for cache purging
+    #[serde(rename = "surrogateKeys")]
+    pub surrogate_keys: Option<String>,
+}
+
+/// Node data for redirect nodes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RedirectNodeData {
+    /// Target URL, a template such as "https://${host}/eu${path}"
+    pub url: String,
+    /// HTTP status code (301, 302, 307, 308)
+    #[serde(rename = "statusCode")]
+    pub status_code: Option<u16>,
+    /// Whether to preserve the query string
+    #[serde(rename = "preserveQuery")]
+    pub preserve_query: Option<bool>,
+}
+
+/// Node data for respond nodes: a synthetic response served from the graph.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RespondNodeData {
+    /// HTTP status code (default 200)
+    #[serde(rename = "statusCode")]
+    pub status_code: Option<u16>,
+    /// Content-Type of the response (default "text/plain; charset=utf-8")
+    #[serde(rename = "contentType")]
+    pub content_type: Option<String>,
+    /// Extra response headers; values are templates
+    #[serde(default)]
+    pub headers: Vec<ResponseHeaderData>,
+    /// Response body, a template such as "Blocked (request ${requestId})"
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Config Store key holding the body, filled into `body` by the edge
+    /// when the graph is loaded
+    #[serde(rename = "bodyKey")]
+    pub body_key: Option<String>,
+}
+
+/// A header of a respond node's response.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ResponseHeaderData {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+/// Node data for transform nodes.
+/// Transforms field values using various operations.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TransformNodeData {
+    /// Transform operation: lowercase, uppercase, urlDecode, base64Decode, htmlDecode, removeWhitespace, extract
+    pub operation: String,
+    /// Source field to transform: path, query, body, userAgent, header, cookie,
+    /// or var:<name> to chain off another transform
+    pub field: String,
+    /// Regex pattern for extract operation (capture group 1 is used)
+    pub pattern: Option<String>,
+    /// Output variable name to store the result
+    #[serde(rename = "outputVar")]
+    pub output_var: Option<String>,
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_node_retry_and_fallback() {
+        let json = r#"{
+            "name": "primary",
+            "host": "a.example.com",
+            "retry": {"maxAttempts": 3, "retryOnStatus": [500, 503]},
+            "fallback": {"name": "standby", "host": "b.example.com"}
+        }"#;
+
+        let data: BackendNodeData = serde_json::from_str(json).unwrap();
+        let retry = data.retry.unwrap();
+        assert_eq!(retry.max_attempts, 3);
+        assert_eq!(retry.retry_on_status, vec![500, 503]);
+        assert!(retry.retry_on_error);
+        assert!(retry.idempotent_only);
+        assert_eq!(data.fallback.unwrap().host, "b.example.com");
+
+        let data: BackendNodeData = serde_json::from_str(r#"{"name": "o", "host": "example.com"}"#).unwrap();
+        assert!(data.retry.is_none());
+        assert!(data.fallback.is_none());
+        assert_eq!(RetryPolicy::default().max_attempts, 1);
+    }
+}
@@ -0,0 +1,827 @@
+//! Execution plan - a graph compiled once for repeated execution.
+//!
+//! Compiling resolves everything that doesn't depend on the request:
+//! regexes are built, CIDR lists and numbers parsed, templates parsed, switch
+//! cases indexed, and each output port
+//! resolved to the node it leads to. Configuration errors (a bad regex, a
+//! malformed CIDR) are reported here instead of silently failing a match
+//! on every request.
+
+use crate::{
+    ActionType, ActiveWindow, ConditionGroup, ConditionValue, Graph, ListRule, Logic, Node, NodeId, NodeKind,
+    Operator, PoolStrategy, RequestField, RuleCondition, SwitchCase, SwitchMatch, TarpitThen, Template,
+    TransformOp, Value, range_point,
+};
+use ipnet::IpNet;
+use regex::Regex;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// A node that failed to compile.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("Node {node}: {message}")]
+pub struct CompileError {
+    /// Node label (editor id when known)
+    pub node: String,
+    pub message: String,
+}
+
+/// A compiled graph, ready to execute.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    nodes: Vec<PlanNode>,
+    entry: Option<usize>,
+    response_entry: Option<usize>,
+}
+
+/// A node with its request-independent parts resolved.
+#[derive(Debug, Clone)]
+pub struct PlanNode {
+    pub id: NodeId,
+    /// Identifier used in logs
+    pub label: String,
+    pub kind: NodeKind,
+    /// When the node takes part in execution; always when `None`
+    pub active: Option<ActiveWindow>,
+    /// Compiled condition of a condition node
+    pub conditions: Vec<CompiledCondition>,
+    /// Compiled conditions and nested groups of a rule group
+    pub group: Option<CompiledGroup>,
+    /// Compiled pattern of an extract transform
+    pub pattern: Option<Regex>,
+    /// Parsed header value, redirect URL, response body, or block or log message
+    pub template: Option<Template>,
+    /// Parsed header values of a respond node, in header order
+    pub header_templates: Vec<Template>,
+    /// Case lookup of a switch node
+    pub switch: Option<SwitchTable>,
+    /// Compiled rules of a rule list, in order
+    pub rules: Vec<CompiledRule>,
+    /// Targets of the edges on each output port, in execution order
+    next: Vec<Vec<usize>>,
+    /// Targets of the edges on any port, in execution order
+    any_next: Vec<usize>,
+}
+
+impl PlanNode {
+    /// Indices of the nodes the edges on `port` lead to (`None` = any port),
+    /// in the order they are executed.
+    pub fn next(&self, port: Option<u8>) -> &[usize] {
+        match port {
+            Some(p) => self.next.get(p as usize).map(Vec::as_slice).unwrap_or(&[]),
+            None => &self.any_next,
+        }
+    }
+
+    /// Whether the node reads a request field `pred` holds for: in a
+    /// condition, template, switch, transform, pool hash or logged field.
+    pub fn reads_field(&self, pred: &dyn Fn(&RequestField) -> bool) -> bool {
+        let in_kind = match &self.kind {
+            NodeKind::Switch { field, .. }
+            | NodeKind::Transform { field, .. }
+            | NodeKind::BackendPool { strategy: PoolStrategy::Hash { field }, .. } => pred(field),
+            NodeKind::Action { action } => logs_field(action, pred),
+            NodeKind::RuleList { rules, .. } => {
+                rules.iter().filter_map(|r| r.action.as_ref()).any(|a| logs_field(a, pred))
+            }
+            _ => false,
+        };
+        let templates = self
+            .template
+            .iter()
+            .chain(&self.header_templates)
+            .chain(self.rules.iter().flat_map(|r| r.template.iter().chain(&r.header_templates)));
+        in_kind
+            || self.conditions.iter().any(|c| pred(&c.field))
+            || self.group.as_ref().is_some_and(|g| g.reads_field(pred))
+            || self.rules.iter().any(|r| r.group.reads_field(pred))
+            || templates.flat_map(Template::fields).any(pred)
+    }
+}
+
+fn logs_field(action: &ActionType, pred: &dyn Fn(&RequestField) -> bool) -> bool {
+    matches!(action, ActionType::Log { fields, .. } if fields.iter().any(pred))
+}
+
+impl Plan {
+    /// Compile a graph. All node errors are collected and returned together.
+    pub fn compile(graph: &Graph) -> Result<Plan, Vec<CompileError>> {
+        let index: HashMap<NodeId, usize> = graph
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.id, i))
+            .collect();
+
+        let mut errors = Vec::new();
+        let mut nodes = Vec::with_capacity(graph.nodes.len());
+        for node in &graph.nodes {
+            match compile_node(node) {
+                Ok(plan_node) => nodes.push(plan_node),
+                Err(message) => errors.push(CompileError { node: node.label(), message }),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // Edges keep their order, which is the order they are executed in
+        for edge in &graph.edges {
+            let (Some(&from), Some(&to)) = (index.get(&edge.from_node), index.get(&edge.to_node)) else {
+                continue;
+            };
+            let node = &mut nodes[from];
+            let port = edge.from_port as usize;
+            if node.next.len() <= port {
+                node.next.resize(port + 1, Vec::new());
+            }
+            node.next[port].push(to);
+            node.any_next.push(to);
+        }
+
+        let entry = nodes.iter().position(|n| matches!(n.kind, NodeKind::Request));
+        let response_entry = nodes.iter().position(|n| matches!(n.kind, NodeKind::Response));
+        Ok(Plan { nodes, entry, response_entry })
+    }
+
+    /// Index of the Request node execution starts from.
+    pub fn entry(&self) -> Option<usize> {
+        self.entry
+    }
+
+    /// Index of the Response node the response phase starts from, if the
+    /// graph has one.
+    pub fn response_entry(&self) -> Option<usize> {
+        self.response_entry
+    }
+
+    pub fn node(&self, index: usize) -> &PlanNode {
+        &self.nodes[index]
+    }
+
+    pub fn nodes(&self) -> &[PlanNode] {
+        &self.nodes
+    }
+
+    /// Whether any node reads the request body, so the edge has to buffer it.
+    pub fn reads_body(&self) -> bool {
+        self.nodes.iter().any(|n| n.reads_field(&RequestField::is_body))
+    }
+
+    /// Find a cycle among the edges execution can follow, as the node
+    /// indices along it (the first node is repeated at the end).
+    pub fn find_cycle(&self) -> Option<Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            New,
+            OnPath,
+            Done,
+        }
+
+        let mut marks = vec![Mark::New; self.nodes.len()];
+        for start in 0..self.nodes.len() {
+            if marks[start] != Mark::New {
+                continue;
+            }
+            // Iterative DFS: (node, index of the next edge to look at)
+            let mut path: Vec<(usize, usize)> = vec![(start, 0)];
+            marks[start] = Mark::OnPath;
+            while let Some(&mut (node, ref mut edge)) = path.last_mut() {
+                let Some(&target) = self.nodes[node].any_next.get(*edge) else {
+                    marks[node] = Mark::Done;
+                    path.pop();
+                    continue;
+                };
+                *edge += 1;
+                match marks[target] {
+                    Mark::OnPath => {
+                        let from = path.iter().position(|&(n, _)| n == target).unwrap_or(0);
+                        let mut cycle: Vec<usize> = path[from..].iter().map(|&(n, _)| n).collect();
+                        cycle.push(target);
+                        return Some(cycle);
+                    }
+                    Mark::New => {
+                        marks[target] = Mark::OnPath;
+                        path.push((target, 0));
+                    }
+                    Mark::Done => {}
+                }
+            }
+        }
+        None
+    }
+}
+
+pub(crate) fn compile_node(node: &Node) -> Result<PlanNode, String> {
+    let mut conditions = Vec::new();
+    let mut group = None;
+    let mut pattern = None;
+    let mut template = None;
+    let mut header_templates = Vec::new();
+    let mut switch = None;
+    let mut rules = Vec::new();
+
+    match &node.kind {
+        NodeKind::Condition { field, operator, value } => {
+            conditions.push(CompiledCondition::compile(field, operator, value)?);
+        }
+        NodeKind::RuleGroup { logic, conditions: list, groups, .. } => {
+            group = Some(CompiledGroup::compile(*logic, list, groups)?);
+        }
+        NodeKind::RuleList { rules: list, .. } => {
+            // One output per rule plus noMatch, each a u8 port
+            if list.len() >= u8::MAX as usize {
+                return Err(format!("Too many rules: {}", list.len()));
+            }
+            for rule in list {
+                let compiled = CompiledRule::compile(rule).map_err(|e| format!("rule {}: {}", rule.id, e))?;
+                rules.push(compiled);
+            }
+        }
+        NodeKind::Switch { cases, .. } => {
+            switch = Some(SwitchTable::compile(cases)?);
+        }
+        NodeKind::Transform { operation: TransformOp::Extract, pattern: p, .. } => {
+            let p = p.as_deref().ok_or("Extract operation requires a pattern")?;
+            pattern = Some(compile_regex(p)?);
+        }
+        NodeKind::Header { value: Some(value), .. } => {
+            template = Some(match value.strip_prefix("var:") {
+                Some(name) => Template::variable(name),
+                None => Template::parse(value)?,
+            });
+        }
+        NodeKind::Action { action } => {
+            (template, header_templates) = action_templates(action)?;
+        }
+        _ => {}
+    }
+
+    Ok(PlanNode {
+        id: node.id,
+        label: node.label(),
+        kind: node.kind.clone(),
+        active: node.active,
+        conditions,
+        group,
+        pattern,
+        template,
+        header_templates,
+        switch,
+        rules,
+        next: Vec::new(),
+        any_next: Vec::new(),
+    })
+}
+
+/// Parsed templates of an action: its redirect URL, response body, or block
+/// or log message, and the header values of a respond action.
+fn action_templates(action: &ActionType) -> Result<(Option<Template>, Vec<Template>), String> {
+    match action {
+        ActionType::Redirect { url, .. } => Ok((Some(Template::parse(url)?), Vec::new())),
+        ActionType::Respond { headers, body, .. } => {
+            let header_templates = headers
+                .iter()
+                .map(|(name, value)| Template::parse(value).map_err(|e| format!("header {}: {}", name, e)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((Some(Template::parse(body)?), header_templates))
+        }
+        ActionType::Block { message, .. }
+        | ActionType::Log { message, .. }
+        | ActionType::Tarpit { then: TarpitThen::Block { message, .. }, .. } => {
+            Ok((Some(Template::parse(message)?), Vec::new()))
+        }
+        _ => Ok((None, Vec::new())),
+    }
+}
+
+/// A rule list rule with its conditions and inline action compiled.
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    pub group: CompiledGroup,
+    /// Parsed templates of the inline action
+    pub template: Option<Template>,
+    pub header_templates: Vec<Template>,
+}
+
+impl CompiledRule {
+    pub fn compile(rule: &ListRule) -> Result<Self, String> {
+        let group = CompiledGroup::compile(rule.logic, &rule.conditions, &rule.groups)?;
+        let (template, header_templates) = match &rule.action {
+            Some(action) => action_templates(action)?,
+            None => (None, Vec::new()),
+        };
+        Ok(Self { group, template, header_templates })
+    }
+}
+
+/// Conditions and nested groups combined with one logic operator.
+#[derive(Debug, Clone)]
+pub struct CompiledGroup {
+    pub logic: Logic,
+    pub conditions: Vec<CompiledCondition>,
+    pub groups: Vec<CompiledGroup>,
+}
+
+impl CompiledGroup {
+    pub fn compile(logic: Logic, conditions: &[RuleCondition], groups: &[ConditionGroup]) -> Result<Self, String> {
+        let conditions = conditions
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                CompiledCondition::compile(&c.field, &c.operator, &c.value)
+                    .map(|compiled| compiled.negated(c.negate))
+                    .map_err(|e| format!("condition {}: {}", i + 1, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let groups = groups
+            .iter()
+            .enumerate()
+            .map(|(i, g)| {
+                Self::compile(g.logic, &g.conditions, &g.groups).map_err(|e| format!("group {}: {}", i + 1, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { logic, conditions, groups })
+    }
+
+    fn reads_field(&self, pred: &dyn Fn(&RequestField) -> bool) -> bool {
+        self.conditions.iter().any(|c| pred(&c.field)) || self.groups.iter().any(|g| g.reads_field(pred))
+    }
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid regex pattern '{}': {}", pattern, e))
+}
+
+/// A condition with its expected value parsed for the operator.
+#[derive(Debug, Clone)]
+pub struct CompiledCondition {
+    pub field: RequestField,
+    pub operator: Operator,
+    pub value: ConditionValue,
+    /// Match when the condition does not hold (rule group conditions)
+    pub negate: bool,
+    matcher: Matcher,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Text(String),
+    Number(f64),
+    List(Vec<String>),
+    Regex(Regex),
+    Cidrs(Vec<IpNet>),
+    /// Inclusive bounds; wraps around when `from` is after `to`
+    Range(f64, f64),
+    None,
+}
+
+impl CompiledCondition {
+    pub fn compile(field: &RequestField, operator: &Operator, value: &ConditionValue) -> Result<Self, String> {
+        if let Some(zone) = field.time_zone() {
+            zone.zone()?;
+        }
+        let matcher = match operator {
+            Operator::Equals | Operator::NotEquals | Operator::Contains | Operator::NotContains |
+            Operator::StartsWith | Operator::EndsWith => Matcher::Text(value.as_text()),
+
+            Operator::Matches => Matcher::Regex(compile_regex(&value.as_text())?),
+
+            Operator::GreaterThan | Operator::LessThan | Operator::GreaterOrEqual |
+            Operator::LessOrEqual => {
+                let n = match value {
+                    ConditionValue::Number(n) => *n,
+                    other => other
+                        .as_text()
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|_| format!("'{}' is not a number", other.as_text()))?,
+                };
+                Matcher::Number(n)
+            }
+
+            Operator::In | Operator::NotIn => Matcher::List(value.items()),
+
+            Operator::InCidr | Operator::NotInCidr => {
+                let nets = value
+                    .items()
+                    .iter()
+                    .map(|cidr| cidr.parse::<IpNet>().map_err(|_| format!("Invalid CIDR: {}", cidr)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Matcher::Cidrs(nets)
+            }
+
+            Operator::Between => {
+                let text = value.as_text();
+                let bounds = text
+                    .split_once("..")
+                    .and_then(|(from, to)| Some((range_point(from, false)?, range_point(to, true)?)));
+                let (from, to) = bounds.ok_or_else(|| {
+                    format!("'{}' is not a range like 9..17, Mon..Fri or 2026-12-24..2026-12-26", text)
+                })?;
+                // Hours and weekdays go round, so 22..6 and Fri..Mon wrap
+                let wraps = matches!(field, RequestField::Hour { .. } | RequestField::Weekday { .. });
+                if from > to && !wraps {
+                    return Err(format!("'{}' ends before it starts", text));
+                }
+                Matcher::Range(from, to)
+            }
+
+            Operator::Exists | Operator::NotExists => Matcher::None,
+        };
+
+        Ok(Self {
+            field: field.clone(),
+            operator: operator.clone(),
+            value: value.clone(),
+            negate: false,
+            matcher,
+        })
+    }
+
+    /// The condition with its result inverted when `negate` is set.
+    pub fn negated(self, negate: bool) -> Self {
+        Self { negate, ..self }
+    }
+
+    /// Whether the condition holds for a field value, `negate` applied.
+    pub fn holds(&self, field_value: &Value) -> bool {
+        self.matches(field_value) != self.negate
+    }
+
+    /// Evaluate the condition against a field value.
+    ///
+    /// Comparisons work on the text form of both sides, the way the editor
+    /// presents them; numeric operators parse the field as a number, and
+    /// `between` as a number, weekday or time like its bounds.
+    ///
+    /// A repeated query parameter or cookie is checked value by value: it
+    /// matches a positive operator when any value does, and a negative one
+    /// ("not equals", "not in", ...) only when every value does.
+    pub fn matches(&self, field_value: &Value) -> bool {
+        match (&self.field, field_value) {
+            (RequestField::QueryParam { .. } | RequestField::Cookie { .. }, Value::List(values)) => {
+                if self.operator.is_negative() {
+                    values.iter().all(|value| self.matches_one(value))
+                } else {
+                    values.iter().any(|value| self.matches_one(value))
+                }
+            }
+            _ => self.matches_one(field_value),
+        }
+    }
+
+    fn matches_one(&self, field_value: &Value) -> bool {
+        let text = field_value.as_text();
+        match (&self.operator, &self.matcher) {
+            (Operator::Equals, Matcher::Text(v)) => text == *v,
+            (Operator::NotEquals, Matcher::Text(v)) => text != *v,
+            (Operator::Contains, Matcher::Text(v)) => text.contains(v.as_str()),
+            (Operator::NotContains, Matcher::Text(v)) => !text.contains(v.as_str()),
+            (Operator::StartsWith, Matcher::Text(v)) => text.starts_with(v.as_str()),
+            (Operator::EndsWith, Matcher::Text(v)) => text.ends_with(v.as_str()),
+
+            (Operator::Matches, Matcher::Regex(re)) => re.is_match(&text),
+
+            (op, Matcher::Number(expected)) => match text.trim().parse::<f64>() {
+                Ok(actual) => match op {
+                    Operator::GreaterThan => actual > *expected,
+                    Operator::LessThan => actual < *expected,
+                    Operator::GreaterOrEqual => actual >= *expected,
+                    _ => actual <= *expected,
+                },
+                Err(_) => false,
+            },
+
+            (Operator::In, Matcher::List(items)) => items.contains(&text),
+            (Operator::NotIn, Matcher::List(items)) => !items.contains(&text),
+
+            (Operator::InCidr, Matcher::Cidrs(nets)) => client_ip(field_value)
+                .map(|ip| nets.iter().any(|net| net.contains(&ip)))
+                .unwrap_or(false),
+            // Requires a parsable IP, so a missing IP doesn't count as "outside"
+            (Operator::NotInCidr, Matcher::Cidrs(nets)) => client_ip(field_value)
+                .map(|ip| !nets.iter().any(|net| net.contains(&ip)))
+                .unwrap_or(false),
+
+            (Operator::Between, Matcher::Range(from, to)) => match range_point(&text, false) {
+                Some(actual) if from <= to => actual >= *from && actual <= *to,
+                Some(actual) => actual >= *from || actual <= *to,
+                None => false,
+            },
+
+            (Operator::Exists, _) => self.is_present(field_value),
+            (Operator::NotExists, _) => !self.is_present(field_value),
+
+            // compile() pairs every operator with its matcher
+            _ => false,
+        }
+    }
+
+    fn is_present(&self, field_value: &Value) -> bool {
+        match (&self.field, field_value) {
+            (_, Value::None) => false,
+            // A parameter or cookie without a value (`?debug`) is still sent
+            (RequestField::QueryParam { .. } | RequestField::Cookie { .. }, _) => true,
+            (_, value) => !value.as_text().is_empty(),
+        }
+    }
+}
+
+/// The cases of a switch node indexed by value, so picking an output takes
+/// a few hash lookups however many cases there are.
+///
+/// An exact value wins over a prefix, the longest prefix over shorter ones,
+/// and the narrowest CIDR range over wider ones. A value listed in more
+/// than one case goes to the first.
+#[derive(Debug, Clone)]
+pub struct SwitchTable {
+    exact: HashMap<String, u8>,
+    prefixes: HashMap<String, u8>,
+    /// Distinct prefix lengths (bytes), longest first
+    prefix_lens: Vec<usize>,
+    /// Network address and prefix length of each range
+    cidrs: HashMap<(IpAddr, u8), u8>,
+    /// Distinct (IPv4?, prefix length) pairs, longest first
+    cidr_lens: Vec<(bool, u8)>,
+    /// Port of the "default" output
+    default: u8,
+}
+
+impl SwitchTable {
+    pub fn compile(cases: &[SwitchCase]) -> Result<Self, String> {
+        if cases.len() >= u8::MAX as usize {
+            return Err(format!("Too many cases: {}", cases.len()));
+        }
+        let mut table = SwitchTable {
+            exact: HashMap::new(),
+            prefixes: HashMap::new(),
+            prefix_lens: Vec::new(),
+            cidrs: HashMap::new(),
+            cidr_lens: Vec::new(),
+            default: cases.len() as u8,
+        };
+        for (port, case) in cases.iter().enumerate() {
+            let port = port as u8;
+            for value in &case.values {
+                match case.match_type {
+                    SwitchMatch::Exact => {
+                        table.exact.entry(value.clone()).or_insert(port);
+                    }
+                    SwitchMatch::Prefix => {
+                        table.prefixes.entry(value.clone()).or_insert(port);
+                        table.prefix_lens.push(value.len());
+                    }
+                    SwitchMatch::Cidr => {
+                        let net = value.parse::<IpNet>().map_err(|_| format!("Invalid CIDR: {}", value))?;
+                        table.cidrs.entry((net.network(), net.prefix_len())).or_insert(port);
+                        table.cidr_lens.push((net.network().is_ipv4(), net.prefix_len()));
+                    }
+                }
+            }
+        }
+        table.prefix_lens.sort_unstable_by(|a, b| b.cmp(a));
+        table.prefix_lens.dedup();
+        table.cidr_lens.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        table.cidr_lens.dedup();
+        Ok(table)
+    }
+
+    /// Output port for a field value.
+    pub fn port(&self, field_value: &Value) -> u8 {
+        let text = field_value.as_text();
+        if let Some(&port) = self.exact.get(&text) {
+            return port;
+        }
+        let prefix = self.prefix_lens.iter().find_map(|&len| {
+            text.get(..len).and_then(|p| self.prefixes.get(p)).copied()
+        });
+        if let Some(port) = prefix {
+            return port;
+        }
+        let cidr = client_ip(field_value).and_then(|ip| {
+            self.cidr_lens
+                .iter()
+                .filter(|(v4, _)| *v4 == ip.is_ipv4())
+                .find_map(|&(_, len)| {
+                    let network = IpNet::new(ip, len).ok()?.network();
+                    self.cidrs.get(&(network, len)).copied()
+                })
+        });
+        cidr.unwrap_or(self.default)
+    }
+}
+
+fn client_ip(value: &Value) -> Option<IpAddr> {
+    match value {
+        Value::Ip(ip) => Some(*ip),
+        other => other.as_text().trim().parse().ok(),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::{cookie, query_param};
+    use crate::ClockZone;
+    use crate::Edge;
+
+    fn node(kind: NodeKind) -> Node {
+        Node { id: 0, kind, position: (0.0, 0.0), editor_id: None, active: None }
+    }
+
+    fn condition(field: RequestField, operator: Operator, value: &str) -> CompiledCondition {
+        let value = ConditionValue::from_editor(&operator, value);
+        CompiledCondition::compile(&field, &operator, &value).unwrap()
+    }
+
+    #[test]
+    fn test_cidr_condition() {
+        let c = condition(RequestField::ClientIp, Operator::InCidr, "10.0.0.0/8, 2001:db8::/32");
+        assert!(c.matches(&Value::String("10.1.2.3".to_string())));
+        assert!(c.matches(&Value::Ip("2001:db8::1".parse().unwrap())));
+        assert!(!c.matches(&Value::String("192.168.1.1".to_string())));
+        assert!(!c.matches(&Value::None));
+
+        let c = condition(RequestField::ClientIp, Operator::NotInCidr, "10.0.0.0/8");
+        assert!(c.matches(&Value::String("192.168.1.1".to_string())));
+        assert!(!c.matches(&Value::None));
+    }
+
+    #[test]
+    fn test_switch_table_picks_most_specific_case() {
+        let case = |handle: &str, match_type, values: &[&str]| SwitchCase {
+            handle: handle.to_string(),
+            match_type,
+            values: values.iter().map(|v| v.to_string()).collect(),
+        };
+        let text = |s: &str| Value::String(s.to_string());
+
+        let paths = SwitchTable::compile(&[
+            case("api", SwitchMatch::Prefix, &["/api"]),
+            case("v2", SwitchMatch::Prefix, &["/api/v2"]),
+            case("health", SwitchMatch::Exact, &["/api/health", "/health"]),
+        ])
+        .unwrap();
+        assert_eq!(paths.port(&text("/api/users")), 0);
+        assert_eq!(paths.port(&text("/api/v2/users")), 1);
+        assert_eq!(paths.port(&text("/api/health")), 2);
+        assert_eq!(paths.port(&text("/")), 3);
+
+        let ips = SwitchTable::compile(&[
+            case("office", SwitchMatch::Cidr, &["10.0.0.0/8"]),
+            case("lab", SwitchMatch::Cidr, &["10.9.0.0/16", "2001:db8::/32"]),
+        ])
+        .unwrap();
+        assert_eq!(ips.port(&text("10.1.2.3")), 0);
+        assert_eq!(ips.port(&Value::Ip("10.9.2.3".parse().unwrap())), 1);
+        assert_eq!(ips.port(&text("2001:db8::1")), 1);
+        assert_eq!(ips.port(&Value::None), 2);
+
+        assert!(SwitchTable::compile(&[case("bad", SwitchMatch::Cidr, &["10.0.0.0/33"])]).is_err());
+    }
+
+    #[test]
+    fn test_numeric_condition_on_text_fields() {
+        let c = condition(RequestField::Asn, Operator::GreaterOrEqual, "13335");
+        assert!(c.matches(&Value::Number(13335.0)));
+        assert!(c.matches(&Value::String("15169".to_string())));
+        assert!(!c.matches(&Value::String("abc".to_string())));
+    }
+
+    #[test]
+    fn test_repeated_parameter_checked_per_value() {
+        let param = || RequestField::QueryParam { name: "role".to_string() };
+        let roles = query_param("?role=user&role=admin", "role");
+
+        assert!(condition(param(), Operator::Equals, "admin").matches(&roles));
+        assert!(condition(param(), Operator::In, "admin, root").matches(&roles));
+        assert!(condition(param(), Operator::StartsWith, "adm").matches(&roles));
+        assert!(condition(param(), Operator::Matches, "^admin$").matches(&roles));
+        assert!(!condition(param(), Operator::NotEquals, "admin").matches(&roles));
+        assert!(!condition(param(), Operator::NotIn, "admin, root").matches(&roles));
+        assert!(condition(param(), Operator::NotIn, "root").matches(&roles));
+
+        let session = RequestField::Cookie { name: "session".to_string() };
+        let sessions = cookie("session=abc; session=stolen", "session");
+        assert!(condition(session.clone(), Operator::Equals, "stolen").matches(&sessions));
+        assert!(!condition(session, Operator::NotContains, "stolen").matches(&sessions));
+    }
+
+    #[test]
+    fn test_parameter_without_value_exists() {
+        let debug = || RequestField::QueryParam { name: "debug".to_string() };
+        let query = "?debug&page=2";
+
+        assert!(condition(debug(), Operator::Exists, "").matches(&query_param(query, "debug")));
+        assert!(!condition(debug(), Operator::NotExists, "").matches(&query_param(query, "debug")));
+        assert!(!condition(debug(), Operator::Exists, "").matches(&query_param("?page=2", "debug")));
+        assert!(condition(debug(), Operator::NotExists, "").matches(&query_param("?page=2", "debug")));
+        assert!(!condition(RequestField::UserAgent, Operator::Exists, "").matches(&Value::String(String::new())));
+    }
+
+    #[test]
+    fn test_only_clock_ranges_wrap_around() {
+        let hour = RequestField::Hour { zone: ClockZone::UTC };
+        let night = condition(hour.clone(), Operator::Between, "22..6");
+        assert!(night.matches(&Value::Number(23.0)));
+        assert!(night.matches(&Value::Number(2.0)));
+        assert!(!night.matches(&Value::Number(12.0)));
+        assert!(condition(RequestField::Weekday { zone: ClockZone::UTC }, Operator::Between, "Fri..Mon")
+            .matches(&Value::String("Sun".to_string())));
+
+        let between = |field: RequestField, range: &str| {
+            CompiledCondition::compile(&field, &Operator::Between, &ConditionValue::String(range.to_string()))
+        };
+        assert!(between(hour, "6..22").is_ok());
+        assert_eq!(between(RequestField::Asn, "200..100").unwrap_err(), "'200..100' ends before it starts");
+        assert!(between(RequestField::Now, "2026-12-26..2026-12-24").is_err());
+        assert!(between(RequestField::Today { zone: ClockZone::UTC }, "2026-12-26..2026-12-24").is_err());
+    }
+
+    #[test]
+    fn test_compile_errors_collected_per_node() {
+        let mut graph = Graph::new("test");
+        graph.add_node(node(NodeKind::Request));
+        graph.add_node(node(NodeKind::Condition {
+            field: RequestField::Path,
+            operator: Operator::Matches,
+            value: ConditionValue::String("(unclosed".to_string()),
+        }));
+        graph.add_node(node(NodeKind::RuleGroup {
+            name: None,
+            logic: Logic::Or,
+            conditions: vec![RuleCondition {
+                field: RequestField::ClientIp,
+                operator: Operator::InCidr,
+                value: ConditionValue::CidrList(vec!["10.0.0.0/33".to_string()]),
+                negate: false,
+            }],
+            groups: Vec::new(),
+        }));
+
+        let errors = Plan::compile(&graph).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].node, "1");
+        assert!(errors[0].message.contains("Invalid regex"));
+        assert_eq!(errors[1].message, "condition 1: Invalid CIDR: 10.0.0.0/33");
+    }
+
+    #[test]
+    fn test_rule_list_rule_count_capped() {
+        let rule_list = |count: usize| {
+            node(NodeKind::RuleList {
+                name: None,
+                rules: (0..count)
+                    .map(|i| ListRule {
+                        id: format!("r{}", i),
+                        logic: Logic::And,
+                        conditions: Vec::new(),
+                        groups: Vec::new(),
+                        action: None,
+                    })
+                    .collect(),
+            })
+        };
+
+        assert!(compile_node(&rule_list(254)).is_ok());
+        assert_eq!(compile_node(&rule_list(255)).unwrap_err(), "Too many rules: 255");
+    }
+
+    #[test]
+    fn test_edges_kept_in_order_per_port() {
+        let mut graph = Graph::new("test");
+        let req = graph.add_node(node(NodeKind::Request));
+        let a = graph.add_node(node(NodeKind::Comment { text: "a".to_string() }));
+        let b = graph.add_node(node(NodeKind::Comment { text: "b".to_string() }));
+        for (from_port, to_node) in [(1, a), (1, b), (0, b)] {
+            graph.edges.push(Edge { from_node: req, from_port, to_node, to_port: 0 });
+        }
+
+        let plan = Plan::compile(&graph).unwrap();
+        let entry = plan.node(plan.entry().unwrap());
+        assert_eq!(entry.next(Some(0)), &[2]);
+        assert_eq!(entry.next(Some(1)), &[1, 2]);
+        assert_eq!(entry.next(None), &[1, 2, 2]);
+        assert_eq!(entry.next(Some(2)), &[] as &[usize]);
+        assert_eq!(plan.find_cycle(), None);
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let mut graph = Graph::new("test");
+        let req = graph.add_node(node(NodeKind::Request));
+        let a = graph.add_node(node(NodeKind::Comment { text: "a".to_string() }));
+        let b = graph.add_node(node(NodeKind::Comment { text: "b".to_string() }));
+        for (from_node, from_port, to_node) in [(req, 0, a), (a, 0, b), (b, 1, a)] {
+            graph.edges.push(Edge { from_node, from_port, to_node, to_port: 0 });
+        }
+
+        let plan = Plan::compile(&graph).unwrap();
+        assert_eq!(plan.find_cycle(), Some(vec![1, 2, 1]));
+    }
+}
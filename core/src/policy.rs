@@ -0,0 +1,44 @@
+//! Failure policy - what a graph does when something goes wrong.
+//!
+//! Each class of failure can fail open (let the request through, the way
+//! the engine has always behaved) or fail closed (refuse it). A guard in
+//! front of an admin area wants the latter; a marketing site the former.
+
+use serde::{Deserialize, Serialize};
+
+/// Behavior for one class of failure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureMode {
+    /// Let the request through
+    #[default]
+    Open,
+    /// Refuse the request
+    Closed,
+}
+
+/// Failure behavior per class. Everything fails open by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FailurePolicy {
+    /// The graph can't be loaded, validated or compiled (edge service)
+    pub load: FailureMode,
+    /// The rate limiter fails; open treats the client as under the limit
+    pub rate_limit: FailureMode,
+    /// A node's data didn't parse. Unset keeps each node type's own
+    /// fallback: flow nodes continue, actions respond with a 500
+    pub invalid_node: Option<FailureMode>,
+    /// The routed backend can't be reached (edge service)
+    pub backend: FailureMode,
+    /// Execution hits the step limit
+    pub step_limit: FailureMode,
+    /// The request body is longer than the inspection cap; open inspects
+    /// its beginning (edge service)
+    pub body_limit: FailureMode,
+}
+
+impl FailureMode {
+    pub fn is_closed(self) -> bool {
+        self == FailureMode::Closed
+    }
+}
@@ -0,0 +1,116 @@
+//! Stable hashing for backend pool and split nodes.
+//!
+//! Pool members are picked by weighted rendezvous hashing: every member that is
+//! up scores the request's key, and the highest score wins. The same key
+//! always lands on the same member, and taking a member down or adding one
+//! only moves the keys that member wins or loses. Hashing a per-request key
+//! (the request id) spreads requests by weight instead.
+
+use crate::payload::PoolMember;
+
+/// Index of the member `key` goes to, or `None` when no member is up.
+pub fn choose_member(members: &[PoolMember], key: &str) -> Option<usize> {
+    members
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| !m.down && m.weight > 0)
+        .map(|(i, m)| (i, score(key, &m.backend.name, m.weight)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+/// Index of the member named `name`, if it is up.
+pub fn member_named(members: &[PoolMember], name: &str) -> Option<usize> {
+    members.iter().position(|m| m.backend.name == name && !m.down && m.weight > 0)
+}
+
+/// Bucket `key` falls in, given the buckets' weights; `None` when they are all 0.
+///
+/// Buckets are consecutive ranges of the hash, so growing a bucket (a canary
+/// from 5% to 10%) only moves users in from the bucket after it.
+pub fn choose_bucket(weights: &[u32], salt: &str, key: &str) -> Option<usize> {
+    let total: u64 = weights.iter().map(|&w| w as u64).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut point = mix(fnv1a(key.as_bytes(), fnv1a(salt.as_bytes(), FNV_OFFSET))) % total;
+    weights.iter().position(|&w| {
+        let hit = point < w as u64;
+        point = point.saturating_sub(w as u64);
+        hit
+    })
+}
+
+/// Weighted rendezvous score: `weight / -ln(u)` for `u` uniform in (0, 1)
+/// derived from the key and member.
+fn score(key: &str, member: &str, weight: u32) -> f64 {
+    let hash = mix(fnv1a(key.as_bytes(), fnv1a(member.as_bytes(), FNV_OFFSET)));
+    let u = ((hash >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+    weight as f64 / -u.ln()
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a, so the editor preview and the edge pick the same members
+fn fnv1a(bytes: &[u8], seed: u64) -> u64 {
+    bytes.iter().fold(seed, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// MurmurHash3 finalizer: FNV barely changes the high bits for short,
+/// similar keys such as sequential request ids
+fn mix(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(spec: &[(&str, u32, bool)]) -> Vec<PoolMember> {
+        spec.iter()
+            .map(|&(name, weight, down)| {
+                serde_json::from_value(serde_json::json!({
+                    "name": name, "host": format!("{}.example.com", name), "weight": weight, "down": down
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_weights_and_down_members() {
+        let pool = members(&[("blue", 9, false), ("green", 1, false), ("red", 5, true)]);
+        let mut counts = [0; 3];
+        for i in 0..10_000 {
+            counts[choose_member(&pool, &format!("request-{}", i)).unwrap()] += 1;
+        }
+        assert_eq!(counts[2], 0);
+        assert!((8_700..9_300).contains(&counts[0]), "blue got {}", counts[0]);
+
+        assert_eq!(choose_member(&members(&[("a", 1, true), ("b", 0, false)]), "x"), None);
+    }
+
+    #[test]
+    fn test_keys_stay_on_their_member() {
+        let pool = members(&[("eu", 1, false), ("us", 1, false), ("ap", 1, false)]);
+        let before: Vec<_> = (0..100).map(|i| choose_member(&pool, &i.to_string()).unwrap()).collect();
+
+        // Taking "ap" down only moves the keys it had
+        let mut degraded = pool.clone();
+        degraded[2].down = true;
+        for (i, &member) in before.iter().enumerate() {
+            let now = choose_member(&degraded, &i.to_string()).unwrap();
+            if member != 2 {
+                assert_eq!(now, member);
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Value templates for header values, redirect URLs and block messages.
+//!
+//! A template is text with `${...}` placeholders, filled in from the request
+//! when the node runs: `https://${host}/eu${path}`. A placeholder names a
+//! request field (`${clientIp}`, `${country}`, `${requestId}`), a header
+//! (`${header.x-foo}`) or a transform variable (`${var.name}`). Text without
+//! placeholders is used as is.
+
+use crate::{RequestField, Value};
+
+/// A parsed template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(RequestField),
+}
+
+impl Template {
+    /// Parse a template. Fails on an unclosed placeholder or one that
+    /// doesn't name a field, header or variable.
+    pub fn parse(template: &str) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find("${") {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let after = &rest[start + 2..];
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("Unclosed placeholder in template '{}'", template))?;
+            parts.push(Part::Field(placeholder(after[..end].trim())?));
+            rest = &after[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+
+        Ok(Template { parts })
+    }
+
+    /// Fields the placeholders refer to.
+    pub fn fields(&self) -> impl Iterator<Item = &RequestField> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Field(field) => Some(field),
+            Part::Text(_) => None,
+        })
+    }
+
+    /// A template that is just the value of a transform variable.
+    pub fn variable(name: &str) -> Template {
+        Template { parts: vec![Part::Field(RequestField::Var { name: name.to_string() })] }
+    }
+
+    /// Fill in the placeholders. A field without a value renders empty.
+    pub fn render(&self, mut field_value: impl FnMut(&RequestField) -> Value) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Field(field) => out.push_str(&field_value(field).as_text()),
+            }
+        }
+        out
+    }
+}
+
+/// A rendered value made safe for a header or redirect URL: control
+/// characters other than tab are dropped, so a CR or LF decoded from the
+/// request can't end the header and start another one.
+pub fn header_safe(text: &str) -> String {
+    text.chars().filter(|&c| c == '\t' || !c.is_control()).collect()
+}
+
+/// Field a placeholder refers to.
+fn placeholder(name: &str) -> Result<RequestField, String> {
+    if let Some(header) = name.strip_prefix("header.") {
+        return Ok(RequestField::Header { name: header.to_string() });
+    }
+    if let Some(var) = name.strip_prefix("var.") {
+        return Ok(RequestField::Var { name: var.to_string() });
+    }
+    // from_key reads unknown names as headers; placeholders spell those out
+    match RequestField::from_key(name) {
+        RequestField::Header { .. } => Err(format!("Unknown placeholder: ${{{}}}", name)),
+        field => Ok(field),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        Template::parse(template).unwrap().render(|field| match field {
+            RequestField::Host => Value::String("example.com".to_string()),
+            RequestField::Path => Value::String("/shop".to_string()),
+            RequestField::Header { name } if name == "x-foo" => Value::String("bar".to_string()),
+            RequestField::Var { name } if name == "section" => Value::String("admin".to_string()),
+            _ => Value::None,
+        })
+    }
+
+    #[test]
+    fn test_placeholders_filled_in() {
+        assert_eq!(render("https://${host}/eu${path}"), "https://example.com/eu/shop");
+        assert_eq!(render("${header.x-foo}:${var.section}"), "bar:admin");
+        assert_eq!(render("country=${country}"), "country=");
+        assert_eq!(render("no placeholders, $5"), "no placeholders, $5");
+    }
+
+    #[test]
+    fn test_header_safe_drops_control_characters() {
+        assert_eq!(header_safe("a\r\nSet-Cookie: x=1"), "aSet-Cookie: x=1");
+        assert_eq!(header_safe("tab\tand\u{0}nul\u{7f}"), "tab\tandnul");
+        assert_eq!(header_safe("caf\u{e9}"), "caf\u{e9}");
+    }
+
+    #[test]
+    fn test_bad_placeholders_rejected() {
+        assert_eq!(
+            Template::parse("/eu${path").unwrap_err(),
+            "Unclosed placeholder in template '/eu${path'"
+        );
+        assert_eq!(Template::parse("${nope}").unwrap_err(), "Unknown placeholder: ${nope}");
+    }
+}
@@ -0,0 +1,525 @@
+//! Clock fields (`now`, `hour:<zone>`, `weekday:<zone>`, `today:<zone>`)
+//! and the bounds of the `between` operator.
+//!
+//! A zone is a fixed offset (`+02:00`, `-0530`, `UTC`), one of the IANA
+//! names in [`ZONES`], or a POSIX TZ rule such as
+//! `CET-1CEST,M3.5.0,M10.5.0/3`. The edge has no time zone database, so
+//! IANA names map to the POSIX rule each zone currently follows: daylight
+//! saving time is applied, historic changes to a zone's rules are not.
+
+use crate::{RequestField, Value};
+use serde::{Deserialize, Serialize};
+
+const DAY: i64 = 86_400;
+
+/// Weekday names as the `weekday` field reports them, Monday first.
+pub const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// IANA zone names understood without a rule, with the POSIX rule each
+/// one currently follows.
+pub const ZONES: &[(&str, &str)] = &[
+    ("Europe/London", "GMT0BST,M3.5.0/1,M10.5.0"),
+    ("Europe/Dublin", "GMT0IST,M3.5.0/1,M10.5.0"),
+    ("Europe/Lisbon", "WET0WEST,M3.5.0/1,M10.5.0"),
+    ("Europe/Amsterdam", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Berlin", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Brussels", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Copenhagen", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Madrid", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Oslo", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Paris", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Prague", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Rome", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Stockholm", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Vienna", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Warsaw", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Zurich", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Athens", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Bucharest", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Helsinki", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Kyiv", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Istanbul", "<+03>-3"),
+    ("Europe/Moscow", "MSK-3"),
+    ("Africa/Cairo", "EET-2EEST,M4.5.5/0,M10.5.4/24"),
+    ("Africa/Johannesburg", "SAST-2"),
+    ("Africa/Lagos", "WAT-1"),
+    ("Africa/Nairobi", "EAT-3"),
+    ("Asia/Jerusalem", "IST-2IDT,M3.4.4/26,M10.5.0"),
+    ("Asia/Riyadh", "<+03>-3"),
+    ("Asia/Dubai", "<+04>-4"),
+    ("Asia/Karachi", "PKT-5"),
+    ("Asia/Kolkata", "IST-5:30"),
+    ("Asia/Dhaka", "<+06>-6"),
+    ("Asia/Bangkok", "<+07>-7"),
+    ("Asia/Jakarta", "WIB-7"),
+    ("Asia/Hong_Kong", "HKT-8"),
+    ("Asia/Shanghai", "CST-8"),
+    ("Asia/Singapore", "<+08>-8"),
+    ("Asia/Taipei", "CST-8"),
+    ("Asia/Seoul", "KST-9"),
+    ("Asia/Tokyo", "JST-9"),
+    ("Australia/Perth", "AWST-8"),
+    ("Australia/Adelaide", "ACST-9:30ACDT,M10.1.0,M4.1.0/3"),
+    ("Australia/Brisbane", "AEST-10"),
+    ("Australia/Melbourne", "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    ("Australia/Sydney", "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    ("Pacific/Auckland", "NZST-12NZDT,M9.5.0,M4.1.0/3"),
+    ("Pacific/Honolulu", "HST10"),
+    ("America/Anchorage", "AKST9AKDT,M3.2.0,M11.1.0"),
+    ("America/Los_Angeles", "PST8PDT,M3.2.0,M11.1.0"),
+    ("America/Vancouver", "PST8PDT,M3.2.0,M11.1.0"),
+    ("America/Phoenix", "MST7"),
+    ("America/Denver", "MST7MDT,M3.2.0,M11.1.0"),
+    ("America/Chicago", "CST6CDT,M3.2.0,M11.1.0"),
+    ("America/Mexico_City", "CST6"),
+    ("America/Bogota", "<-05>5"),
+    ("America/New_York", "EST5EDT,M3.2.0,M11.1.0"),
+    ("America/Toronto", "EST5EDT,M3.2.0,M11.1.0"),
+    ("America/Halifax", "AST4ADT,M3.2.0,M11.1.0"),
+    ("America/Sao_Paulo", "<-03>3"),
+    ("America/Argentina/Buenos_Aires", "<-03>3"),
+];
+
+/// A time zone: a standard offset from UTC and an optional yearly
+/// daylight saving rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeZone {
+    /// Standard offset in seconds east of UTC
+    offset: i64,
+    dst: Option<DaylightSaving>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DaylightSaving {
+    /// Offset in seconds east of UTC while daylight saving time applies
+    offset: i64,
+    start: Transition,
+    end: Transition,
+}
+
+/// A POSIX `Mm.w.d/time` rule: weekday `d` (0 = Sunday) of week `w`
+/// (5 = last) of month `m`, at `time` seconds past local midnight.
+#[derive(Debug, Clone, PartialEq)]
+struct Transition {
+    month: u32,
+    week: i64,
+    weekday: i64,
+    time: i64,
+}
+
+impl TimeZone {
+    pub const UTC: TimeZone = TimeZone { offset: 0, dst: None };
+
+    /// Parse a zone: empty or `UTC` for UTC, a fixed offset, a name from
+    /// [`ZONES`] (case-insensitive) or a POSIX TZ rule.
+    pub fn parse(zone: &str) -> Result<TimeZone, String> {
+        let zone = zone.trim();
+        if zone.is_empty() || zone == "Z" || zone.eq_ignore_ascii_case("UTC") || zone.eq_ignore_ascii_case("GMT") {
+            return Ok(TimeZone::UTC);
+        }
+        if zone.starts_with(['+', '-']) {
+            return fixed_offset(zone)
+                .map(|offset| TimeZone { offset, dst: None })
+                .ok_or_else(|| format!("Invalid UTC offset '{}', expected e.g. +02:00", zone));
+        }
+        if let Some((_, rule)) = ZONES.iter().find(|(name, _)| name.eq_ignore_ascii_case(zone)) {
+            return Ok(posix_rule(rule).expect("built-in zone rules are valid"));
+        }
+        posix_rule(zone).filter(|_| !zone.contains('/')).ok_or_else(|| {
+            format!(
+                "Unknown time zone '{}'; use an offset like +02:00, a zone like Europe/Berlin \
+                 or a POSIX rule like CET-1CEST,M3.5.0,M10.5.0/3",
+                zone
+            )
+        })
+    }
+
+    /// Offset from UTC in seconds at the instant `utc` (Unix seconds).
+    pub fn offset_at(&self, utc: i64) -> i64 {
+        let Some(dst) = &self.dst else {
+            return self.offset;
+        };
+        let (year, _, _) = civil_from_days((utc + self.offset).div_euclid(DAY));
+        // Daylight saving starts at a standard time and ends at a daylight one
+        let start = dst.start.local_seconds(year) - self.offset;
+        let end = dst.end.local_seconds(year) - dst.offset;
+        let in_dst = if start < end {
+            utc >= start && utc < end
+        } else {
+            // Southern hemisphere: daylight saving spans the new year
+            utc >= start || utc < end
+        };
+        if in_dst { dst.offset } else { self.offset }
+    }
+}
+
+/// Time zone of a clock field as written in the graph, parsed once when
+/// the field is built so evaluating it doesn't parse it again. Fields
+/// compare and serialize by the name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct ClockZone {
+    name: String,
+    zone: Result<TimeZone, String>,
+}
+
+impl ClockZone {
+    pub const UTC: ClockZone = ClockZone { name: String::new(), zone: Ok(TimeZone::UTC) };
+
+    pub fn new(name: &str) -> ClockZone {
+        ClockZone { name: name.to_string(), zone: TimeZone::parse(name) }
+    }
+
+    /// The zone as written (empty for UTC).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The parsed zone, or why it doesn't parse.
+    pub fn zone(&self) -> Result<&TimeZone, &str> {
+        self.zone.as_ref().map_err(String::as_str)
+    }
+}
+
+impl PartialEq for ClockZone {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for ClockZone {}
+
+impl From<String> for ClockZone {
+    fn from(name: String) -> ClockZone {
+        ClockZone::new(&name)
+    }
+}
+
+impl From<ClockZone> for String {
+    fn from(zone: ClockZone) -> String {
+        zone.name
+    }
+}
+
+impl Transition {
+    /// Local time of the transition in `year`, as seconds since the epoch.
+    fn local_seconds(&self, year: i64) -> i64 {
+        let first = days_from_civil(year, self.month, 1);
+        let length = days_in_month(year, self.month);
+        let mut day = (self.weekday - sunday_weekday(first)).rem_euclid(7) + (self.week - 1) * 7;
+        while day >= length {
+            day -= 7;
+        }
+        (first + day) * DAY + self.time
+    }
+}
+
+/// Value of a clock field at `now` (Unix seconds); other fields, and zones
+/// that don't parse, have no value.
+pub fn time_field(field: &RequestField, now: i64) -> Value {
+    let zone = match field {
+        RequestField::Now => return Value::String(format_utc(now)),
+        RequestField::Hour { zone } | RequestField::Weekday { zone } | RequestField::Today { zone } => zone,
+        _ => return Value::None,
+    };
+    let Ok(zone) = zone.zone() else {
+        return Value::None;
+    };
+    let local = now + zone.offset_at(now);
+    let days = local.div_euclid(DAY);
+    match field {
+        RequestField::Hour { .. } => Value::Number((local.rem_euclid(DAY) / 3600) as f64),
+        RequestField::Weekday { .. } => Value::String(WEEKDAYS[(sunday_weekday(days) + 6) as usize % 7].to_string()),
+        _ => Value::String(format_date(days)),
+    }
+}
+
+/// A bound of a `between` range, or a field value compared against one:
+/// a number, a weekday (`Mon` = 1 … `Sun` = 7), a date (`2026-12-24`) or a
+/// date and time (`2026-12-24T18:00`, UTC unless an offset follows), the
+/// last two as Unix seconds. A date as an `end` bound covers its whole day.
+pub fn range_point(text: &str, end: bool) -> Option<f64> {
+    let text = text.trim();
+    if let Ok(n) = text.parse::<f64>() {
+        return Some(n);
+    }
+    if let Some(day) = WEEKDAYS.iter().position(|d| d.eq_ignore_ascii_case(text)) {
+        return Some(day as f64 + 1.0);
+    }
+    parse_time(text, end)
+}
+
+/// A date (`2026-12-24`) or date and time (`2026-12-24T18:00:00Z`, UTC
+/// unless an offset follows) as Unix seconds. With `end` set, a date alone
+/// means the last second of that day.
+pub fn parse_time(text: &str, end: bool) -> Option<f64> {
+    let text = text.trim();
+    let (date, time) = match text.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+    let days = parse_date(date)?;
+    let Some(time) = time else {
+        return Some((days * DAY + if end { DAY - 1 } else { 0 }) as f64);
+    };
+    let (clock, offset) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else if let Some(at) = time.rfind(['+', '-']) {
+        (&time[..at], fixed_offset(&time[at..])?)
+    } else {
+        (time, 0)
+    };
+    let mut parts = clock.split(':');
+    let hours: i64 = parts.next()?.parse().ok().filter(|h| (0..24).contains(h))?;
+    let minutes: i64 = parts.next()?.parse().ok().filter(|m| (0..60).contains(m))?;
+    let seconds: f64 = match parts.next() {
+        Some(s) => s.parse().ok().filter(|s| (0.0..61.0).contains(s))?,
+        None => 0.0,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((days * DAY + hours * 3600 + minutes * 60 - offset) as f64 + seconds)
+}
+
+/// RFC 3339 form of a Unix time in UTC, e.g. `2026-10-16T08:30:00Z`.
+pub fn format_utc(secs: i64) -> String {
+    let time = secs.rem_euclid(DAY);
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_date(secs.div_euclid(DAY)),
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `YYYY-MM-DD` as days since the epoch.
+fn parse_date(text: &str) -> Option<i64> {
+    let mut parts = text.split('-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    let year: i64 = year.parse().ok()?;
+    let month: u32 = month.parse().ok().filter(|m| (1..=12).contains(m))?;
+    let day: u32 = day.parse().ok()?;
+    if day == 0 || i64::from(day) > days_in_month(year, month) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+/// `+02:00`, `-0530` or `+5` as seconds east of UTC.
+fn fixed_offset(text: &str) -> Option<i64> {
+    let (sign, digits) = match text.as_bytes().first()? {
+        b'+' => (1, &text[1..]),
+        b'-' => (-1, &text[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if digits.len() == 4 => digits.split_at(2),
+        None => (digits, "0"),
+    };
+    if !(1..=2).contains(&hours.len()) || !hours.bytes().chain(minutes.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = hours.parse().ok().filter(|h| *h <= 14)?;
+    let minutes: i64 = minutes.parse().ok().filter(|m| *m < 60)?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parse a POSIX TZ rule: `STDoffset[DST[offset],Mm.w.d[/time],Mm.w.d[/time]]`.
+/// POSIX offsets count west of UTC, so `CET-1` is an hour ahead of it.
+fn posix_rule(rule: &str) -> Option<TimeZone> {
+    let rest = zone_abbreviation(rule)?;
+    let (west, rest) = duration(rest)?;
+    let offset = -west;
+    if rest.is_empty() {
+        return Some(TimeZone { offset, dst: None });
+    }
+    let rest = zone_abbreviation(rest)?;
+    let (dst_offset, rest) = match duration(rest) {
+        Some((west, rest)) => (-west, rest),
+        None => (offset + 3600, rest),
+    };
+    let (start, rest) = transition(rest.strip_prefix(',')?)?;
+    let (end, rest) = transition(rest.strip_prefix(',')?)?;
+    if !rest.is_empty() {
+        return None;
+    }
+    Some(TimeZone { offset, dst: Some(DaylightSaving { offset: dst_offset, start, end }) })
+}
+
+/// Skip a zone abbreviation (`CET`, or quoted like `<+03>`).
+fn zone_abbreviation(text: &str) -> Option<&str> {
+    if let Some(quoted) = text.strip_prefix('<') {
+        return quoted.split_once('>').map(|(_, rest)| rest);
+    }
+    let length = text.bytes().take_while(u8::is_ascii_alphabetic).count();
+    (length >= 3).then_some(&text[length..])
+}
+
+/// `[+-]hh[:mm[:ss]]` as seconds.
+fn duration(text: &str) -> Option<(i64, &str)> {
+    let (sign, mut rest) = match text.as_bytes().first()? {
+        b'+' => (1, &text[1..]),
+        b'-' => (-1, &text[1..]),
+        _ => (1, text),
+    };
+    let mut seconds = 0;
+    for (i, unit) in [3600, 60, 1].into_iter().enumerate() {
+        if i > 0 {
+            match rest.strip_prefix(':') {
+                Some(after) => rest = after,
+                None => break,
+            }
+        }
+        let (n, after) = number(rest)?;
+        seconds += n * unit;
+        rest = after;
+    }
+    Some((sign * seconds, rest))
+}
+
+/// `Mm.w.d[/time]`; the time defaults to 02:00.
+fn transition(text: &str) -> Option<(Transition, &str)> {
+    let (month, rest) = number(text.strip_prefix('M')?)?;
+    let (week, rest) = number(rest.strip_prefix('.')?)?;
+    let (weekday, rest) = number(rest.strip_prefix('.')?)?;
+    let (time, rest) = match rest.strip_prefix('/') {
+        Some(time) => duration(time)?,
+        None => (7200, rest),
+    };
+    if !(1..=12).contains(&month) || !(1..=5).contains(&week) || !(0..=6).contains(&weekday) {
+        return None;
+    }
+    Some((Transition { month: month as u32, week, weekday, time }, rest))
+}
+
+/// Up to three leading digits.
+fn number(text: &str) -> Option<(i64, &str)> {
+    let length = text.bytes().take_while(u8::is_ascii_digit).count();
+    if length == 0 || length > 3 {
+        return None;
+    }
+    Some((text[..length].parse().ok()?, &text[length..]))
+}
+
+/// Day of the week, 0 = Sunday (the epoch was a Thursday).
+fn sunday_weekday(days: i64) -> i64 {
+    (days + 4).rem_euclid(7)
+}
+
+fn days_in_month(year: i64, month: u32) -> i64 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Date of a day counted from 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> i64 {
+        range_point(text, false).unwrap() as i64
+    }
+
+    fn hour(zone: &str, now: i64) -> Value {
+        time_field(&RequestField::Hour { zone: ClockZone::new(zone) }, now)
+    }
+
+    #[test]
+    fn test_clock_fields_in_zones() {
+        let now = at("2026-10-16T22:30:00Z");
+        assert_eq!(time_field(&RequestField::Now, now), Value::String("2026-10-16T22:30:00Z".to_string()));
+        assert_eq!(hour("", now), Value::Number(22.0));
+        assert_eq!(hour("+05:30", now), Value::Number(4.0));
+        assert_eq!(hour("-0800", now), Value::Number(14.0));
+        // Summer time in Berlin until the last Sunday of October
+        assert_eq!(hour("Europe/Berlin", now), Value::Number(0.0));
+        assert_eq!(hour("Europe/Berlin", at("2026-12-01T22:30:00Z")), Value::Number(23.0));
+        assert_eq!(hour("America/New_York", now), Value::Number(18.0));
+        // Daylight saving spans the new year in the southern hemisphere
+        assert_eq!(hour("Australia/Sydney", now), Value::Number(9.0));
+        assert_eq!(hour("Australia/Sydney", at("2026-06-01T22:30:00Z")), Value::Number(8.0));
+        assert_eq!(hour("Mars/Olympus", now), Value::None);
+
+        let weekday = |zone: &str| time_field(&RequestField::Weekday { zone: ClockZone::new(zone) }, now);
+        assert_eq!(weekday("UTC"), Value::String("Fri".to_string()));
+        assert_eq!(weekday("Asia/Tokyo"), Value::String("Sat".to_string()));
+        let today = time_field(&RequestField::Today { zone: ClockZone::new("Asia/Tokyo") }, now);
+        assert_eq!(today, Value::String("2026-10-17".to_string()));
+
+        // US daylight saving ends at 02:00 local on the first Sunday in November
+        let zone = TimeZone::parse("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        assert_eq!(zone.offset_at(at("2026-11-01T05:59:59Z")), -4 * 3600);
+        assert_eq!(zone.offset_at(at("2026-11-01T06:00:00Z")), -5 * 3600);
+        assert!(TimeZone::parse("Europe/Nowhere").is_err());
+        assert!(TimeZone::parse("+25:00").is_err());
+    }
+
+    #[test]
+    fn test_zone_parsed_with_field() {
+        let field = RequestField::from_key("hour:Europe/Berlin");
+        let zone = field.time_zone().unwrap();
+        assert_eq!(zone.zone(), Ok(&TimeZone::parse("Europe/Berlin").unwrap()));
+        assert_eq!(field.to_string(), "hour:Europe/Berlin");
+        assert_eq!(serde_json::to_string(zone).unwrap(), r#""Europe/Berlin""#);
+        assert_eq!(RequestField::from_key("hour"), RequestField::Hour { zone: ClockZone::UTC });
+
+        let unknown = RequestField::from_key("hour:Mars/Olympus");
+        assert!(unknown.time_zone().unwrap().zone().unwrap_err().starts_with("Unknown time zone 'Mars/Olympus'"));
+    }
+
+    #[test]
+    fn test_range_points() {
+        assert_eq!(range_point("9", false), Some(9.0));
+        assert_eq!(range_point("fri", false), Some(5.0));
+        assert_eq!(at("1970-01-02"), DAY);
+        assert_eq!(range_point("1970-01-02", true), Some((2 * DAY - 1) as f64));
+        assert_eq!(at("2026-10-16T12:00+02:00"), at("2026-10-16T10:00:00Z"));
+        assert_eq!(at("2024-02-29 00:00"), at("2024-02-29"));
+        assert_eq!(range_point("2026-02-29", false), None);
+        assert_eq!(range_point("2026-10-16T25:00", false), None);
+        assert_eq!(range_point("soon", false), None);
+        for days in [-800_000, -1, 0, 59, 20_000, 2_932_896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
+}
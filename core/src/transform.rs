@@ -0,0 +1,141 @@
+//! Value transformations used by transform nodes.
+
+use crate::TransformOp;
+use regex::Regex;
+
+/// Apply a transformation to a value.
+/// `pattern` is the compiled pattern of an Extract transform.
+/// Problems (bad base64, no regex match) are reported into `trace`.
+pub fn apply_transform(
+    operation: TransformOp,
+    value: &str,
+    pattern: Option<&Regex>,
+    trace: &mut Vec<String>,
+) -> String {
+    match operation {
+        TransformOp::Lowercase => value.to_lowercase(),
+        TransformOp::Uppercase => value.to_uppercase(),
+        TransformOp::UrlDecode => url_decode(value),
+        TransformOp::Base64Decode => {
+            use base64::{Engine as _, engine::general_purpose::STANDARD};
+            STANDARD.decode(value)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .unwrap_or_else(|| {
+                    trace.push(format!("Base64 decode failed for: {}", value));
+                    value.to_string()
+                })
+        }
+        TransformOp::HtmlDecode => html_decode(value),
+        TransformOp::RemoveWhitespace => value.chars().filter(|c| !c.is_whitespace()).collect(),
+        TransformOp::Extract => {
+            // Regex extract - use capture group 1 if available
+            let Some(re) = pattern else {
+                trace.push("Extract operation requires a pattern".to_string());
+                return String::new();
+            };
+            re.captures(value)
+                .and_then(|caps| caps.get(1).map(|m| m.as_str().to_string()))
+                .unwrap_or_else(|| {
+                    trace.push(format!("No match for pattern '{}' in '{}'", re.as_str(), value));
+                    String::new()
+                })
+        }
+    }
+}
+
+/// URL decode a string (percent-encoding).
+pub fn url_decode(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            // Try to read two hex digits
+            let hex: String = chars.by_ref().take(2).collect();
+            if hex.len() == 2 {
+                if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                    result.push(byte as char);
+                    continue;
+                }
+            }
+            // Failed to decode, keep the original
+            result.push('%');
+            result.push_str(&hex);
+        } else if c == '+' {
+            // Plus sign is space in query strings
+            result.push(' ');
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// HTML entity decode common entities.
+pub fn html_decode(input: &str) -> String {
+    let mut result = input.to_string();
+
+    // Named entities
+    result = result.replace("&amp;", "&");
+    result = result.replace("&lt;", "<");
+    result = result.replace("&gt;", ">");
+    result = result.replace("&quot;", "\"");
+    result = result.replace("&apos;", "'");
+    result = result.replace("&nbsp;", " ");
+
+    // Numeric entities (basic support)
+    // Handle &#NNN; format
+    let mut output = String::with_capacity(result.len());
+    let mut chars = result.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '&' && chars.peek() == Some(&'#') {
+            chars.next(); // consume '#'
+            let mut num_str = String::new();
+            let is_hex = chars.peek() == Some(&'x') || chars.peek() == Some(&'X');
+            if is_hex {
+                chars.next(); // consume 'x'
+            }
+
+            // Collect digits
+            while let Some(&d) = chars.peek() {
+                if d == ';' {
+                    chars.next(); // consume ';'
+                    break;
+                }
+                if (is_hex && d.is_ascii_hexdigit()) || (!is_hex && d.is_ascii_digit()) {
+                    num_str.push(chars.next().unwrap());
+                } else {
+                    break;
+                }
+            }
+
+            // Parse and convert
+            let code_point = if is_hex {
+                u32::from_str_radix(&num_str, 16).ok()
+            } else {
+                num_str.parse::<u32>().ok()
+            };
+
+            if let Some(cp) = code_point {
+                if let Some(ch) = char::from_u32(cp) {
+                    output.push(ch);
+                    continue;
+                }
+            }
+
+            // Failed to decode, keep original
+            output.push('&');
+            output.push('#');
+            if is_hex {
+                output.push('x');
+            }
+            output.push_str(&num_str);
+        } else {
+            output.push(c);
+        }
+    }
+
+    output
+}
@@ -0,0 +1,485 @@
+//! Load-time validation of editor graphs.
+//!
+//! The converter and interpreter are lenient on purpose: a node that
+//! doesn't parse falls back at runtime, and an edge that can't be
+//! followed is dropped. [`validate`] reports all of those problems up
+//! front, with the editor ids of the nodes and edges involved, so a bad
+//! graph can be rejected before it is deployed or loaded.
+
+use crate::convert::{active_window, convert_node, from_payload, output_port};
+use crate::payload::{BackendNodeData, GraphPayload, MAX_BACKEND_ATTEMPTS};
+use crate::plan::compile_node;
+use crate::{ActionType, Node, NodeKind, Plan, MAX_TARPIT_DELAY_MS};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The graph doesn't do what the editor shows
+    Error,
+    /// The graph runs as shown, but something looks unintended
+    Warning,
+}
+
+/// What a diagnostic is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticCode {
+    /// No request node: nothing is evaluated
+    MissingRequestNode,
+    /// More than one request node: only the first is evaluated
+    MultipleRequestNodes,
+    DuplicateNodeId,
+    UnknownNodeType,
+    /// Node data that doesn't parse (missing fields, unknown operator, ...)
+    InvalidNodeData,
+    /// Node data that parses but can't be compiled (bad regex, bad CIDR, ...)
+    InvalidNodeConfig,
+    /// Edge whose source or target node doesn't exist
+    DanglingEdge,
+    /// Edge leaving from a handle the source node doesn't have
+    UnknownHandle,
+    /// Execution can go around in a loop (it is cut off by the step limit)
+    Cycle,
+    /// Node whose `activeUntil` has passed: it only passes requests through
+    Expired,
+    /// Setting outside its allowed range: the nearest allowed value is used
+    OutOfRange,
+}
+
+/// A problem found in a graph.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    /// Editor id of the node involved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+    /// Editor id of the edge involved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edge_id: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn node(severity: Severity, code: DiagnosticCode, node_id: &str, message: String) -> Self {
+        Self { severity, code, node_id: Some(node_id.to_string()), edge_id: None, message }
+    }
+
+    fn edge(severity: Severity, code: DiagnosticCode, edge_id: &str, message: String) -> Self {
+        Self { severity, code, node_id: None, edge_id: Some(edge_id.to_string()), message }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match (&self.node_id, &self.edge_id) {
+            (Some(node), _) => write!(f, "{} [node {}]: {}", severity, node, self.message),
+            (None, Some(edge)) => write!(f, "{} [edge {}]: {}", severity, edge, self.message),
+            (None, None) => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+/// Check an editor graph, returning every problem found (empty when valid).
+/// Checks that need the current time are skipped; see [`validate_at`].
+pub fn validate(payload: &GraphPayload) -> Vec<Diagnostic> {
+    validate_at(payload, None)
+}
+
+/// [`validate`], also warning about nodes that have expired at `now`
+/// (Unix seconds).
+pub fn validate_at(payload: &GraphPayload, now: Option<i64>) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut kinds: HashMap<&str, NodeKind> = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut request_seen = false;
+
+    if !payload.nodes.iter().any(|n| n.node_type == "request") {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            code: DiagnosticCode::MissingRequestNode,
+            node_id: None,
+            edge_id: None,
+            message: "Graph has no request node".to_string(),
+        });
+    }
+
+    for node in &payload.nodes {
+        if node.node_type == "request" {
+            if request_seen {
+                diagnostics.push(Diagnostic::node(
+                    Severity::Warning,
+                    DiagnosticCode::MultipleRequestNodes,
+                    &node.id,
+                    "Only the first request node is evaluated".to_string(),
+                ));
+            }
+            request_seen = true;
+        }
+        if !seen.insert(node.id.as_str()) {
+            diagnostics.push(Diagnostic::node(
+                Severity::Error,
+                DiagnosticCode::DuplicateNodeId,
+                &node.id,
+                format!("Duplicate node id: {}", node.id),
+            ));
+            continue;
+        }
+
+        let kind = convert_node(node);
+        match &kind {
+            NodeKind::Invalid { node_type, error } => {
+                let code = if error == "Unknown node type" {
+                    DiagnosticCode::UnknownNodeType
+                } else {
+                    DiagnosticCode::InvalidNodeData
+                };
+                diagnostics.push(Diagnostic::node(
+                    Severity::Error,
+                    code,
+                    &node.id,
+                    format!("{} ({})", error, node_type),
+                ));
+            }
+            _ => {
+                let typed = Node { id: 0, kind: kind.clone(), position: (0.0, 0.0), editor_id: None, active: None };
+                if let Err(message) = compile_node(&typed) {
+                    diagnostics.push(Diagnostic::node(
+                        Severity::Error,
+                        DiagnosticCode::InvalidNodeConfig,
+                        &node.id,
+                        message,
+                    ));
+                }
+                for backend in backends(&kind) {
+                    let Some(retry) = &backend.retry else {
+                        continue;
+                    };
+                    if retry.attempts() != retry.max_attempts {
+                        diagnostics.push(Diagnostic::node(
+                            Severity::Warning,
+                            DiagnosticCode::OutOfRange,
+                            &node.id,
+                            format!(
+                                "Backend {}: maxAttempts {} is outside 1..={}; {} is used",
+                                backend.name, retry.max_attempts, MAX_BACKEND_ATTEMPTS, retry.attempts()
+                            ),
+                        ));
+                    }
+                }
+                for action in actions(&kind) {
+                    if let ActionType::Tarpit { delay_ms, .. } = action {
+                        if *delay_ms > MAX_TARPIT_DELAY_MS {
+                            diagnostics.push(Diagnostic::node(
+                                Severity::Warning,
+                                DiagnosticCode::OutOfRange,
+                                &node.id,
+                                format!(
+                                    "Tarpit delayMs {} is above {}; {} is used",
+                                    delay_ms, MAX_TARPIT_DELAY_MS, MAX_TARPIT_DELAY_MS
+                                ),
+                            ));
+                        }
+                    }
+                }
+                let window = active_window(&node.data).ok().flatten();
+                if let (Some(window), Some(now)) = (window, now) {
+                    if window.has_ended(now) {
+                        diagnostics.push(Diagnostic::node(
+                            Severity::Warning,
+                            DiagnosticCode::Expired,
+                            &node.id,
+                            format!("Node expired ({}); it passes every request through", window),
+                        ));
+                    }
+                }
+            }
+        }
+        kinds.insert(&node.id, kind);
+    }
+
+    for edge in &payload.edges {
+        let Some(kind) = kinds.get(edge.source.as_str()) else {
+            diagnostics.push(Diagnostic::edge(
+                Severity::Error,
+                DiagnosticCode::DanglingEdge,
+                &edge.id,
+                format!("Source node {} doesn't exist", edge.source),
+            ));
+            continue;
+        };
+        if !kinds.contains_key(edge.target.as_str()) {
+            diagnostics.push(Diagnostic::edge(
+                Severity::Error,
+                DiagnosticCode::DanglingEdge,
+                &edge.id,
+                format!("Target node {} doesn't exist", edge.target),
+            ));
+            continue;
+        }
+        // Edges of invalid nodes are already covered by the node's error
+        if !matches!(kind, NodeKind::Invalid { .. })
+            && output_port(kind, edge.source_handle.as_deref()).is_none()
+        {
+            diagnostics.push(Diagnostic::edge(
+                Severity::Warning,
+                DiagnosticCode::UnknownHandle,
+                &edge.id,
+                format!(
+                    "Node {} has no output '{}'; the edge is never followed",
+                    edge.source,
+                    edge.source_handle.as_deref().unwrap_or("")
+                ),
+            ));
+        }
+    }
+
+    // Cycles only matter along the edges execution follows, which needs a
+    // plan; a graph that doesn't compile has already been reported
+    if let Some(plan) = from_payload(payload).ok().and_then(|g| Plan::compile(&g).ok()) {
+        if let Some(cycle) = plan.find_cycle() {
+            let labels: Vec<&str> = cycle.iter().map(|&i| plan.node(i).label.as_str()).collect();
+            diagnostics.push(Diagnostic::node(
+                Severity::Error,
+                DiagnosticCode::Cycle,
+                labels[0],
+                format!("Cycle: {}", labels.join(" -> ")),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Backends a node sends requests to (not counting fallbacks, which are
+/// tried once).
+fn backends(kind: &NodeKind) -> Vec<&BackendNodeData> {
+    match kind {
+        NodeKind::Backend { config } => vec![config],
+        NodeKind::BackendPool { members, .. } => members.iter().map(|m| &m.backend).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Actions a node takes: its own, or the inline actions of its rules.
+fn actions(kind: &NodeKind) -> Vec<&ActionType> {
+    match kind {
+        NodeKind::Action { action } => vec![action],
+        NodeKind::RuleList { rules, .. } => rules.iter().filter_map(|r| r.action.as_ref()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(json: &str) -> Vec<Diagnostic> {
+        validate(&serde_json::from_str(json).unwrap())
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<DiagnosticCode> {
+        diagnostics.iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_valid_graph_has_no_diagnostics() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "cond", "type": "condition", "position": {"x": 100, "y": 0},
+                 "data": {"field": "path", "operator": "matches", "value": "^/api/"}},
+                {"id": "block", "type": "action", "position": {"x": 200, "y": 0}, "data": {"action": "block"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "cond"},
+                {"id": "e2", "source": "cond", "target": "block", "sourceHandle": "true"}
+            ]
+        }"#;
+
+        assert_eq!(check(json), vec![]);
+    }
+
+    #[test]
+    fn test_node_problems_reported_with_ids() {
+        let json = r#"{
+            "nodes": [
+                {"id": "cond", "type": "condition", "position": {"x": 0, "y": 0},
+                 "data": {"field": "path", "operator": "looksLike", "value": "/api"}},
+                {"id": "re", "type": "condition", "position": {"x": 0, "y": 0},
+                 "data": {"field": "path", "operator": "matches", "value": "(unclosed"}},
+                {"id": "what", "type": "teleport", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "what", "type": "comment", "position": {"x": 0, "y": 0}, "data": {}}
+            ],
+            "edges": []
+        }"#;
+
+        let diagnostics = check(json);
+        assert_eq!(
+            codes(&diagnostics),
+            vec![
+                DiagnosticCode::MissingRequestNode,
+                DiagnosticCode::InvalidNodeData,
+                DiagnosticCode::InvalidNodeConfig,
+                DiagnosticCode::UnknownNodeType,
+                DiagnosticCode::DuplicateNodeId,
+            ]
+        );
+        assert_eq!(diagnostics[1].node_id.as_deref(), Some("cond"));
+        assert_eq!(diagnostics[1].message, "Unknown operator: looksLike (condition)");
+        assert!(diagnostics.iter().all(Diagnostic::is_error));
+    }
+
+    #[test]
+    fn test_edge_problems_reported_with_ids() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "req2", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "cond", "type": "condition", "position": {"x": 100, "y": 0},
+                 "data": {"field": "method", "operator": "equals", "value": "GET"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "cond"},
+                {"id": "e2", "source": "cond", "target": "gone", "sourceHandle": "true"},
+                {"id": "e3", "source": "cond", "target": "req", "sourceHandle": "maybe"}
+            ]
+        }"#;
+
+        let diagnostics = check(json);
+        assert_eq!(
+            codes(&diagnostics),
+            vec![
+                DiagnosticCode::MultipleRequestNodes,
+                DiagnosticCode::DanglingEdge,
+                DiagnosticCode::UnknownHandle,
+            ]
+        );
+        assert_eq!(diagnostics[1].edge_id.as_deref(), Some("e2"));
+        assert_eq!(diagnostics[1].to_string(), "error [edge e2]: Target node gone doesn't exist");
+        assert_eq!(diagnostics[2].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_cycle_reported() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "hdr", "type": "header", "position": {"x": 100, "y": 0},
+                 "data": {"operation": "set", "name": "X-Loop", "value": "1"}},
+                {"id": "cond", "type": "condition", "position": {"x": 200, "y": 0},
+                 "data": {"field": "method", "operator": "equals", "value": "GET"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "hdr"},
+                {"id": "e2", "source": "hdr", "target": "cond", "sourceHandle": "next"},
+                {"id": "e3", "source": "cond", "target": "hdr", "sourceHandle": "false"}
+            ]
+        }"#;
+
+        let diagnostics = check(json);
+        assert_eq!(codes(&diagnostics), vec![DiagnosticCode::Cycle]);
+        assert_eq!(diagnostics[0].node_id.as_deref(), Some("hdr"));
+        assert_eq!(diagnostics[0].message, "Cycle: hdr -> cond -> hdr");
+    }
+
+    #[test]
+    fn test_schedules_checked() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "incident", "type": "condition", "position": {"x": 100, "y": 0},
+                 "data": {"field": "asn", "operator": "equals", "value": "64496", "activeUntil": "2026-10-01"}},
+                {"id": "launch", "type": "action", "position": {"x": 200, "y": 0},
+                 "data": {"action": "block", "activeFrom": "2026-11-01T09:00:00+01:00", "activeUntil": ""}},
+                {"id": "typo", "type": "action", "position": {"x": 200, "y": 0},
+                 "data": {"action": "block", "activeFrom": "2026-11-01", "activeUntil": "2026-10-01"}},
+                {"id": "bad", "type": "action", "position": {"x": 200, "y": 0},
+                 "data": {"action": "block", "activeUntil": "next week"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "req", "target": "incident"},
+                {"id": "e2", "source": "incident", "target": "launch", "sourceHandle": "true"}
+            ]
+        }"#;
+        let payload = serde_json::from_str(json).unwrap();
+        let now = crate::parse_time("2026-10-16T12:00:00Z", false).map(|secs| secs as i64);
+
+        let diagnostics = validate_at(&payload, now);
+        assert_eq!(
+            codes(&diagnostics),
+            vec![DiagnosticCode::Expired, DiagnosticCode::InvalidNodeData, DiagnosticCode::InvalidNodeData]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "warning [node incident]: Node expired (active until 2026-10-01T23:59:59Z); it passes every request through"
+        );
+        assert_eq!(diagnostics[1].message, "activeFrom is after activeUntil (action)");
+        assert!(diagnostics[2].message.starts_with(r#"activeUntil "next week" is not a date or time"#));
+        // Without a clock nothing has expired
+        assert_eq!(codes(&validate(&payload)), vec![DiagnosticCode::InvalidNodeData; 2]);
+    }
+
+    #[test]
+    fn test_tarpit_delay_out_of_range() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "slow", "type": "action", "position": {"x": 100, "y": 0},
+                 "data": {"action": "tarpit", "delayMs": 60000}},
+                {"id": "ok", "type": "action", "position": {"x": 100, "y": 100},
+                 "data": {"action": "tarpit", "delayMs": 10000}}
+            ],
+            "edges": [{"id": "e1", "source": "req", "target": "slow"}]
+        }"#;
+
+        let diagnostics = check(json);
+        assert_eq!(codes(&diagnostics), vec![DiagnosticCode::OutOfRange]);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "warning [node slow]: Tarpit delayMs 60000 is above 10000; 10000 is used"
+        );
+    }
+
+    #[test]
+    fn test_retry_attempts_out_of_range() {
+        let json = r#"{
+            "nodes": [
+                {"id": "req", "type": "request", "position": {"x": 0, "y": 0}, "data": {}},
+                {"id": "origin", "type": "backend", "position": {"x": 100, "y": 0},
+                 "data": {"name": "origin", "host": "origin.example.com", "retry": {"maxAttempts": 4000000000}}},
+                {"id": "pool", "type": "backendPool", "position": {"x": 100, "y": 100},
+                 "data": {"name": "pool", "members": [
+                    {"name": "a", "host": "a.example.com", "retry": {"maxAttempts": 3}},
+                    {"name": "b", "host": "b.example.com", "retry": {"maxAttempts": 0}}
+                 ]}}
+            ],
+            "edges": [{"id": "e1", "source": "req", "target": "origin"}]
+        }"#;
+
+        let diagnostics = check(json);
+        assert_eq!(codes(&diagnostics), vec![DiagnosticCode::OutOfRange; 2]);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "warning [node origin]: Backend origin: maxAttempts 4000000000 is outside 1..=5; 5 is used"
+        );
+        assert_eq!(diagnostics[1].node_id.as_deref(), Some("pool"));
+        assert!(diagnostics[1].message.ends_with("1 is used"));
+    }
+}
@@ -4,9 +4,10 @@ use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 
 /// A value that can be passed between nodes during graph execution.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub enum Value {
     /// No value / null
+    #[default]
     None,
     /// Boolean signal (used for logic gates and conditions)
     Bool(bool),
@@ -50,6 +51,29 @@ impl Value {
         }
     }
 
+    /// Text of each value: the items of a list, none for a missing value,
+    /// otherwise the value itself.
+    pub fn texts(&self) -> Vec<String> {
+        match self {
+            Value::None => Vec::new(),
+            Value::List(items) => items.iter().map(Value::as_text).collect(),
+            other => vec![other.as_text()],
+        }
+    }
+
+    /// Text form used when comparing against editor condition values.
+    /// Missing values become the empty string.
+    pub fn as_text(&self) -> String {
+        match self {
+            Value::None => String::new(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => format_number(*n),
+            Value::String(s) => s.clone(),
+            Value::Ip(ip) => ip.to_string(),
+            Value::List(items) => items.iter().map(Value::as_text).collect::<Vec<_>>().join(","),
+        }
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Value::None => false,
@@ -62,12 +86,6 @@ impl Value {
     }
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::None
-    }
-}
-
 impl From<bool> for Value {
     fn from(b: bool) -> Self {
         Value::Bool(b)
@@ -109,3 +127,13 @@ impl From<IpAddr> for Value {
         Value::Ip(ip)
     }
 }
+
+/// Format a number the way request fields present it: integers without a
+/// trailing ".0" (so an ASN compares equal to "13335").
+pub fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
@@ -6,10 +6,12 @@ use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
 use crate::{
     Graph, Node, NodeKind, GraphError,
-    RequestField, Operator, ConditionValue, ActionType, ChallengeType,
+    RequestField, Operator, ConditionValue, ActionType, ChallengeType, TarpitThen,
     RateLimitMode, RateWindow, NodeCategory,
-    RequestContext, ExecutionState, ExecutionResult, execute,
+    RequestBody, RequestContext, ExecutionState, ExecutionResult, execute, execute_response, from_payload,
+    validate_at, Plan,
 };
+use crate::payload::{BackendNodeData, GraphPayload};
 use std::collections::HashMap;
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -40,6 +42,17 @@ impl WasmGraph {
         Ok(WasmGraph { inner: graph })
     }
 
+    /// Load a graph from the editor's format (React Flow nodes + edges),
+    /// the same JSON that is deployed to the edge.
+    #[wasm_bindgen(js_name = fromPayload)]
+    pub fn from_payload(json: &str) -> Result<WasmGraph, JsError> {
+        let payload: GraphPayload = serde_json::from_str(json)
+            .map_err(|e| JsError::new(&format!("Invalid graph JSON: {}", e)))?;
+        let graph = from_payload(&payload)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(WasmGraph { inner: graph })
+    }
+
     /// Load a graph from RON format.
     #[wasm_bindgen(js_name = fromRon)]
     pub fn from_ron(ron: &str) -> Result<WasmGraph, JsError> {
@@ -104,6 +117,8 @@ impl WasmGraph {
             id: 0, // Will be assigned by add_node
             kind,
             position: (x, y),
+            editor_id: None,
+            active: None,
         };
         Ok(self.inner.add_node(node))
     }
@@ -198,9 +213,10 @@ impl WasmGraph {
 /// Execute a graph against a mock request for preview/testing.
 #[wasm_bindgen(js_name = executeWithMockRequest)]
 pub fn execute_with_mock_request(graph: &WasmGraph) -> Result<String, JsError> {
+    let plan = compile_plan(&graph.inner)?;
     let request = RequestContext::mock();
     let mut state = ExecutionState::new();
-    let result = execute(&graph.inner, &request, &mut state);
+    let result = execute(&plan, &request, &mut state);
     serde_json::to_string(&ExecutionResultJson::from(result))
         .map_err(|e| JsError::new(&e.to_string()))
 }
@@ -212,13 +228,51 @@ pub fn execute_graph(graph: &WasmGraph, request_json: &str) -> Result<String, Js
     let request: RequestContextJson = serde_json::from_str(request_json)
         .map_err(|e| JsError::new(&format!("Invalid request JSON: {}", e)))?;
 
+    let plan = compile_plan(&graph.inner)?;
+    let request: RequestContext = request.into();
     let mut state = ExecutionState::new();
-    let result = execute(&graph.inner, &request.into(), &mut state);
+    let result = execute(&plan, &request, &mut state);
 
     serde_json::to_string(&ExecutionResultJson::from(result))
         .map_err(|e| JsError::new(&e.to_string()))
 }
 
+/// Execute the response phase of a graph (from its Response node).
+/// request_json is as for executeGraph, with response_status and
+/// response_headers describing the origin's response.
+#[wasm_bindgen(js_name = executeResponsePhase)]
+pub fn execute_response_phase(graph: &WasmGraph, request_json: &str) -> Result<String, JsError> {
+    let request: RequestContextJson = serde_json::from_str(request_json)
+        .map_err(|e| JsError::new(&format!("Invalid request JSON: {}", e)))?;
+
+    let plan = compile_plan(&graph.inner)?;
+    let request: RequestContext = request.into();
+    let mut state = ExecutionState::new();
+    let result = execute_response(&plan, &request, &mut state);
+
+    serde_json::to_string(&ExecutionResultJson::from(result))
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Validate an editor graph (the JSON that is deployed to the edge).
+/// Returns a JSON array of diagnostics; empty when the graph is valid.
+/// With `now` (Unix seconds), nodes that have expired are reported too.
+#[wasm_bindgen(js_name = validatePayload)]
+pub fn validate_payload(json: &str, now: Option<f64>) -> Result<String, JsError> {
+    let payload: GraphPayload = serde_json::from_str(json)
+        .map_err(|e| JsError::new(&format!("Invalid graph JSON: {}", e)))?;
+    serde_json::to_string(&validate_at(&payload, now.map(|secs| secs as i64)))
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Compile a graph for execution, reporting every node error at once.
+fn compile_plan(graph: &Graph) -> Result<Plan, JsError> {
+    Plan::compile(graph).map_err(|errors| {
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        JsError::new(&messages.join("; "))
+    })
+}
+
 // JSON-friendly version of RequestContext (IpAddr as string)
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 struct RequestContextJson {
@@ -298,9 +352,19 @@ struct RequestContextJson {
     #[serde(default)]
     pub path: String,
     #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
     pub host: String,
     #[serde(default)]
+    pub scheme: Option<String>,
+    #[serde(default)]
     pub user_agent: String,
+    #[serde(default)]
+    pub pop: Option<String>,
+    #[serde(default)]
+    pub ddos_detected: Option<bool>,
+    #[serde(default)]
+    pub request_id: Option<String>,
 
     // TLS
     #[serde(default)]
@@ -311,6 +375,23 @@ struct RequestContextJson {
     // Headers
     #[serde(default)]
     pub headers: HashMap<String, String>,
+
+    /// Request body, inspected in full
+    #[serde(default)]
+    pub body: Option<String>,
+
+    /// Current time in Unix seconds (the editor fills in its clock)
+    #[serde(default)]
+    pub now: Option<i64>,
+
+    #[serde(default)]
+    pub challenge_passed: bool,
+
+    // Backend response (response phase)
+    #[serde(default)]
+    pub response_status: Option<u16>,
+    #[serde(default)]
+    pub response_headers: HashMap<String, String>,
 }
 
 impl From<RequestContextJson> for RequestContext {
@@ -354,13 +435,24 @@ impl From<RequestContextJson> for RequestContext {
             // Request
             method: json.method,
             path: json.path,
+            query: json.query,
             host: json.host,
+            scheme: json.scheme,
             user_agent: json.user_agent,
+            pop: json.pop,
+            ddos_detected: json.ddos_detected,
+            request_id: json.request_id,
             // TLS
             ja3: json.ja3,
             ja4: json.ja4,
             // Headers
             headers: json.headers,
+            body: json.body.as_deref().map(RequestBody::complete),
+            now: json.now,
+            challenge_passed: json.challenge_passed,
+            // Response
+            response_status: json.response_status,
+            response_headers: json.response_headers,
         }
     }
 }
@@ -372,9 +464,14 @@ enum ExecutionResultJson {
     Allow,
     Block { status_code: u16, message: String },
     Challenge { challenge_type: String },
-    Tarpit { delay_ms: u32 },
-    Log { message: String, severity: String },
+    Tarpit { delay_ms: u32, then: String },
+    Redirect { url: String, status_code: u16, preserve_query: bool },
+    Respond { status_code: u16, content_type: String, headers: Vec<(String, String)>, body: String },
     Forward { backend: String },
+    Route { backend: Box<BackendNodeData> },
+    NoMatch,
+    StepLimitExceeded,
+    FailClosed { reason: String },
 }
 
 impl From<ExecutionResult> for ExecutionResultJson {
@@ -385,17 +482,28 @@ impl From<ExecutionResult> for ExecutionResultJson {
                 ExecutionResultJson::Block { status_code, message }
             }
             ExecutionResult::Challenge { challenge_type } => {
-                ExecutionResultJson::Challenge { challenge_type }
+                ExecutionResultJson::Challenge { challenge_type: format!("{:?}", challenge_type) }
+            }
+            ExecutionResult::Tarpit { delay_ms, then } => {
+                let then = match then {
+                    TarpitThen::Block { .. } => "block",
+                    TarpitThen::Forward => "forward",
+                };
+                ExecutionResultJson::Tarpit { delay_ms, then: then.to_string() }
             }
-            ExecutionResult::Tarpit { delay_ms } => {
-                ExecutionResultJson::Tarpit { delay_ms }
+            ExecutionResult::Redirect { url, status_code, preserve_query } => {
+                ExecutionResultJson::Redirect { url, status_code, preserve_query }
             }
-            ExecutionResult::Log { message, severity } => {
-                ExecutionResultJson::Log { message, severity }
+            ExecutionResult::Respond { status_code, content_type, headers, body } => {
+                ExecutionResultJson::Respond { status_code, content_type, headers, body }
             }
             ExecutionResult::Forward { backend } => {
                 ExecutionResultJson::Forward { backend }
             }
+            ExecutionResult::Route { backend } => ExecutionResultJson::Route { backend },
+            ExecutionResult::NoMatch => ExecutionResultJson::NoMatch,
+            ExecutionResult::StepLimitExceeded => ExecutionResultJson::StepLimitExceeded,
+            ExecutionResult::FailClosed { reason } => ExecutionResultJson::FailClosed { reason },
         }
     }
 }
@@ -559,7 +667,10 @@ pub fn create_challenge_node(challenge_type: &str) -> Result<String, JsError> {
         "captcha" | "Captcha" => ChallengeType::Captcha,
         "interactive" | "Interactive" => ChallengeType::Interactive,
         "non_interactive" | "NonInteractive" => ChallengeType::NonInteractive,
-        _ => return Err(JsError::new("Invalid challenge type. Use: captcha, interactive, or non_interactive")),
+        "proof_of_work" | "ProofOfWork" => ChallengeType::ProofOfWork,
+        _ => return Err(JsError::new(
+            "Invalid challenge type. Use: captcha, interactive, non_interactive, or proof_of_work",
+        )),
     };
 
     let kind = NodeKind::Action {
@@ -598,6 +709,7 @@ pub fn create_rate_limit_node(
         window,
         threshold,
         penalty_ttl_seconds,
+        key: Default::default(),
     };
     Ok(serde_json::to_string(&kind).unwrap())
 }
@@ -22,7 +22,7 @@ import '@xyflow/react/dist/style.css'
 import { BrowserRouter, Routes, Route, useParams, useNavigate } from 'react-router-dom'
 import { Box, Stack, Title, Text, Flex, Pill } from '@fastly/beacon-mantine'
 
-import { ConditionNode, ActionNode, RequestNode, RateLimitNode, TransformNode, BackendNode, LoggingNode, RuleGroupNode, HeaderNode, CacheNode } from './components/nodes'
+import { ConditionNode, ActionNode, RequestNode, ResponseNode, RateLimitNode, TransformNode, BackendNode, BackendPoolNode, LoggingNode, RuleGroupNode, RuleListNode, HeaderNode, CacheNode, RespondNode, SplitNode, SwitchNode } from './components/nodes'
 import { DeletableEdge } from './components/edges'
 import { Sidebar } from './components/Sidebar'
 import { CCHeader } from './components/CCHeader'
@@ -30,15 +30,21 @@ import { toCanonicalGraph } from './types/graph'
 
 const nodeTypes: NodeTypes = {
   request: RequestNode,
+  response: ResponseNode,
   condition: ConditionNode,
   rateLimit: RateLimitNode,
   transform: TransformNode,
   backend: BackendNode,
+  backendPool: BackendPoolNode,
   logging: LoggingNode,
   action: ActionNode,
   ruleGroup: RuleGroupNode,
+  ruleList: RuleListNode,
   header: HeaderNode,
   cache: CacheNode,
+  respond: RespondNode,
+  split: SplitNode,
+  switch: SwitchNode,
 }
 
 const edgeTypes: EdgeTypes = {
@@ -55,6 +61,15 @@ function getDefaultData(type: string) {
       return { operation: 'lowercase', field: 'path' }
     case 'backend':
       return { name: 'origin', host: 'origin.example.com', port: 443, useTLS: true }
+    case 'backendPool':
+      return {
+        name: 'origins',
+        strategy: 'weightedRandom',
+        members: [
+          { name: 'origin-1', host: 'origin-1.example.com', port: 443, useTLS: true, weight: 1 },
+          { name: 'origin-2', host: 'origin-2.example.com', port: 443, useTLS: true, weight: 1 },
+        ],
+      }
     case 'logging':
       return { endpoint: 'bigquery', format: 'json', sampleRate: 100, includeHeaders: true, includeBody: false }
     case 'action':
@@ -67,10 +82,41 @@ function getDefaultData(type: string) {
           { id: 'cond-1', field: 'path', operator: 'equals', value: '/' }
         ]
       }
+    case 'ruleList':
+      return {
+        name: 'waf',
+        rules: [
+          {
+            id: 'block-admin',
+            logic: 'AND',
+            conditions: [{ id: 'cond-1', field: 'path', operator: 'startsWith', value: '/admin' }],
+            action: { action: 'block', statusCode: 403 },
+          },
+        ],
+      }
     case 'header':
       return { operation: 'set', name: 'X-Custom-Header', value: '' }
     case 'cache':
       return { mode: 'configure', ttl: 300, ttlUnit: 'seconds', staleWhileRevalidate: 60, swrUnit: 'seconds' }
+    case 'respond':
+      return { statusCode: 200, contentType: 'text/plain; charset=utf-8', headers: [], body: '' }
+    case 'switch':
+      return {
+        field: 'country',
+        cases: [
+          { id: 'case-eu', match: 'exact', value: 'DE, FR, NL' },
+          { id: 'case-us', match: 'exact', value: 'US' },
+        ],
+      }
+    case 'split':
+      return {
+        name: 'canary',
+        keyBy: 'clientIp',
+        buckets: [
+          { id: 'control', label: 'control', weight: 95 },
+          { id: 'canary', label: 'canary', weight: 5 },
+        ],
+      }
     default:
       return {}
   }
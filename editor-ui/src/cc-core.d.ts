@@ -8,6 +8,7 @@ declare module 'cc-core' {
     toJson(): string
     toRon(): string
     static fromJson(json: string): WasmGraph
+    static fromPayload(json: string): WasmGraph
     static fromRon(ron: string): WasmGraph
   }
 
@@ -22,4 +23,5 @@ declare module 'cc-core' {
   export function createRateLimitNode(mode: string, counter_name: string, window: string, threshold: number, penalty_ttl_seconds: number): string
   export function executeGraph(graph: WasmGraph, request_json: string): string
   export function executeWithMockRequest(graph: WasmGraph): string
+  export function validatePayload(json: string, now?: number): string
 }
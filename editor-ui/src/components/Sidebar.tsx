@@ -47,18 +47,42 @@ const nodeTypes = [
     category: 'input' as const,
     description: 'Starting point for all incoming requests. Connect to conditions or actions.',
   },
+  {
+    type: 'response',
+    label: 'Response',
+    category: 'input' as const,
+    description: 'Runs after the origin replies: set security headers, branch on status, replace error pages.',
+  },
   {
     type: 'condition',
     label: 'Condition',
     category: 'condition' as const,
     description: 'Evaluate request properties like path, IP, headers, geo location, and more.',
   },
+  {
+    type: 'switch',
+    label: 'Switch',
+    category: 'condition' as const,
+    description: 'Branch on one field: an output per value, prefix or CIDR list, plus a default.',
+  },
   {
     type: 'ruleGroup',
     label: 'Rule Group',
     category: 'logic' as const,
     description: 'Combine multiple conditions using AND/OR logic for complex matching.',
   },
+  {
+    type: 'ruleList',
+    label: 'Rule List',
+    category: 'logic' as const,
+    description: 'Ordered WAF rules: the first matching rule takes its action or output.',
+  },
+  {
+    type: 'split',
+    label: 'Split',
+    category: 'logic' as const,
+    description: 'Send a stable percentage of users down each output for canaries and A/B tests.',
+  },
   {
     type: 'rateLimit',
     label: 'Rate Limit',
@@ -77,6 +101,12 @@ const nodeTypes = [
     category: 'routing' as const,
     description: 'Route requests to a specific origin server or backend service.',
   },
+  {
+    type: 'backendPool',
+    label: 'Backend Pool',
+    category: 'routing' as const,
+    description: 'Spread traffic across weighted origins: random, consistent hash, or sticky cookie.',
+  },
   {
     type: 'header',
     label: 'Header',
@@ -101,6 +131,12 @@ const nodeTypes = [
     category: 'action' as const,
     description: 'Terminal action: block with status code, allow through, or challenge.',
   },
+  {
+    type: 'respond',
+    label: 'Respond',
+    category: 'action' as const,
+    description: 'Serve a custom response: block pages, JSON errors, robots.txt, maintenance pages.',
+  },
 ]
 
 const categoryLabels: Record<string, string> = {
@@ -23,7 +23,8 @@ export function TestPanel({ onExecute, isLoaded }: Props) {
 
   const handleExecute = () => {
     try {
-      const ctx = JSON.parse(request)
+      // Clock fields use the browser's clock unless the request sets "now" (Unix seconds)
+      const ctx = { now: Math.floor(Date.now() / 1000), ...JSON.parse(request) }
       const res = onExecute(ctx)
       setResult(JSON.stringify(res, null, 2))
     } catch (err) {
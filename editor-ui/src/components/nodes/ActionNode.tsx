@@ -1,21 +1,36 @@
 import { type NodeProps, useReactFlow } from '@xyflow/react'
 import { useCallback } from 'react'
-import { NodeBase, NodeField, NodeSelect, NodeInput, NodeCheckbox } from './NodeBase'
+import { NodeBase, NodeField, NodeSelect, NodeInput, NodeCheckbox, NodeSchedule } from './NodeBase'
 
 export type ActionNodeData = {
-  action: 'block' | 'allow' | 'challenge' | 'log' | 'redirect'
+  action: 'block' | 'allow' | 'challenge' | 'tarpit' | 'log' | 'redirect'
   statusCode?: number
   message?: string
   // Redirect-specific fields
   url?: string
   preserveQuery?: boolean
+  // Tarpit-specific fields
+  delayMs?: number
+  then?: 'block' | 'forward'
+  // Challenge-specific fields
+  challengeType?: 'javascript' | 'proofOfWork'
+  // Log-specific fields
+  severity?: 'debug' | 'info' | 'warning' | 'error'
+  ruleName?: string
+  fields?: string[]
+  activeFrom?: string
+  activeUntil?: string
 }
 
+// Longest a tarpit holds a request; longer delays are shortened to it
+const MAX_TARPIT_DELAY_MS = 10000
+
 const actionOptions = [
   { value: 'block', label: 'Block' },
   { value: 'allow', label: 'Allow' },
   { value: 'redirect', label: 'Redirect' },
   { value: 'challenge', label: 'Challenge' },
+  { value: 'tarpit', label: 'Tarpit' },
   { value: 'log', label: 'Log Only' },
 ]
 
@@ -26,6 +41,23 @@ const statusCodeOptions = [
   { value: '503', label: '503 Service Unavailable' },
 ]
 
+const challengeTypeOptions = [
+  { value: 'javascript', label: 'JavaScript (non-interactive)' },
+  { value: 'proofOfWork', label: 'Proof of work' },
+]
+
+const tarpitThenOptions = [
+  { value: 'block', label: 'Then block' },
+  { value: 'forward', label: 'Then forward' },
+]
+
+const severityOptions = [
+  { value: 'debug', label: 'Debug' },
+  { value: 'info', label: 'Info' },
+  { value: 'warning', label: 'Warning' },
+  { value: 'error', label: 'Error' },
+]
+
 const redirectStatusOptions = [
   { value: '302', label: '302 Found (Temporary)' },
   { value: '301', label: '301 Moved Permanently' },
@@ -38,7 +70,7 @@ export function ActionNode({ id, data, selected }: NodeProps) {
   const { setNodes } = useReactFlow()
   const action = nodeData.action || 'block'
 
-  const updateData = useCallback((field: string, value: string | number | boolean) => {
+  const updateData = useCallback((field: string, value: string | number | boolean | string[]) => {
     setNodes((nodes) =>
       nodes.map((node) =>
         node.id === id
@@ -54,6 +86,7 @@ export function ActionNode({ id, data, selected }: NodeProps) {
     allow: 'Allow',
     redirect: 'Redirect',
     challenge: 'Challenge',
+    tarpit: 'Tarpit',
     log: 'Log',
   }
 
@@ -63,7 +96,11 @@ export function ActionNode({ id, data, selected }: NodeProps) {
       category="action"
       selected={selected}
       inputs={[{ id: 'trigger', label: 'Trigger', type: 'bool' }]}
-      outputs={[]}
+      outputs={
+        action === 'challenge' ? [{ id: 'passed', label: 'Passed', type: 'bool' }]
+        : action === 'log' ? [{ id: 'next', label: 'Next', type: 'bool' }]
+        : []
+      }
       width={action === 'redirect' ? 260 : 200}
       docUrl="https://docs.fastly.com/en/guides/compute/"
     >
@@ -95,16 +132,92 @@ export function ActionNode({ id, data, selected }: NodeProps) {
         </>
       )}
 
-      {action === 'log' && (
-        <NodeField label="Message">
-          <NodeInput
-            value={nodeData.message || ''}
-            onChange={(v) => updateData('message', v)}
-            placeholder="Log message..."
+      {action === 'tarpit' && (
+        <>
+          <NodeField label={`Delay (ms, at most ${MAX_TARPIT_DELAY_MS})`}>
+            <NodeInput
+              type="number"
+              value={String(nodeData.delayMs ?? 5000)}
+              onChange={(v) => updateData('delayMs', parseInt(v) || 0)}
+            />
+          </NodeField>
+
+          <NodeField label="Then">
+            <NodeSelect
+              value={nodeData.then || 'block'}
+              onChange={(v) => updateData('then', v)}
+              options={tarpitThenOptions}
+            />
+          </NodeField>
+
+          {(nodeData.then || 'block') === 'block' && (
+            <>
+              <NodeField label="Status">
+                <NodeSelect
+                  value={String(nodeData.statusCode || 403)}
+                  onChange={(v) => updateData('statusCode', parseInt(v))}
+                  options={statusCodeOptions}
+                />
+              </NodeField>
+
+              <NodeField label="Message">
+                <NodeInput
+                  value={nodeData.message || ''}
+                  onChange={(v) => updateData('message', v)}
+                  placeholder="Response message..."
+                />
+              </NodeField>
+            </>
+          )}
+        </>
+      )}
+
+      {action === 'challenge' && (
+        <NodeField label="Type">
+          <NodeSelect
+            value={nodeData.challengeType || 'javascript'}
+            onChange={(v) => updateData('challengeType', v)}
+            options={challengeTypeOptions}
           />
         </NodeField>
       )}
 
+      {action === 'log' && (
+        <>
+          <NodeField label="Message">
+            <NodeInput
+              value={nodeData.message || ''}
+              onChange={(v) => updateData('message', v)}
+              placeholder="Log message..."
+            />
+          </NodeField>
+
+          <NodeField label="Severity">
+            <NodeSelect
+              value={nodeData.severity || 'info'}
+              onChange={(v) => updateData('severity', v)}
+              options={severityOptions}
+            />
+          </NodeField>
+
+          <NodeField label="Rule">
+            <NodeInput
+              value={nodeData.ruleName || ''}
+              onChange={(v) => updateData('ruleName', v)}
+              placeholder="Rule name"
+            />
+          </NodeField>
+
+          <NodeField label="Fields">
+            <NodeInput
+              value={(nodeData.fields || []).join(', ')}
+              onChange={(v) => updateData('fields', v.split(',').map((f) => f.trim()))}
+              placeholder="clientIp, userAgent"
+            />
+          </NodeField>
+        </>
+      )}
+
       {action === 'redirect' && (
         <>
           <NodeField label="URL">
@@ -130,6 +243,12 @@ export function ActionNode({ id, data, selected }: NodeProps) {
           />
         </>
       )}
+
+      <NodeSchedule
+        activeFrom={nodeData.activeFrom}
+        activeUntil={nodeData.activeUntil}
+        onChange={updateData}
+      />
     </NodeBase>
   )
 }
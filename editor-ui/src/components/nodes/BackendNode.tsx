@@ -42,6 +42,17 @@ export type BackendNodeData = {
 
   // Edge Auth - HMAC signature added to requests for origin verification
   edgeAuthSecret?: string        // shared secret for Edge-Auth header
+
+  // Failover
+  retry?: RetryPolicy
+  fallback?: Partial<BackendNodeData> // tried once after this backend fails
+}
+
+export type RetryPolicy = {
+  maxAttempts?: number           // including the first, default 1
+  retryOnError?: boolean         // default true
+  retryOnStatus?: number[]       // default [502, 503, 504]
+  idempotentOnly?: boolean       // default true
 }
 
 const tlsVersionOptions = [
@@ -56,7 +67,7 @@ export function BackendNode({ id, data, selected }: NodeProps) {
   const nodeData = data as BackendNodeData
   const { setNodes } = useReactFlow()
 
-  const updateData = useCallback((field: string, value: string | number | boolean) => {
+  const updateData = useCallback((field: string, value: string | number | boolean | RetryPolicy | Partial<BackendNodeData> | undefined) => {
     setNodes((nodes) =>
       nodes.map((node) =>
         node.id === id
@@ -71,6 +82,8 @@ export function BackendNode({ id, data, selected }: NodeProps) {
   const host = nodeData.host || ''
   const port = nodeData.port ?? 443
   const useTLS = nodeData.useTLS ?? true
+  const retry = nodeData.retry || {}
+  const fallback = nodeData.fallback || {}
 
   return (
     <NodeBase
@@ -314,6 +327,45 @@ export function BackendNode({ id, data, selected }: NodeProps) {
           />
         </NodeField>
       </NodeSection>
+
+      {/* Failover Section */}
+      <NodeSection title="Retry & Failover">
+        <NodeField label="Attempts">
+          <NodeInput
+            value={retry.maxAttempts ?? ''}
+            onChange={(v) => updateData('retry', { ...retry, maxAttempts: parseInt(v) || 1 })}
+            type="number"
+            placeholder="1"
+          />
+        </NodeField>
+        <NodeField label="On status">
+          <NodeInput
+            value={(retry.retryOnStatus ?? [502, 503, 504]).join(', ')}
+            onChange={(v) => updateData('retry', {
+              ...retry,
+              retryOnStatus: v.split(',').map((s) => parseInt(s.trim())).filter((n) => !isNaN(n)),
+            })}
+            placeholder="502, 503, 504"
+          />
+        </NodeField>
+        <NodeCheckbox
+          checked={retry.retryOnError ?? true}
+          onChange={(v) => updateData('retry', { ...retry, retryOnError: v })}
+          label="Retry on connection errors"
+        />
+        <NodeCheckbox
+          checked={retry.idempotentOnly ?? true}
+          onChange={(v) => updateData('retry', { ...retry, idempotentOnly: v })}
+          label="Idempotent methods only"
+        />
+        <NodeField label="Fallback" hint="Tried once after the attempts above fail. Requests with a body are never retried.">
+          <NodeInput
+            value={fallback.host || ''}
+            onChange={(v) => updateData('fallback', v ? { ...fallback, name: fallback.name || `${name}_fallback`, host: v } : undefined)}
+            placeholder="standby.example.com"
+          />
+        </NodeField>
+      </NodeSection>
     </NodeBase>
   )
 }
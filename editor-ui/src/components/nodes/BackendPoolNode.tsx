@@ -0,0 +1,178 @@
+import { type NodeProps, useReactFlow } from '@xyflow/react'
+import { useCallback } from 'react'
+import { ActionIcon, Button, Text } from '@fastly/beacon-mantine'
+import { IconAdd, IconClose } from '@fastly/beacon-icons'
+import { NodeBase, NodeField, NodeSelect, NodeInput, NodeCheckbox, NodeSection } from './NodeBase'
+import type { BackendNodeData } from './BackendNode'
+
+// A member is a full backend config plus its share of the traffic
+export type PoolMember = Partial<BackendNodeData> & {
+  weight?: number                // default 1, 0 takes no traffic
+  down?: boolean                 // skipped while set
+}
+
+export type BackendPoolNodeData = {
+  name: string
+  members: PoolMember[]
+  strategy?: 'weightedRandom' | 'hash' | 'cookie'
+  hashKey?: string               // 'clientIp' (default) or 'header'
+  headerName?: string            // when hashKey is 'header'
+  cookieName?: string            // default 'cc_pool'
+}
+
+const strategyOptions = [
+  { value: 'weightedRandom', label: 'Weighted random' },
+  { value: 'hash', label: 'Consistent hash' },
+  { value: 'cookie', label: 'Sticky cookie' },
+]
+
+const hashKeyOptions = [
+  { value: 'clientIp', label: 'Client IP' },
+  { value: 'header', label: 'Header' },
+]
+
+export function BackendPoolNode({ id, data, selected }: NodeProps) {
+  const nodeData = data as BackendPoolNodeData
+  const { setNodes } = useReactFlow()
+
+  const updateData = useCallback((field: string, value: string | PoolMember[] | undefined) => {
+    setNodes((nodes) =>
+      nodes.map((node) =>
+        node.id === id
+          ? { ...node, data: { ...node.data, [field]: value } }
+          : node
+      )
+    )
+  }, [id, setNodes])
+
+  const name = nodeData.name || 'pool'
+  const members = nodeData.members || []
+  const strategy = nodeData.strategy || 'weightedRandom'
+  const hashKey = nodeData.hashKey || 'clientIp'
+
+  const updateMember = (index: number, field: string, value: string | number | boolean) => {
+    updateData('members', members.map((m, i) => (i === index ? { ...m, [field]: value } : m)))
+  }
+
+  const addMember = () => {
+    const n = members.length + 1
+    updateData('members', [...members, { name: `origin-${n}`, host: '', port: 443, useTLS: true, weight: 1 }])
+  }
+
+  const removeMember = (index: number) => {
+    updateData('members', members.filter((_, i) => i !== index))
+  }
+
+  return (
+    <NodeBase
+      title={`Pool: ${name}`}
+      category="routing"
+      selected={selected}
+      inputs={[{ id: 'route', label: 'Route', type: 'bool' }]}
+      outputs={[]}
+      width={260}
+      docUrl="https://docs.fastly.com/en/guides/working-with-hosts"
+    >
+      <NodeField label="Name">
+        <NodeInput value={name} onChange={(v) => updateData('name', v)} placeholder="pool" />
+      </NodeField>
+
+      <NodeField label="Strategy">
+        <NodeSelect value={strategy} onChange={(v) => updateData('strategy', v)} options={strategyOptions} />
+      </NodeField>
+
+      {strategy === 'hash' && (
+        <NodeField label="Hash on">
+          <NodeSelect value={hashKey} onChange={(v) => updateData('hashKey', v)} options={hashKeyOptions} />
+        </NodeField>
+      )}
+
+      {strategy === 'hash' && hashKey === 'header' && (
+        <NodeField label="Header">
+          <NodeInput
+            value={nodeData.headerName || ''}
+            onChange={(v) => updateData('headerName', v)}
+            placeholder="X-User-Id"
+          />
+        </NodeField>
+      )}
+
+      {strategy === 'cookie' && (
+        <NodeField label="Cookie">
+          <NodeInput
+            value={nodeData.cookieName || ''}
+            onChange={(v) => updateData('cookieName', v || undefined)}
+            placeholder="cc_pool"
+          />
+        </NodeField>
+      )}
+
+      {members.map((member, index) => (
+        <NodeSection
+          key={index}
+          title={`${member.name || 'member'} (${member.weight ?? 1})${member.down ? ' - down' : ''}`}
+        >
+          <ActionIcon
+            onClick={() => removeMember(index)}
+            title="Remove member"
+            variant="subtle"
+            size="xs"
+            className="nodrag nopan"
+          >
+            <IconClose width={12} height={12} />
+          </ActionIcon>
+          <NodeField label="Name">
+            <NodeInput value={member.name || ''} onChange={(v) => updateMember(index, 'name', v)} />
+          </NodeField>
+          <NodeField label="Host">
+            <NodeInput
+              value={member.host || ''}
+              onChange={(v) => updateMember(index, 'host', v)}
+              placeholder="eu.origin.example.com"
+            />
+          </NodeField>
+          <NodeField label="Port">
+            <NodeInput
+              value={member.port ?? 443}
+              onChange={(v) => updateMember(index, 'port', parseInt(v) || 443)}
+              type="number"
+            />
+          </NodeField>
+          <NodeField label="Weight">
+            <NodeInput
+              value={member.weight ?? 1}
+              onChange={(v) => updateMember(index, 'weight', Math.max(0, parseInt(v) || 0))}
+              type="number"
+            />
+          </NodeField>
+          <NodeCheckbox
+            checked={member.useTLS ?? true}
+            onChange={(v) => updateMember(index, 'useTLS', v)}
+            label="Use TLS/SSL"
+          />
+          <NodeCheckbox
+            checked={member.down ?? false}
+            onChange={(v) => updateMember(index, 'down', v)}
+            label="Down (take no traffic)"
+          />
+        </NodeSection>
+      ))}
+
+      <Button
+        variant="outline"
+        size="sm"
+        onClick={addMember}
+        leftSection={<IconAdd width={14} height={14} />}
+        className="nodrag nopan"
+      >
+        Add Member
+      </Button>
+
+      {members.length === 0 && (
+        <Text size="xs" className="cc-node-helper-text" style={{ marginTop: '8px' }}>
+          Add at least one member
+        </Text>
+      )}
+    </NodeBase>
+  )
+}
@@ -1,6 +1,6 @@
 import { type NodeProps, useReactFlow } from '@xyflow/react'
 import { useCallback } from 'react'
-import { NodeBase, NodeField, NodeSelect, NodeTextarea, NodeCheckbox, NodeInput } from './NodeBase'
+import { NodeBase, NodeField, NodeSelect, NodeTextarea, NodeCheckbox, NodeInput, NodeSchedule } from './NodeBase'
 
 // Fields that return boolean values - show checkbox instead of text input
 const booleanFields = new Set([
@@ -18,14 +18,30 @@ export type ConditionNodeData = {
   field: string
   operator: string
   value: string
-  headerName?: string  // For custom header field
+  headerName?: string  // Header name; older graphs also name the fields below with it
+  paramName?: string   // Query parameter, cookie or form field
+  pointer?: string     // JSON pointer
+  timeZone?: string    // Time zone of the clock fields
+  activeFrom?: string
+  activeUntil?: string
 }
 
 const fieldOptions = [
   // Request basics
   { value: 'path', label: 'Path' },
   { value: 'query', label: 'Query String' },
+  { value: 'queryParam', label: 'Query Parameter' },
+  { value: 'queryParamCount', label: 'Query Parameter Count' },
+  { value: 'queryParamNames', label: 'Query Parameter Names' },
+  { value: 'cookie', label: 'Cookie' },
   { value: 'method', label: 'Method' },
+
+  // Request body (buffered up to the deployed size cap)
+  { value: 'body', label: 'Body' },
+  { value: 'bodyLength', label: 'Body Length' },
+  { value: 'json', label: 'JSON Body Field' },
+  { value: 'form', label: 'Form Field' },
+
   { value: 'host', label: 'Host' },
   { value: 'scheme', label: 'Scheme (http/https)' },
 
@@ -94,10 +110,36 @@ const fieldOptions = [
   { value: 'ohFingerprint', label: 'OH Fingerprint' },
   { value: 'ddosDetected', label: 'DDoS Detected' },
 
+  // Clock (hour, weekday and date in a time zone; UTC when none is given)
+  { value: 'now', label: 'Current Time (UTC)' },
+  { value: 'hour', label: 'Hour of Day (0-23)' },
+  { value: 'weekday', label: 'Day of Week (Mon-Sun)' },
+  { value: 'today', label: 'Date (YYYY-MM-DD)' },
+
   // Custom header
   { value: 'header', label: 'Custom Header' },
+
+  // Backend response (after a Response node)
+  { value: 'responseStatus', label: 'Response Status' },
+  { value: 'responseHeader', label: 'Response Header' },
 ]
 
+type FieldNameKey = 'headerName' | 'paramName' | 'pointer' | 'timeZone'
+
+// Fields that read a header, query parameter, cookie or body field, and clock
+// fields read in a time zone, with the data key that names it
+const namedFields: Record<string, { key: FieldNameKey; label: string; placeholder: string }> = {
+  header: { key: 'headerName', label: 'Header Name', placeholder: 'X-Custom-Header' },
+  responseHeader: { key: 'headerName', label: 'Header Name', placeholder: 'X-Custom-Header' },
+  queryParam: { key: 'paramName', label: 'Parameter Name', placeholder: 'api_key' },
+  cookie: { key: 'paramName', label: 'Cookie Name', placeholder: 'session_id' },
+  json: { key: 'pointer', label: 'JSON Pointer', placeholder: '/user/name' },
+  form: { key: 'paramName', label: 'Form Field', placeholder: 'username' },
+  hour: { key: 'timeZone', label: 'Time Zone', placeholder: 'UTC, +02:00 or Europe/Berlin' },
+  weekday: { key: 'timeZone', label: 'Time Zone', placeholder: 'UTC, +02:00 or Europe/Berlin' },
+  today: { key: 'timeZone', label: 'Time Zone', placeholder: 'UTC, +02:00 or Europe/Berlin' },
+}
+
 const operatorOptions = [
   // String operators
   { value: 'equals', label: 'equals' },
@@ -117,6 +159,7 @@ const operatorOptions = [
   { value: 'lessThan', label: 'less than' },
   { value: 'greaterOrEqual', label: 'greater or equal' },
   { value: 'lessOrEqual', label: 'less or equal' },
+  { value: 'between', label: 'between' },
 
   // IP operators
   { value: 'inCidr', label: 'in CIDR' },
@@ -139,6 +182,9 @@ function getValueHint(field: string, operator: string): string | undefined {
   if (operator === 'inCidr' || operator === 'notInCidr') {
     return 'e.g., 192.168.0.0/16 or 10.0.0.0/8'
   }
+  if (operator === 'between') {
+    return 'Inclusive: 9..17, Mon..Fri, 2026-12-24..2026-12-26; 22..6 wraps around'
+  }
 
   // Field-specific hints
   switch (field) {
@@ -173,7 +219,7 @@ export function ConditionNode({ id, data, selected }: NodeProps) {
 
   const currentField = nodeData.field || 'path'
   const isBooleanField = booleanFields.has(currentField)
-  const isCustomHeader = currentField === 'header'
+  const nameLabel = namedFields[currentField]
 
   // For boolean fields, handle the checkbox toggle
   const handleBooleanToggle = useCallback((checked: boolean) => {
@@ -220,12 +266,12 @@ export function ConditionNode({ id, data, selected }: NodeProps) {
         />
       </NodeField>
 
-      {isCustomHeader && (
-        <NodeField label="Header Name">
+      {nameLabel && (
+        <NodeField label={nameLabel.label}>
           <NodeInput
-            value={nodeData.headerName || ''}
-            onChange={(v) => updateData('headerName', v)}
-            placeholder="X-Custom-Header"
+            value={nodeData[nameLabel.key] ?? nodeData.headerName ?? ''}
+            onChange={(v) => updateData(nameLabel.key, v)}
+            placeholder={nameLabel.placeholder}
           />
         </NodeField>
       )}
@@ -259,6 +305,12 @@ export function ConditionNode({ id, data, selected }: NodeProps) {
           </NodeField>
         </>
       )}
+
+      <NodeSchedule
+        activeFrom={nodeData.activeFrom}
+        activeUntil={nodeData.activeUntil}
+        onChange={updateData}
+      />
     </NodeBase>
   )
 }
@@ -334,3 +334,34 @@ export function NodeTextarea({
     </Box>
   )
 }
+
+// activeFrom/activeUntil on a node; outside the window the node passes
+// requests through (its false/no-match output)
+export function NodeSchedule({
+  activeFrom,
+  activeUntil,
+  onChange,
+}: {
+  activeFrom?: string
+  activeUntil?: string
+  onChange: (key: 'activeFrom' | 'activeUntil', value: string) => void
+}) {
+  return (
+    <NodeSection title="Schedule" defaultOpen={Boolean(activeFrom || activeUntil)}>
+      <NodeField label="Active from" hint="Date or time, in UTC unless it has an offset">
+        <NodeInput
+          value={activeFrom || ''}
+          onChange={(v) => onChange('activeFrom', v)}
+          placeholder="2026-10-16T08:00:00Z"
+        />
+      </NodeField>
+      <NodeField label="Active until" hint="A date includes the whole day (UTC)">
+        <NodeInput
+          value={activeUntil || ''}
+          onChange={(v) => onChange('activeUntil', v)}
+          placeholder="2026-10-31"
+        />
+      </NodeField>
+    </NodeSection>
+  )
+}
@@ -6,7 +6,7 @@ export type RateLimitNodeData = {
   limit: number
   window: number
   windowUnit: 'second' | 'minute' | 'hour'
-  keyBy: 'ip' | 'fingerprint' | 'header' | 'path'
+  keyBy: string  // 'ip', 'fingerprint', 'header', 'path', 'queryParam:<name>' or 'cookie:<name>'
   headerName?: string
 }
 
@@ -21,6 +21,8 @@ const keyByOptions = [
   { value: 'fingerprint', label: 'JA3 Fingerprint' },
   { value: 'header', label: 'Header Value' },
   { value: 'path', label: 'Request Path' },
+  { value: 'queryParam:', label: 'Query Parameter' },
+  { value: 'cookie:', label: 'Cookie' },
 ]
 
 export function RateLimitNode({ id, data, selected }: NodeProps) {
@@ -40,6 +42,7 @@ export function RateLimitNode({ id, data, selected }: NodeProps) {
   const limit = nodeData.limit || 100
   const windowUnit = nodeData.windowUnit || 'minute'
   const keyBy = nodeData.keyBy || 'ip'
+  const keyKind = keyBy.includes(':') ? keyBy.slice(0, keyBy.indexOf(':') + 1) : keyBy
 
   return (
     <NodeBase
@@ -73,12 +76,22 @@ export function RateLimitNode({ id, data, selected }: NodeProps) {
 
       <NodeField label="Key By" hint="Group requests by this field">
         <NodeSelect
-          value={keyBy}
+          value={keyKind}
           onChange={(v) => updateData('keyBy', v)}
           options={keyByOptions}
         />
       </NodeField>
 
+      {keyKind.endsWith(':') && (
+        <NodeField label="Name" hint={keyKind === 'cookie:' ? 'Cookie to use as key' : 'Query parameter to use as key'}>
+          <NodeInput
+            value={keyBy.slice(keyKind.length)}
+            onChange={(v) => updateData('keyBy', `${keyKind}${v}`)}
+            placeholder={keyKind === 'cookie:' ? 'session_id' : 'api_key'}
+          />
+        </NodeField>
+      )}
+
       {keyBy === 'header' && (
         <NodeField label="Header" hint="Header name to use as key">
           <NodeInput
@@ -0,0 +1,127 @@
+import { type NodeProps, useReactFlow } from '@xyflow/react'
+import { useCallback } from 'react'
+import { Text } from '@fastly/beacon-mantine'
+import { NodeBase, NodeField, NodeSelect, NodeInput, NodeTextarea } from './NodeBase'
+
+export type ResponseHeader = {
+  name: string
+  value: string
+}
+
+export type RespondNodeData = {
+  statusCode?: number
+  contentType?: string
+  headers?: ResponseHeader[]
+  body?: string
+  // Config Store key holding the body (instead of an inline body)
+  bodyKey?: string
+}
+
+const contentTypeOptions = [
+  { value: 'text/plain; charset=utf-8', label: 'Plain text' },
+  { value: 'text/html; charset=utf-8', label: 'HTML' },
+  { value: 'application/json', label: 'JSON' },
+]
+
+const bodySourceOptions = [
+  { value: 'inline', label: 'Inline' },
+  { value: 'configStore', label: 'Config Store key' },
+]
+
+// Headers are edited one per line as "Name: value"
+function headersToText(headers: ResponseHeader[]): string {
+  return headers.map((h) => `${h.name}: ${h.value}`).join('\n')
+}
+
+function textToHeaders(text: string): ResponseHeader[] {
+  return text
+    .split('\n')
+    .map((line) => {
+      const colon = line.indexOf(':')
+      return colon === -1
+        ? { name: line.trim(), value: '' }
+        : { name: line.slice(0, colon).trim(), value: line.slice(colon + 1).trim() }
+    })
+    .filter((h) => h.name !== '')
+}
+
+export function RespondNode({ id, data, selected }: NodeProps) {
+  const nodeData = data as RespondNodeData
+  const { setNodes } = useReactFlow()
+
+  const updateData = useCallback((field: string, value: string | number | ResponseHeader[] | undefined) => {
+    setNodes((nodes) =>
+      nodes.map((node) =>
+        node.id === id
+          ? { ...node, data: { ...node.data, [field]: value } }
+          : node
+      )
+    )
+  }, [id, setNodes])
+
+  const statusCode = nodeData.statusCode ?? 200
+  const bodySource = nodeData.bodyKey !== undefined ? 'configStore' : 'inline'
+
+  return (
+    <NodeBase
+      title={`Respond: ${statusCode}`}
+      category="action"
+      selected={selected}
+      inputs={[{ id: 'trigger', label: 'Trigger', type: 'bool' }]}
+      outputs={[]}
+      width={260}
+      docUrl="https://docs.fastly.com/en/guides/compute/"
+    >
+      <NodeField label="Status">
+        <NodeInput
+          type="number"
+          value={String(statusCode)}
+          onChange={(v) => updateData('statusCode', parseInt(v) || 200)}
+        />
+      </NodeField>
+
+      <NodeField label="Content type">
+        <NodeSelect
+          value={nodeData.contentType || 'text/plain; charset=utf-8'}
+          onChange={(v) => updateData('contentType', v)}
+          options={contentTypeOptions}
+        />
+      </NodeField>
+
+      <NodeField label="Headers" hint="One per line, e.g. Retry-After: 60">
+        <NodeTextarea
+          value={headersToText(nodeData.headers || [])}
+          onChange={(v) => updateData('headers', textToHeaders(v))}
+          placeholder="Retry-After: 60"
+        />
+      </NodeField>
+
+      <NodeField label="Body">
+        <NodeSelect
+          value={bodySource}
+          onChange={(v) => updateData('bodyKey', v === 'configStore' ? '' : undefined)}
+          options={bodySourceOptions}
+        />
+      </NodeField>
+
+      {bodySource === 'inline' ? (
+        <NodeTextarea
+          value={nodeData.body || ''}
+          onChange={(v) => updateData('body', v)}
+          placeholder='{"error":"rate_limited"}'
+          maxRows={8}
+        />
+      ) : (
+        <NodeInput
+          value={nodeData.bodyKey || ''}
+          onChange={(v) => updateData('bodyKey', v)}
+          placeholder="page_maintenance"
+        />
+      )}
+
+      <Text size="xs" className="cc-node-helper-text" style={{ marginTop: '8px' }}>
+        {'Use ${clientIp}, ${requestId}, ${header.name} in the body and header values'}
+      </Text>
+    </NodeBase>
+  )
+}
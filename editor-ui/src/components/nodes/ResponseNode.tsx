@@ -0,0 +1,22 @@
+import { type NodeProps } from '@xyflow/react'
+import { NodeBase } from './NodeBase'
+
+export function ResponseNode({ selected }: NodeProps) {
+  return (
+    <NodeBase
+      title="Response"
+      category="input"
+      selected={selected}
+      inputs={[]}
+      outputs={[
+        { id: 'response', label: 'Response', type: 'geometry' },
+      ]}
+      width={140}
+      docUrl="https://docs.fastly.com/en/guides/compute/"
+    >
+      <div className="cc-node-subtitle">
+        After the origin replies
+      </div>
+    </NodeBase>
+  )
+}
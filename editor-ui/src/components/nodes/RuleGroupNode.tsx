@@ -2,7 +2,7 @@ import { Handle, Position, type NodeProps, useReactFlow } from '@xyflow/react'
 import { useCallback, useState } from 'react'
 import { Box, Flex, Text, Pill, Button, TextInput, Switch, ActionIcon } from '@fastly/beacon-mantine'
 import { IconHelp, IconClose, IconAdd } from '@fastly/beacon-icons'
-import { NodeSelect, NodeInput } from './NodeBase'
+import { NodeSelect, NodeInput, NodeSchedule } from './NodeBase'
 
 // Fields that return boolean values - show checkbox instead of text input
 const booleanFields = new Set([
@@ -17,26 +17,54 @@ const booleanFields = new Set([
 ])
 
 // Condition definition (embedded in the group)
-type ConditionDef = {
+export type ConditionDef = {
   id: string
   field: string
   operator: string
   value: string
-  headerName?: string  // For custom header field
+  headerName?: string  // Header name; older graphs also name the fields below with it
+  paramName?: string   // Query parameter, cookie or form field
+  pointer?: string     // JSON pointer
+  timeZone?: string    // Time zone of the clock fields
+  negate?: boolean     // Match when the condition does not hold
+}
+
+export type GroupLogic = 'AND' | 'OR' | 'NOT'
+
+// Nested group, combined with the other conditions under the parent's logic
+export type ConditionGroupDef = {
+  id: string
+  logic: GroupLogic
+  conditions: ConditionDef[]
+  groups?: ConditionGroupDef[]
 }
 
 export type RuleGroupNodeData = {
   name: string
-  logic: 'AND' | 'OR'
+  logic: GroupLogic
   conditions: ConditionDef[]
+  groups?: ConditionGroupDef[]
   collapsed?: boolean
+  activeFrom?: string
+  activeUntil?: string
 }
 
-const fieldOptions = [
+export const fieldOptions = [
   // Request basics
   { value: 'path', label: 'Path' },
   { value: 'query', label: 'Query String' },
+  { value: 'queryParam', label: 'Query Parameter' },
+  { value: 'queryParamCount', label: 'Query Parameter Count' },
+  { value: 'queryParamNames', label: 'Query Parameter Names' },
+  { value: 'cookie', label: 'Cookie' },
   { value: 'method', label: 'Method' },
+
+  // Request body
+  { value: 'body', label: 'Body' },
+  { value: 'bodyLength', label: 'Body Length' },
+  { value: 'json', label: 'JSON Body Field' },
+  { value: 'form', label: 'Form Field' },
+
   { value: 'host', label: 'Host' },
   { value: 'scheme', label: 'Scheme' },
 
@@ -72,11 +100,49 @@ const fieldOptions = [
   { value: 'ja3', label: 'JA3' },
   { value: 'ja4', label: 'JA4' },
 
+  // Clock
+  { value: 'now', label: 'Time (UTC)' },
+  { value: 'hour', label: 'Hour' },
+  { value: 'weekday', label: 'Weekday' },
+  { value: 'today', label: 'Date' },
+
   // Custom Header
   { value: 'header', label: 'Custom Header' },
 ]
 
-const operatorOptions = [
+// Clock fields read in the time zone named by timeZone (UTC when empty)
+export const clockFields = new Set(['hour', 'weekday', 'today'])
+
+type FieldNameKey = 'headerName' | 'paramName' | 'pointer' | 'timeZone'
+
+// Fields that read a header, query parameter, cookie or body field, with the
+// data key that names it
+export const namedFields: Record<string, FieldNameKey> = {
+  header: 'headerName',
+  queryParam: 'paramName',
+  cookie: 'paramName',
+  json: 'pointer',
+  form: 'paramName',
+  hour: 'timeZone',
+  weekday: 'timeZone',
+  today: 'timeZone',
+}
+
+// Name given to a condition's field; older graphs keep it in headerName
+export const fieldName = (cond: ConditionDef) => {
+  const key = namedFields[cond.field]
+  return (key ? cond[key] : undefined) ?? cond.headerName ?? ''
+}
+
+// Changes naming a condition's field
+export const fieldNameChanges = (field: string, name: string): Partial<ConditionDef> => {
+  const changes: Partial<ConditionDef> = {}
+  const key = namedFields[field]
+  if (key) changes[key] = name
+  return changes
+}
+
+export const operatorOptions = [
   { value: 'equals', label: '=' },
   { value: 'notEquals', label: '!=' },
   { value: 'contains', label: 'contains' },
@@ -88,8 +154,122 @@ const operatorOptions = [
   { value: 'notIn', label: '!in' },
   { value: 'inCidr', label: 'in CIDR' },
   { value: 'notInCidr', label: 'not in CIDR' },
+  { value: 'between', label: 'between' },
+]
+
+const logicOptions = [
+  { value: 'AND', label: 'AND' },
+  { value: 'OR', label: 'OR' },
+  { value: 'NOT', label: 'NOT' },
 ]
 
+const logicHints: Record<GroupLogic, string> = {
+  AND: 'All conditions must match',
+  OR: 'Any condition must match',
+  NOT: 'No condition may match',
+}
+
+const newCondition = (): ConditionDef => ({
+  id: `cond-${Date.now()}`,
+  field: 'path',
+  operator: 'equals',
+  value: '',
+})
+
+// Compact editor for a nested group and, recursively, its own sub-groups
+function SubGroupEditor({ group, onChange, onRemove }: {
+  group: ConditionGroupDef
+  onChange: (group: ConditionGroupDef) => void
+  onRemove: () => void
+}) {
+  const groups = group.groups || []
+
+  const updateCondition = (condId: string, changes: Partial<ConditionDef>) => {
+    onChange({ ...group, conditions: group.conditions.map((c) => (c.id === condId ? { ...c, ...changes } : c)) })
+  }
+
+  const updateGroup = (groupId: string, changed: ConditionGroupDef) => {
+    onChange({ ...group, groups: groups.map((g) => (g.id === groupId ? changed : g)) })
+  }
+
+  return (
+    <Box className="cc-rule-group-subgroup">
+      <Flex gap="xs" align="center">
+        <NodeSelect
+          value={group.logic}
+          onChange={(v) => onChange({ ...group, logic: v as GroupLogic })}
+          options={logicOptions}
+        />
+        <Text size="xs" className="cc-rule-group-logic-hint">{logicHints[group.logic]}</Text>
+        <ActionIcon onClick={onRemove} title="Remove group" variant="subtle" size="xs" className="nodrag nopan">
+          <IconClose width={12} height={12} />
+        </ActionIcon>
+      </Flex>
+
+      {group.conditions.map((cond) => (
+        <Flex key={cond.id} gap="xs" align="center">
+          <Switch
+            checked={cond.negate ?? false}
+            onChange={(e) => updateCondition(cond.id, { negate: e.currentTarget.checked })}
+            label="NOT"
+            size="xs"
+            className="nodrag nopan"
+          />
+          <NodeSelect value={cond.field} onChange={(v) => updateCondition(cond.id, { field: v })} options={fieldOptions} />
+          {namedFields[cond.field] && (
+            <NodeInput
+              value={fieldName(cond)}
+              onChange={(v) => updateCondition(cond.id, fieldNameChanges(cond.field, v))}
+              placeholder={clockFields.has(cond.field) ? 'UTC' : 'Name'}
+            />
+          )}
+          <NodeSelect value={cond.operator} onChange={(v) => updateCondition(cond.id, { operator: v })} options={operatorOptions} />
+          <NodeInput value={cond.value} onChange={(v) => updateCondition(cond.id, { value: v })} />
+          <ActionIcon
+            onClick={() => onChange({ ...group, conditions: group.conditions.filter((c) => c.id !== cond.id) })}
+            title="Remove condition"
+            variant="subtle"
+            size="xs"
+            className="nodrag nopan"
+          >
+            <IconClose width={12} height={12} />
+          </ActionIcon>
+        </Flex>
+      ))}
+
+      {groups.map((sub) => (
+        <SubGroupEditor
+          key={sub.id}
+          group={sub}
+          onChange={(changed) => updateGroup(sub.id, changed)}
+          onRemove={() => onChange({ ...group, groups: groups.filter((g) => g.id !== sub.id) })}
+        />
+      ))}
+
+      <Flex gap="xs">
+        <Button
+          variant="subtle"
+          size="xs"
+          onClick={() => onChange({ ...group, conditions: [...group.conditions, newCondition()] })}
+          leftSection={<IconAdd width={12} height={12} />}
+          className="nodrag nopan"
+        >
+          Condition
+        </Button>
+        <Button
+          variant="subtle"
+          size="xs"
+          onClick={() => onChange({ ...group, groups: [...groups, { id: `group-${Date.now()}`, logic: 'AND', conditions: [newCondition()] }] })}
+          leftSection={<IconAdd width={12} height={12} />}
+          className="nodrag nopan"
+        >
+          Group
+        </Button>
+      </Flex>
+    </Box>
+  )
+}
+
 const HANDLE_SIZE = 12
 const HEADER_HEIGHT = 44  // Header with padding + content
 const PORT_ROW_HEIGHT = 22  // Height of each port label row
@@ -100,6 +280,7 @@ export function RuleGroupNode({ id, data, selected }: NodeProps) {
   const [collapsed, setCollapsed] = useState(nodeData.collapsed ?? false)
 
   const conditions = nodeData.conditions || []
+  const groups = nodeData.groups || []
   const logic = nodeData.logic || 'AND'
   const name = nodeData.name || 'Rule Group'
 
@@ -114,15 +295,14 @@ export function RuleGroupNode({ id, data, selected }: NodeProps) {
   }, [id, setNodes])
 
   const addCondition = useCallback(() => {
-    const newCondition: ConditionDef = {
-      id: `cond-${Date.now()}`,
-      field: 'path',
-      operator: 'equals',
-      value: '',
-    }
-    updateData({ conditions: [...conditions, newCondition] })
+    updateData({ conditions: [...conditions, newCondition()] })
   }, [conditions, updateData])
 
+  const addGroup = useCallback(() => {
+    const group: ConditionGroupDef = { id: `group-${Date.now()}`, logic: 'AND', conditions: [newCondition()] }
+    updateData({ groups: [...groups, group] })
+  }, [groups, updateData])
+
   const updateCondition = useCallback((condId: string, field: string, value: string) => {
     updateData({
       conditions: conditions.map((c) =>
@@ -166,11 +346,8 @@ export function RuleGroupNode({ id, data, selected }: NodeProps) {
           <Box className="cc-rule-group-logic-select" onClick={(e) => e.stopPropagation()}>
             <NodeSelect
               value={logic}
-              onChange={(v) => updateData({ logic: v as 'AND' | 'OR' })}
-              options={[
-                { value: 'AND', label: 'AND' },
-                { value: 'OR', label: 'OR' },
-              ]}
+              onChange={(v) => updateData({ logic: v as GroupLogic })}
+              options={logicOptions}
             />
           </Box>
           <a
@@ -242,7 +419,7 @@ export function RuleGroupNode({ id, data, selected }: NodeProps) {
         <Box className="cc-rule-group-body">
           {/* Logic indicator */}
           <Text size="xs" className="cc-rule-group-logic-hint">
-            {logic === 'AND' ? 'All conditions must match' : 'Any condition must match'}
+            {logicHints[logic]}
           </Text>
 
           {/* Condition cards */}
@@ -272,6 +449,22 @@ export function RuleGroupNode({ id, data, selected }: NodeProps) {
 
                 {/* Condition fields - row layout with labels */}
                 <Flex className="cc-rule-group-condition-fields" gap="xs" align="flex-end">
+                  <Box className="cc-rule-group-field cc-rule-group-field--negate">
+                    <Text size="xs" className="cc-rule-group-field-label">NOT</Text>
+                    <Switch
+                      checked={condition.negate ?? false}
+                      onChange={(e) => {
+                        updateData({
+                          conditions: conditions.map((c) =>
+                            c.id === condition.id ? { ...c, negate: e.currentTarget.checked } : c
+                          ),
+                        })
+                      }}
+                      size="xs"
+                      className="nodrag nopan"
+                    />
+                  </Box>
+
                   <Box className="cc-rule-group-field cc-rule-group-field--field">
                     <Text size="xs" className="cc-rule-group-field-label">Field</Text>
                     <NodeSelect
@@ -294,21 +487,23 @@ export function RuleGroupNode({ id, data, selected }: NodeProps) {
                     />
                   </Box>
 
-                  {condition.field === 'header' && (
+                  {namedFields[condition.field] && (
                     <Box className="cc-rule-group-field cc-rule-group-field--header">
-                      <Text size="xs" className="cc-rule-group-field-label">Header</Text>
+                      <Text size="xs" className="cc-rule-group-field-label">
+                        {clockFields.has(condition.field) ? 'Time Zone' : 'Name'}
+                      </Text>
                       <NodeInput
-                        value={condition.headerName || ''}
+                        value={fieldName(condition)}
                         onChange={(v) => {
                           updateData({
                             conditions: conditions.map((c) =>
                               c.id === condition.id
-                                ? { ...c, headerName: v }
+                                ? { ...c, ...fieldNameChanges(condition.field, v) }
                                 : c
                             ),
                           })
                         }}
-                        placeholder="Name"
+                        placeholder={clockFields.has(condition.field) ? 'UTC' : 'Name'}
                       />
                     </Box>
                   )}
@@ -359,6 +554,22 @@ export function RuleGroupNode({ id, data, selected }: NodeProps) {
             ))}
           </Box>
 
+          {/* Nested groups */}
+          {groups.map((group, idx) => (
+            <Box key={group.id} className="cc-rule-group-subgroup-slot">
+              {(idx > 0 || conditions.length > 0) && (
+                <Box className="cc-rule-group-logic-connector">
+                  <Pill variant="default">{logic}</Pill>
+                </Box>
+              )}
+              <SubGroupEditor
+                group={group}
+                onChange={(changed) => updateData({ groups: groups.map((g) => (g.id === group.id ? changed : g)) })}
+                onRemove={() => updateData({ groups: groups.filter((g) => g.id !== group.id) })}
+              />
+            </Box>
+          ))}
+
           {/* Add condition button */}
           <Button
             variant="outline"
@@ -369,6 +580,21 @@ export function RuleGroupNode({ id, data, selected }: NodeProps) {
           >
             Add Condition
           </Button>
+          <Button
+            variant="subtle"
+            size="sm"
+            onClick={addGroup}
+            leftSection={<IconAdd width={14} height={14} />}
+            className="cc-rule-group-add-btn nodrag nopan"
+          >
+            Add Group
+          </Button>
+
+          <NodeSchedule
+            activeFrom={nodeData.activeFrom}
+            activeUntil={nodeData.activeUntil}
+            onChange={(key, value) => updateData({ [key]: value })}
+          />
         </Box>
       )}
     </Box>
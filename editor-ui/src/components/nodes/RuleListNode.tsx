@@ -0,0 +1,222 @@
+import { type NodeProps, useReactFlow } from '@xyflow/react'
+import { useCallback } from 'react'
+import { ActionIcon, Button, Flex, Text } from '@fastly/beacon-mantine'
+import { IconAdd, IconClose } from '@fastly/beacon-icons'
+import { NodeBase, NodeField, NodeSelect, NodeInput, NodeSection } from './NodeBase'
+import {
+  type ConditionDef,
+  clockFields,
+  fieldName,
+  fieldNameChanges,
+  fieldOptions,
+  namedFields,
+  operatorOptions,
+} from './RuleGroupNode'
+
+// Inline action, same shape as an action node's data
+export type RuleAction = {
+  action: 'allow' | 'block' | 'challenge' | 'log'
+  statusCode?: number
+  message?: string
+}
+
+export type RuleListRule = {
+  id: string                     // output handle, logged when the rule matches
+  logic?: 'AND' | 'OR' | 'NOT'   // default 'AND'
+  conditions: ConditionDef[]
+  action?: RuleAction            // without one the rule continues on its output
+}
+
+export type RuleListNodeData = {
+  name?: string
+  rules: RuleListRule[]
+}
+
+const logicOptions = [
+  { value: 'AND', label: 'All match (AND)' },
+  { value: 'OR', label: 'Any matches (OR)' },
+  { value: 'NOT', label: 'None match (NOT)' },
+]
+
+const outcomeOptions = [
+  { value: 'output', label: 'Continue on output' },
+  { value: 'allow', label: 'Allow' },
+  { value: 'block', label: 'Block' },
+  { value: 'challenge', label: 'Challenge' },
+  { value: 'log', label: 'Log, then output' },
+]
+
+export function RuleListNode({ id, data, selected }: NodeProps) {
+  const nodeData = data as RuleListNodeData
+  const { setNodes } = useReactFlow()
+
+  const updateData = useCallback((field: string, value: string | RuleListRule[]) => {
+    setNodes((nodes) =>
+      nodes.map((node) =>
+        node.id === id
+          ? { ...node, data: { ...node.data, [field]: value } }
+          : node
+      )
+    )
+  }, [id, setNodes])
+
+  const name = nodeData.name || 'Rules'
+  const rules = nodeData.rules || []
+
+  const updateRule = (index: number, changes: Partial<RuleListRule>) => {
+    updateData('rules', rules.map((r, i) => (i === index ? { ...r, ...changes } : r)))
+  }
+
+  const updateCondition = (index: number, condId: string, changes: Partial<ConditionDef>) => {
+    updateRule(index, {
+      conditions: rules[index].conditions.map((c) => (c.id === condId ? { ...c, ...changes } : c)),
+    })
+  }
+
+  const addRule = () => {
+    const rule: RuleListRule = {
+      id: `rule-${rules.length + 1}`,
+      logic: 'AND',
+      conditions: [{ id: `cond-${Date.now()}`, field: 'path', operator: 'equals', value: '' }],
+    }
+    updateData('rules', [...rules, rule])
+  }
+
+  const moveRule = (index: number, offset: number) => {
+    const target = index + offset
+    if (target < 0 || target >= rules.length) return
+    const reordered = [...rules]
+    ;[reordered[index], reordered[target]] = [reordered[target], reordered[index]]
+    updateData('rules', reordered)
+  }
+
+  // Rules with a terminal inline action have no output to connect
+  const outputs = rules
+    .filter((r) => !r.action || r.action.action === 'log' || r.action.action === 'challenge')
+    .map((r) => ({ id: r.id, label: r.id, type: 'bool' as const }))
+
+  return (
+    <NodeBase
+      title={name}
+      category="condition"
+      selected={selected}
+      inputs={[{ id: 'trigger', label: 'Trigger', type: 'bool' }]}
+      outputs={[...outputs, { id: 'noMatch', label: 'No Match', type: 'bool' }]}
+      width={320}
+    >
+      <NodeField label="Name">
+        <NodeInput value={nodeData.name || ''} onChange={(v) => updateData('name', v)} placeholder="waf" />
+      </NodeField>
+
+      {rules.map((rule, index) => (
+        <NodeSection key={index} title={`${index + 1}. ${rule.id}`} defaultOpen={rules.length <= 3}>
+          <Flex gap="xs" align="center">
+            <Button variant="subtle" size="xs" onClick={() => moveRule(index, -1)} className="nodrag nopan">Up</Button>
+            <Button variant="subtle" size="xs" onClick={() => moveRule(index, 1)} className="nodrag nopan">Down</Button>
+            <ActionIcon
+              onClick={() => updateData('rules', rules.filter((_, i) => i !== index))}
+              title="Remove rule"
+              variant="subtle"
+              size="xs"
+              className="nodrag nopan"
+            >
+              <IconClose width={12} height={12} />
+            </ActionIcon>
+          </Flex>
+
+          <NodeField label="Rule id">
+            <NodeInput value={rule.id} onChange={(v) => updateRule(index, { id: v })} />
+          </NodeField>
+
+          <NodeField label="Logic">
+            <NodeSelect
+              value={rule.logic || 'AND'}
+              onChange={(v) => updateRule(index, { logic: v as RuleListRule['logic'] })}
+              options={logicOptions}
+            />
+          </NodeField>
+
+          {rule.conditions.map((cond) => (
+            <Flex key={cond.id} gap="xs" align="flex-end">
+              <NodeSelect value={cond.field} onChange={(v) => updateCondition(index, cond.id, { field: v })} options={fieldOptions} />
+              {namedFields[cond.field] && (
+                <NodeInput
+                  value={fieldName(cond)}
+                  onChange={(v) => updateCondition(index, cond.id, fieldNameChanges(cond.field, v))}
+                  placeholder={clockFields.has(cond.field) ? 'UTC' : 'Name'}
+                />
+              )}
+              <NodeSelect value={cond.operator} onChange={(v) => updateCondition(index, cond.id, { operator: v })} options={operatorOptions} />
+              <NodeInput value={cond.value} onChange={(v) => updateCondition(index, cond.id, { value: v })} />
+              <ActionIcon
+                onClick={() => updateRule(index, { conditions: rule.conditions.filter((c) => c.id !== cond.id) })}
+                title="Remove condition"
+                variant="subtle"
+                size="xs"
+                className="nodrag nopan"
+              >
+                <IconClose width={12} height={12} />
+              </ActionIcon>
+            </Flex>
+          ))}
+
+          <Button
+            variant="subtle"
+            size="xs"
+            onClick={() => updateRule(index, {
+              conditions: [...rule.conditions, { id: `cond-${Date.now()}`, field: 'path', operator: 'equals', value: '' }],
+            })}
+            leftSection={<IconAdd width={12} height={12} />}
+            className="nodrag nopan"
+          >
+            Add Condition
+          </Button>
+
+          <NodeField label="Then">
+            <NodeSelect
+              value={rule.action?.action || 'output'}
+              onChange={(v) => updateRule(index, {
+                action: v === 'output' ? undefined : { action: v as RuleAction['action'], statusCode: v === 'block' ? 403 : undefined },
+              })}
+              options={outcomeOptions}
+            />
+          </NodeField>
+
+          {rule.action?.action === 'block' && (
+            <NodeField label="Status">
+              <NodeInput
+                type="number"
+                value={rule.action.statusCode ?? 403}
+                onChange={(v) => updateRule(index, { action: { ...rule.action!, statusCode: parseInt(v) || 403 } })}
+              />
+            </NodeField>
+          )}
+
+          {(rule.action?.action === 'block' || rule.action?.action === 'log') && (
+            <NodeField label="Message">
+              <NodeInput
+                value={rule.action.message || ''}
+                onChange={(v) => updateRule(index, { action: { ...rule.action!, message: v } })}
+                placeholder="Blocked by ${clientIp}"
+              />
+            </NodeField>
+          )}
+        </NodeSection>
+      ))}
+
+      <Button
+        variant="outline"
+        size="sm"
+        onClick={addRule}
+        leftSection={<IconAdd width={14} height={14} />}
+        className="nodrag nopan"
+      >
+        Add Rule
+      </Button>
+
+      <Text size="xs" className="cc-node-helper-text" style={{ marginTop: '8px' }}>
+        Rules are checked top to bottom; the first match wins
+      </Text>
+    </NodeBase>
+  )
+}
@@ -0,0 +1,155 @@
+import { type NodeProps, useReactFlow } from '@xyflow/react'
+import { useCallback } from 'react'
+import { ActionIcon, Button, Flex, Text } from '@fastly/beacon-mantine'
+import { IconAdd, IconClose } from '@fastly/beacon-icons'
+import { NodeBase, NodeField, NodeSelect, NodeInput } from './NodeBase'
+
+export type SplitBucket = {
+  id: string                     // output handle
+  label?: string                 // logged name, defaults to the id
+  weight: number                 // relative share, e.g. 95 and 5
+}
+
+export type SplitNodeData = {
+  name?: string                  // logged with the bucket, default 'split'
+  buckets: SplitBucket[]
+  keyBy?: string                 // 'clientIp' (default), 'header', 'cookie' or 'var:<name>'
+  headerName?: string            // when keyBy is 'header'
+  cookieName?: string            // when keyBy is 'cookie'
+}
+
+const keyByOptions = [
+  { value: 'clientIp', label: 'Client IP' },
+  { value: 'header', label: 'Header' },
+  { value: 'cookie', label: 'Cookie' },
+  { value: 'var', label: 'Variable' },
+]
+
+export function SplitNode({ id, data, selected }: NodeProps) {
+  const nodeData = data as SplitNodeData
+  const { setNodes } = useReactFlow()
+
+  const updateData = useCallback((field: string, value: string | SplitBucket[] | undefined) => {
+    setNodes((nodes) =>
+      nodes.map((node) =>
+        node.id === id
+          ? { ...node, data: { ...node.data, [field]: value } }
+          : node
+      )
+    )
+  }, [id, setNodes])
+
+  const name = nodeData.name || 'split'
+  const buckets = nodeData.buckets || []
+  const keyBy = nodeData.keyBy || 'clientIp'
+  const keyKind = keyBy.startsWith('var:') ? 'var' : keyBy
+  const total = buckets.reduce((sum, b) => sum + (b.weight || 0), 0)
+  const percent = (weight: number) => (total > 0 ? Math.round((weight / total) * 1000) / 10 : 0)
+
+  const updateBucket = (index: number, field: string, value: string | number) => {
+    updateData('buckets', buckets.map((b, i) => (i === index ? { ...b, [field]: value } : b)))
+  }
+
+  const addBucket = () => {
+    updateData('buckets', [...buckets, { id: `bucket-${Date.now()}`, label: `bucket ${buckets.length + 1}`, weight: 0 }])
+  }
+
+  const removeBucket = (index: number) => {
+    updateData('buckets', buckets.filter((_, i) => i !== index))
+  }
+
+  return (
+    <NodeBase
+      title={`Split: ${name}`}
+      category="logic"
+      selected={selected}
+      inputs={[{ id: 'trigger', label: 'Trigger', type: 'bool' }]}
+      outputs={buckets.map((b) => ({
+        id: b.id,
+        label: `${b.label || b.id} (${percent(b.weight || 0)}%)`,
+        type: 'bool' as const,
+      }))}
+      width={260}
+    >
+      <NodeField label="Name">
+        <NodeInput value={name} onChange={(v) => updateData('name', v)} placeholder="split" />
+      </NodeField>
+
+      <NodeField label="Bucket by">
+        <NodeSelect
+          value={keyKind}
+          onChange={(v) => updateData('keyBy', v === 'var' ? 'var:' : v)}
+          options={keyByOptions}
+        />
+      </NodeField>
+
+      {keyKind === 'header' && (
+        <NodeField label="Header">
+          <NodeInput
+            value={nodeData.headerName || ''}
+            onChange={(v) => updateData('headerName', v)}
+            placeholder="X-User-Id"
+          />
+        </NodeField>
+      )}
+
+      {keyKind === 'cookie' && (
+        <NodeField label="Cookie">
+          <NodeInput
+            value={nodeData.cookieName || ''}
+            onChange={(v) => updateData('cookieName', v)}
+            placeholder="session_id"
+          />
+        </NodeField>
+      )}
+
+      {keyKind === 'var' && (
+        <NodeField label="Variable">
+          <NodeInput
+            value={keyBy.slice('var:'.length)}
+            onChange={(v) => updateData('keyBy', `var:${v}`)}
+            placeholder="user_id"
+          />
+        </NodeField>
+      )}
+
+      {buckets.map((bucket, index) => (
+        <Flex key={bucket.id} gap="xs" align="flex-end">
+          <NodeField label="Bucket">
+            <NodeInput value={bucket.label ?? bucket.id} onChange={(v) => updateBucket(index, 'label', v)} />
+          </NodeField>
+          <NodeField label="Weight">
+            <NodeInput
+              value={bucket.weight ?? 0}
+              onChange={(v) => updateBucket(index, 'weight', Math.max(0, parseInt(v) || 0))}
+              type="number"
+            />
+          </NodeField>
+          <ActionIcon
+            onClick={() => removeBucket(index)}
+            title="Remove bucket"
+            variant="subtle"
+            size="xs"
+            className="nodrag nopan"
+          >
+            <IconClose width={12} height={12} />
+          </ActionIcon>
+        </Flex>
+      ))}
+
+      <Button
+        variant="outline"
+        size="sm"
+        onClick={addBucket}
+        leftSection={<IconAdd width={14} height={14} />}
+        className="nodrag nopan"
+      >
+        Add Bucket
+      </Button>
+
+      <Text size="xs" className="cc-node-helper-text" style={{ marginTop: '8px' }}>
+        Users without the key are bucketed by client IP
+      </Text>
+    </NodeBase>
+  )
+}
@@ -0,0 +1,135 @@
+import { type NodeProps, useReactFlow } from '@xyflow/react'
+import { useCallback } from 'react'
+import { ActionIcon, Button, Flex } from '@fastly/beacon-mantine'
+import { IconAdd, IconClose } from '@fastly/beacon-icons'
+import { NodeBase, NodeField, NodeSelect, NodeInput } from './NodeBase'
+
+export type SwitchCase = {
+  id: string                     // output handle
+  match?: 'exact' | 'prefix' | 'cidr'  // default 'exact'
+  value: string                  // comma-separated values
+}
+
+export type SwitchNodeData = {
+  field: string
+  headerName?: string            // when field is 'header'
+  cases: SwitchCase[]
+}
+
+const fieldOptions = [
+  { value: 'country', label: 'Country Code (2-letter)' },
+  { value: 'continent', label: 'Continent' },
+  { value: 'region', label: 'Region (ISO 3166-2)' },
+  { value: 'asn', label: 'ASN' },
+  { value: 'datacenter', label: 'Datacenter (POP)' },
+  { value: 'clientIp', label: 'Client IP' },
+  { value: 'host', label: 'Host' },
+  { value: 'path', label: 'Path' },
+  { value: 'method', label: 'Method' },
+  { value: 'header', label: 'Custom Header' },
+]
+
+const matchOptions = [
+  { value: 'exact', label: 'Exact' },
+  { value: 'prefix', label: 'Prefix' },
+  { value: 'cidr', label: 'CIDR' },
+]
+
+export function SwitchNode({ id, data, selected }: NodeProps) {
+  const nodeData = data as SwitchNodeData
+  const { setNodes } = useReactFlow()
+
+  const updateData = useCallback((field: string, value: string | SwitchCase[]) => {
+    setNodes((nodes) =>
+      nodes.map((node) =>
+        node.id === id
+          ? { ...node, data: { ...node.data, [field]: value } }
+          : node
+      )
+    )
+  }, [id, setNodes])
+
+  const field = nodeData.field || 'country'
+  const cases = nodeData.cases || []
+  const fieldLabel = field === 'header'
+    ? nodeData.headerName || 'Header'
+    : fieldOptions.find((f) => f.value === field)?.label || field
+
+  const updateCase = (index: number, key: string, value: string) => {
+    updateData('cases', cases.map((c, i) => (i === index ? { ...c, [key]: value } : c)))
+  }
+
+  const addCase = () => {
+    updateData('cases', [...cases, { id: `case-${Date.now()}`, match: 'exact', value: '' }])
+  }
+
+  const removeCase = (index: number) => {
+    updateData('cases', cases.filter((_, i) => i !== index))
+  }
+
+  return (
+    <NodeBase
+      title={`Switch: ${fieldLabel}`}
+      category="condition"
+      selected={selected}
+      inputs={[{ id: 'trigger', label: 'Trigger', type: 'bool' }]}
+      outputs={[
+        ...cases.map((c) => ({ id: c.id, label: c.value || '(empty)', type: 'bool' as const })),
+        { id: 'default', label: 'Default', type: 'bool' as const },
+      ]}
+      width={280}
+    >
+      <NodeField label="Field">
+        <NodeSelect value={field} onChange={(v) => updateData('field', v)} options={fieldOptions} />
+      </NodeField>
+
+      {field === 'header' && (
+        <NodeField label="Header">
+          <NodeInput
+            value={nodeData.headerName || ''}
+            onChange={(v) => updateData('headerName', v)}
+            placeholder="X-Tenant"
+          />
+        </NodeField>
+      )}
+
+      {cases.map((c, index) => (
+        <Flex key={c.id} gap="xs" align="flex-end">
+          <NodeField label="Match">
+            <NodeSelect
+              value={c.match || 'exact'}
+              onChange={(v) => updateCase(index, 'match', v)}
+              options={matchOptions}
+            />
+          </NodeField>
+          <NodeField label="Values">
+            <NodeInput
+              value={c.value}
+              onChange={(v) => updateCase(index, 'value', v)}
+              placeholder={c.match === 'cidr' ? '10.0.0.0/8' : c.match === 'prefix' ? '/api/' : 'DE, FR, NL'}
+            />
+          </NodeField>
+          <ActionIcon
+            onClick={() => removeCase(index)}
+            title="Remove case"
+            variant="subtle"
+            size="xs"
+            className="nodrag nopan"
+          >
+            <IconClose width={12} height={12} />
+          </ActionIcon>
+        </Flex>
+      ))}
+
+      <Button
+        variant="outline"
+        size="sm"
+        onClick={addCase}
+        leftSection={<IconAdd width={14} height={14} />}
+        className="nodrag nopan"
+      >
+        Add Case
+      </Button>
+    </NodeBase>
+  )
+}
@@ -25,9 +25,20 @@ const fieldOptions = [
   { value: 'body', label: 'Request Body' },
   { value: 'userAgent', label: 'User Agent' },
   { value: 'header', label: 'Header' },
-  { value: 'cookie', label: 'Cookie' },
+  { value: 'cookie', label: 'Cookie Header' },
+  { value: 'queryParam:', label: 'Query Parameter' },
+  { value: 'cookie:', label: 'Cookie Value' },
+  { value: 'json:', label: 'JSON Body Field' },
+  { value: 'form:', label: 'Form Field' },
 ]
 
+const fieldPlaceholders: Record<string, string> = {
+  'queryParam:': 'q',
+  'cookie:': 'session_id',
+  'json:': '/user/name',
+  'form:': 'username',
+}
+
 export function TransformNode({ id, data, selected }: NodeProps) {
   const nodeData = data as TransformNodeData
   const { setNodes } = useReactFlow()
@@ -44,6 +55,8 @@ export function TransformNode({ id, data, selected }: NodeProps) {
 
   const operation = nodeData.operation || 'lowercase'
   const field = nodeData.field || 'path'
+  // Query parameters, cookies and body fields are written as '<kind>:<name>'
+  const fieldKind = field.includes(':') ? field.slice(0, field.indexOf(':') + 1) : field
 
   // Dynamic title
   const titles: Record<string, string> = {
@@ -81,12 +94,22 @@ export function TransformNode({ id, data, selected }: NodeProps) {
 
       <NodeField label="Field">
         <NodeSelect
-          value={field}
+          value={fieldKind}
           onChange={(v) => updateData('field', v)}
           options={fieldOptions}
         />
       </NodeField>
 
+      {fieldKind.endsWith(':') && (
+        <NodeField label="Name">
+          <NodeInput
+            value={field.slice(fieldKind.length)}
+            onChange={(v) => updateData('field', `${fieldKind}${v}`)}
+            placeholder={fieldPlaceholders[fieldKind]}
+          />
+        </NodeField>
+      )}
+
       {operation === 'extract' && (
         <NodeField label="Pattern">
           <NodeInput
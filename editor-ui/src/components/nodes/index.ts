@@ -1,11 +1,17 @@
 export { ConditionNode } from './ConditionNode'
 export { ActionNode } from './ActionNode'
 export { RequestNode } from './RequestNode'
+export { ResponseNode } from './ResponseNode'
 export { RateLimitNode } from './RateLimitNode'
 export { TransformNode } from './TransformNode'
 export { BackendNode } from './BackendNode'
+export { BackendPoolNode } from './BackendPoolNode'
 export { LoggingNode } from './LoggingNode'
 export { RuleGroupNode } from './RuleGroupNode'
+export { RuleListNode } from './RuleListNode'
 export { HeaderNode } from './HeaderNode'
 export { CacheNode } from './CacheNode'
+export { RespondNode } from './RespondNode'
+export { SplitNode } from './SplitNode'
+export { SwitchNode } from './SwitchNode'
 export { NodeBase } from './NodeBase'
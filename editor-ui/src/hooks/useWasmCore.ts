@@ -1,9 +1,32 @@
 import { useState, useEffect } from 'react'
 import type { Node, Edge } from '@xyflow/react'
+import { toCanonicalGraph } from '../types/graph'
 
 // We'll dynamically import the WASM module
 let wasmModule: typeof import('cc-core') | null = null
 
+// A problem reported by cc-core's graph validator (same checks as the edge)
+export interface GraphDiagnostic {
+  severity: 'error' | 'warning'
+  code: string
+  nodeId?: string
+  edgeId?: string
+  message: string
+}
+
+// The editor graph in the format that is deployed to the edge
+function toPayload(nodes: Node[], edges: Edge[]) {
+  const graph = toCanonicalGraph(nodes, edges)
+  return {
+    nodes: graph.nodes,
+    edges: graph.edges.map((edge) => ({
+      ...edge,
+      sourceHandle: edge.sourceHandle ?? null,
+      targetHandle: edge.targetHandle ?? null,
+    })),
+  }
+}
+
 export function useWasmCore() {
   const [isLoaded, setIsLoaded] = useState(false)
   const [error, setError] = useState<string | null>(null)
@@ -20,69 +43,18 @@ export function useWasmCore() {
     loadWasm()
   }, [])
 
+  // The editor graph is converted by cc-core itself, the same way the edge
+  // converts it, so previews and deployed rules behave identically
   const convertToWasmGraph = (nodes: Node[], edges: Edge[]) => {
     if (!wasmModule) throw new Error('WASM not loaded')
+    return wasmModule.WasmGraph.fromPayload(JSON.stringify(toPayload(nodes, edges)))
+  }
 
-    const graph = new wasmModule.WasmGraph('rule')
-
-    // Map React Flow node IDs to WASM node IDs
-    const idMap = new Map<string, number>()
-
-    // Add nodes
-    for (const node of nodes) {
-      let kindJson: string
-
-      switch (node.type) {
-        case 'condition': {
-          const data = node.data as { field: string; operator: string; value: string }
-          const fieldJson = JSON.stringify({ type: 'String', value: data.field })
-          const opJson = JSON.stringify({ type: 'String', operator: data.operator })
-          const valueJson = JSON.stringify({ type: 'String', value: data.value })
-          kindJson = wasmModule.createConditionNode(fieldJson, opJson, valueJson)
-          break
-        }
-        case 'logic': {
-          const data = node.data as { operation: string }
-          if (data.operation === 'AND') {
-            kindJson = wasmModule.createAndNode(2)
-          } else if (data.operation === 'OR') {
-            kindJson = wasmModule.createOrNode(2)
-          } else {
-            kindJson = wasmModule.createNotNode()
-          }
-          break
-        }
-        case 'action': {
-          const data = node.data as { action: string; statusCode?: number; message?: string }
-          if (data.action === 'block') {
-            kindJson = wasmModule.createBlockNode(data.statusCode || 403, data.message || 'Blocked')
-          } else if (data.action === 'allow') {
-            kindJson = wasmModule.createAllowNode()
-          } else if (data.action === 'challenge') {
-            kindJson = wasmModule.createChallengeNode('javascript')
-          } else {
-            kindJson = wasmModule.createAllowNode() // log = allow with logging
-          }
-          break
-        }
-        default:
-          continue
-      }
-
-      const wasmId = graph.addNodeByKind(kindJson, node.position.x, node.position.y)
-      idMap.set(node.id, wasmId)
-    }
-
-    // Add edges
-    for (const edge of edges) {
-      const fromId = idMap.get(edge.source)
-      const toId = idMap.get(edge.target)
-      if (fromId !== undefined && toId !== undefined) {
-        graph.connect(fromId, 0, toId, 0)
-      }
-    }
-
-    return graph
+  // Diagnostics for the graph; deploys should be blocked on any 'error'
+  const validateGraph = (nodes: Node[], edges: Edge[]): GraphDiagnostic[] => {
+    if (!wasmModule) throw new Error('WASM not loaded')
+    const now = Math.floor(Date.now() / 1000)
+    return JSON.parse(wasmModule.validatePayload(JSON.stringify(toPayload(nodes, edges)), now))
   }
 
   const executeRules = (nodes: Node[], edges: Edge[], requestContext: object) => {
@@ -107,5 +79,6 @@ export function useWasmCore() {
     error,
     executeRules,
     exportToJson,
+    validateGraph,
   }
 }
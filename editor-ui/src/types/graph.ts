@@ -29,6 +29,8 @@ export interface CanonicalEdge {
   target: string
   sourceHandle?: string | null
   targetHandle?: string | null
+  // Execution order among edges leaving the same node (lowest first)
+  priority?: number
 }
 
 /**
@@ -57,12 +59,14 @@ export function toCanonicalNode(node: Node): CanonicalNode {
  * Strips runtime fields.
  */
 export function toCanonicalEdge(edge: Edge): CanonicalEdge {
+  const { priority } = edge as Edge & { priority?: number }
   return {
     id: edge.id,
     source: edge.source,
     target: edge.target,
     sourceHandle: edge.sourceHandle,
     targetHandle: edge.targetHandle,
+    ...(priority !== undefined ? { priority } : {}),
   }
 }
 
@@ -176,6 +176,7 @@ async function makeRequest(path, options = {}) {
   const response = await fetch(url, {
     method: options.method || 'GET',
     headers: options.headers || {},
+    body: options.body,
     redirect: 'manual',  // Don't follow redirects - we want to see the 3xx response
   })
 
@@ -1810,6 +1811,21 @@ async function main() {
     },
   ])
 
+  // Test 22: Request bodies are forwarded to the routed backend
+  await runTest('Backend routing - POST body forwarded', simpleRoutingGraph, [
+    {
+      path: '/post',
+      options: {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ marker: 'cc-body-forwarded' }),
+      },
+      expectHeader: ['x-vce-action', 'routed:httpbin'],
+      // httpbin echoes the body back; only reachable when deployed
+      ...(useDeployed ? { expectBodyContains: 'cc-body-forwarded' } : {}),
+    },
+  ])
+
   // Summary
   console.log('')
   console.log('============================')